@@ -0,0 +1,91 @@
+#[macro_use]
+extern crate bencher;
+extern crate lust;
+
+use std::env;
+use std::fs::File;
+use std::io::Write;
+use bencher::Bencher;
+use lust::{Lexer, Parser, State, Node};
+
+/// A program of `n` small definitions and calls, standing in for a big `.ls` file.
+fn big_source(n: usize) -> String {
+    let mut src = String::new();
+    for i in 0..n {
+        src.push_str(&format!(";; definition {}\n\
+                               (defn f{} [a b & more]\n  \
+                                 \"Adds its args.\"\n  \
+                                 (if (> a {}) (+ a b) `(:skip ~a ~@more)))\n\
+                               (def v{} [1 2.5 \"str\" :kw {{:k {}}} \\c])\n", i, i, i, i, i));
+    }
+    src
+}
+
+fn parse(src: &str) -> Vec<Node> {
+    Parser::new(src.chars()).map(|e| e.ok().unwrap()).collect()
+}
+
+fn eval_all(state: &mut State, exprs: &[Node]) -> Node {
+    let mut last = None;
+    for e in exprs {
+        last = Some(state.eval(e).unwrap_or_else(|e| panic!("{:?}", e)));
+    }
+    last.unwrap()
+}
+
+fn lex_big_file(b: &mut Bencher) {
+    let src = big_source(500);
+    b.bytes = src.len() as u64;
+    b.iter(|| Lexer::new(src.chars()).count());
+}
+
+fn parse_big_file(b: &mut Bencher) {
+    let src = big_source(500);
+    b.bytes = src.len() as u64;
+    b.iter(|| Parser::new(src.chars()).count());
+}
+
+fn eval_fib(b: &mut Bencher) {
+    let ref mut state = State::new("user".to_string());
+    eval_all(state, &parse("(defn fib [n] (if (< n 2) n (+ (fib (- n 1)) (fib (- n 2)))))"));
+    let call = parse("(fib 15)");
+    b.iter(|| eval_all(state, &call));
+}
+
+fn eval_list_building(b: &mut Bencher) {
+    let ref mut state = State::new("user".to_string());
+    eval_all(state, &parse("(defn build [n acc] (if (= n 0) acc (build (- n 1) `(~n ~@acc))))"));
+    let call = parse("(build 200 '())");
+    b.iter(|| eval_all(state, &call));
+}
+
+fn eval_macro_heavy(b: &mut Bencher) {
+    let ref mut state = State::new("user".to_string());
+    eval_all(state, &parse("(defmacro unless [t e] `(if ~t 0 ~e))
+                            (defmacro twice [v] `(let [x# ~v] (+ x# x#)))
+                            (defmacro calc [a b] `(unless (> ~a ~b) (twice (unless (< ~a 0) ~b))))"));
+    let mut src = String::from("(defn g [a b] (+ ");
+    for _ in 0..50 {
+        src.push_str("(calc a (calc b a)) ");
+    }
+    src.push_str("))\n(g 1 2)");
+    let forms = parse(&src);
+    b.iter(|| eval_all(state, &forms));
+}
+
+fn eval_ns_loading(b: &mut Bencher) {
+    let path = env::temp_dir().join("lust_bench_big_ns.ls");
+    let mut file = File::create(&path).unwrap();
+    file.write_all(b"(in-ns 'bench.big)\n").unwrap();
+    file.write_all(big_source(200).as_bytes()).unwrap();
+    let load = parse(&format!("(load {:?})", path.to_str().unwrap()));
+    b.iter(|| {
+        let ref mut state = State::new("user".to_string());
+        eval_all(state, &load)
+    });
+}
+
+benchmark_group!(lexer, lex_big_file);
+benchmark_group!(parser, parse_big_file);
+benchmark_group!(eval, eval_fib, eval_list_building, eval_macro_heavy, eval_ns_loading);
+benchmark_main!(lexer, parser, eval);
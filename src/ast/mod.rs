@@ -4,29 +4,43 @@ pub mod nodes;
 
 use std::fmt;
 
-#[derive(Debug, PartialEq, Clone)]
+/// Values compare and hash structurally, ignoring metadata. Values of different
+/// types are ordered by the declaration order of their variants.
+#[derive(Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Clone)]
 pub enum Node {
+    Nil,
     Number(nodes::Number),
     Bool(nodes::Bool),
     String(nodes::String),
+    Char(nodes::Char),
+    Inst(nodes::Inst),
+    Regex(nodes::Regex),
     Symbol(nodes::Symbol),
     Keyword(nodes::Symbol),
     Alias(nodes::Symbol),
     List(nodes::Vector),
     Vector(nodes::Vector),
+    Map(nodes::Map),
+    Set(nodes::Set),
+    Var(nodes::Symbol),
     Let(nodes::Let),
+    Case(nodes::Case),
     Fn(nodes::Fn),
     Macro(nodes::Fn),
+    Builtin(nodes::Symbol),
+    Preds(nodes::Preds),
     Def(nodes::Def),
     Call(nodes::Call),
+    Stream(nodes::Stream),
 }
 
 impl Node {
+    /// Truthiness of the node: only `nil` and `false` are falsey.
     pub fn as_bool(&self) -> bool {
-        if let Node::Bool(ref b) = *self {
-            b.value()
-        } else {
-            true
+        match *self {
+            Node::Nil => false,
+            Node::Bool(ref b) => b.value(),
+            _ => true,
         }
     }
 
@@ -46,9 +60,36 @@ impl Node {
         }
     }
 
+    pub fn meta(&self) -> Option<&nodes::Map> {
+        match *self {
+            Node::Symbol(ref s) => s.meta(),
+            Node::List(ref l) | Node::Vector(ref l) => l.meta(),
+            Node::Map(ref m) => m.meta(),
+            Node::Set(ref s) => s.meta(),
+            Node::Fn(ref f) | Node::Macro(ref f) => f.meta(),
+            Node::Def(ref d) => d.meta(),
+            _ => None,
+        }
+    }
+
+    /// Returns a copy of the node carrying `meta`, or `None` if the node can't hold metadata.
+    pub fn with_meta(&self, meta: Option<nodes::Map>) -> Option<Node> {
+        match *self {
+            Node::Symbol(ref s) => Some(Node::Symbol(s.clone().with_meta(meta))),
+            Node::List(ref l) => Some(Node::List(l.clone().with_meta(meta))),
+            Node::Vector(ref v) => Some(Node::Vector(v.clone().with_meta(meta))),
+            Node::Map(ref m) => Some(Node::Map(m.clone().with_meta(meta))),
+            Node::Set(ref s) => Some(Node::Set(s.clone().with_meta(meta))),
+            Node::Fn(ref f) => Some(Node::Fn(f.clone().with_meta(meta))),
+            Node::Macro(ref f) => Some(Node::Macro(f.clone().with_meta(meta))),
+            Node::Def(ref d) => Some(Node::Def(d.clone().with_meta(meta))),
+            _ => None,
+        }
+    }
+
     pub fn is_call_of(&self, name: &str) -> bool {
         if let Node::Call(ref c) = *self {
-            &c.symbol().name()[..] == name
+            c.symbol().map_or(false, |s| &s.name()[..] == name)
         } else {
             false
         }
@@ -58,6 +99,9 @@ impl Node {
 impl fmt::Display for Node {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
+            Node::Nil => {
+                write!(f, "nil")
+            },
             Node::Number(ref n) => {
                 write!(f, "{}", n)
             },
@@ -76,27 +120,57 @@ impl fmt::Display for Node {
             Node::String(ref s) => {
                 write!(f, r#""{}""#, s)
             },
+            Node::Char(ref c) => {
+                write!(f, "{}", c)
+            },
+            Node::Inst(ref i) => {
+                write!(f, "{}", i)
+            },
+            Node::Regex(ref r) => {
+                write!(f, "{}", r)
+            },
             Node::List(ref l) => {
                 write!(f, "({})", l)
             },
             Node::Vector(ref v) => {
                 write!(f, "[{}]", v)
             },
+            Node::Map(ref m) => {
+                write!(f, "{}", m)
+            },
+            Node::Set(ref s) => {
+                write!(f, "{}", s)
+            },
+            Node::Var(ref s) => {
+                write!(f, "#'{}", s)
+            },
             Node::Def(ref d) => {
                 write!(f, "{}", d)
             },
             Node::Let(ref l) => {
                 write!(f, "{}", l)
             },
+            Node::Case(ref c) => {
+                write!(f, "{}", c)
+            },
             Node::Fn(ref fn_node) => {
                 write!(f, "(fn {})", fn_node)
             },
             Node::Macro(ref fn_node) => {
                 write!(f, "(macro {})", fn_node)
             },
+            Node::Builtin(ref s) => {
+                write!(f, "#<builtin {}>", s)
+            },
+            Node::Preds(ref p) => {
+                write!(f, "{}", p)
+            },
             Node::Call(ref c) => {
                 write!(f, "{}", c)
             },
+            Node::Stream(ref s) => {
+                write!(f, "{}", s)
+            },
         }
     }
 }
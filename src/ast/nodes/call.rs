@@ -3,22 +3,33 @@ use ast::Node;
 use ast::nodes::Symbol;
 use utils::format_vec;
 
-#[derive(Debug, PartialEq, Clone)]
+/// Application of a callee form to argument forms. The callee is usually a symbol
+/// naming a builtin or a global, but may be any form evaluating to a callable value.
+#[derive(Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Clone)]
 pub struct Call {
-    symbol: Symbol,
+    callee: Box<Node>,
     args: Vec<Node>,
 }
 
 impl Call {
-    pub fn new(symbol: Symbol, args: Vec<Node>) -> Call {
+    pub fn new(callee: Node, args: Vec<Node>) -> Call {
         Call {
-            symbol: symbol,
+            callee: Box::new(callee),
             args: args,
         }
     }
 
-    pub fn symbol(&self) -> &Symbol {
-        &self.symbol
+    pub fn callee(&self) -> &Node {
+        &self.callee
+    }
+
+    /// The callee when it is a symbol.
+    pub fn symbol(&self) -> Option<&Symbol> {
+        if let Node::Symbol(ref s) = *self.callee {
+            Some(s)
+        } else {
+            None
+        }
     }
 
     pub fn args(&self) -> &Vec<Node> {
@@ -29,7 +40,7 @@ impl Call {
 impl fmt::Display for Call {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let mut a = String::new();
-        a.push_str(&format!("{}", self.symbol));
+        a.push_str(&format!("{}", self.callee));
         if !self.args.is_empty() {
             a.push_str(&format!(" {}", format_vec(&self.args[..])))
         }
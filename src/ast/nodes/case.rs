@@ -0,0 +1,100 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::rc::Rc;
+use ast::Node;
+
+/// Dispatch of a value on clauses of literal constants. The constants are hashed once
+/// when the form is built, so finding the clause doesn't depend on their number.
+#[derive(Debug, Clone)]
+pub struct Case {
+    expr: Box<Node>,
+    clauses: Vec<(Node, Node)>,
+    default: Option<Box<Node>>,
+    table: Rc<HashMap<Node, usize>>,
+}
+
+impl Case {
+    /// Each clause pairs a constant, or a list of alternative constants, with its result.
+    /// Fails with the first constant repeated across the clauses.
+    pub fn new(expr: Node, clauses: Vec<(Node, Node)>, default: Option<Node>) -> Result<Case, Node> {
+        let mut table = HashMap::new();
+        for (i, &(ref test, _)) in clauses.iter().enumerate() {
+            let alternatives = match *test {
+                Node::List(ref l) => l.to_vec(),
+                _ => vec![test.clone()],
+            };
+            for c in alternatives {
+                if table.contains_key(&c) {
+                    return Err(c)
+                }
+                table.insert(c, i);
+            }
+        }
+        Ok(Case {
+            expr: Box::new(expr),
+            clauses: clauses,
+            default: default.map(Box::new),
+            table: Rc::new(table),
+        })
+    }
+
+    pub fn expr(&self) -> &Node {
+        &self.expr
+    }
+
+    pub fn clauses(&self) -> &Vec<(Node, Node)> {
+        &self.clauses
+    }
+
+    pub fn default(&self) -> Option<&Node> {
+        self.default.as_ref().map(|d| &**d)
+    }
+
+    /// Index of the clause matching `value`.
+    pub fn branch(&self, value: &Node) -> Option<usize> {
+        self.table.get(value).cloned()
+    }
+}
+
+impl PartialEq for Case {
+    fn eq(&self, other: &Case) -> bool {
+        self.expr == other.expr && self.clauses == other.clauses && self.default == other.default
+    }
+}
+
+impl Eq for Case {}
+
+impl Hash for Case {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.expr.hash(state);
+        self.clauses.hash(state);
+        self.default.hash(state)
+    }
+}
+
+impl PartialOrd for Case {
+    fn partial_cmp(&self, other: &Case) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Case {
+    fn cmp(&self, other: &Case) -> Ordering {
+        (&self.expr, &self.clauses, &self.default).cmp(&(&other.expr, &other.clauses, &other.default))
+    }
+}
+
+impl fmt::Display for Case {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        try!(write!(f, "(case {}", self.expr));
+        for &(ref test, ref result) in &self.clauses {
+            try!(write!(f, " {} {}", test, result));
+        }
+        if let Some(ref default) = self.default {
+            try!(write!(f, " {}", default));
+        }
+        write!(f, ")")
+    }
+}
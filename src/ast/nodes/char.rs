@@ -0,0 +1,30 @@
+use std::fmt;
+
+#[derive(Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Clone)]
+pub struct Char {
+    value: char,
+}
+
+impl Char {
+    pub fn new(value: char) -> Char {
+        Char { value: value }
+    }
+
+    pub fn value(&self) -> char {
+        self.value
+    }
+}
+
+impl fmt::Display for Char {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.value {
+            '\n' => write!(f, "\\newline"),
+            ' ' => write!(f, "\\space"),
+            '\t' => write!(f, "\\tab"),
+            '\r' => write!(f, "\\return"),
+            '\u{8}' => write!(f, "\\backspace"),
+            '\u{c}' => write!(f, "\\formfeed"),
+            c => write!(f, "\\{}", c),
+        }
+    }
+}
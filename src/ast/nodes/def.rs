@@ -1,11 +1,15 @@
+use std::cmp::Ordering;
 use std::fmt;
+use std::hash::{Hash, Hasher};
 use ast::Node;
-use ast::nodes::Symbol;
+use ast::nodes::{Symbol, Map};
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, Clone)]
 pub struct Def {
     symbol: Symbol,
     expr: Box<Node>,
+    meta: Option<Box<Map>>,
+    source: Option<Box<Node>>,
 }
 
 impl Def {
@@ -13,6 +17,8 @@ impl Def {
         Def {
             symbol: symbol,
             expr: Box::new(expr),
+            meta: None,
+            source: None,
         }
     }
 
@@ -23,6 +29,52 @@ impl Def {
     pub fn expr(&self) -> &Node {
         &self.expr
     }
+
+    pub fn meta(&self) -> Option<&Map> {
+        self.meta.as_ref().map(|m| &**m)
+    }
+
+    pub fn with_meta(mut self, meta: Option<Map>) -> Def {
+        self.meta = meta.map(Box::new);
+        self
+    }
+
+    /// The form the definition was read from, kept for `source`.
+    pub fn source(&self) -> Option<&Node> {
+        self.source.as_ref().map(|s| &**s)
+    }
+
+    pub fn with_source(mut self, source: Node) -> Def {
+        self.source = Some(Box::new(source));
+        self
+    }
+}
+
+impl PartialEq for Def {
+    fn eq(&self, other: &Def) -> bool {
+        self.symbol == other.symbol && self.expr == other.expr
+    }
+}
+
+impl Eq for Def {}
+
+impl Hash for Def {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.symbol.hash(state);
+        self.expr.hash(state)
+    }
+}
+
+impl PartialOrd for Def {
+    fn partial_cmp(&self, other: &Def) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Def {
+    fn cmp(&self, other: &Def) -> Ordering {
+        (&self.symbol, &self.expr).cmp(&(&other.symbol, &other.expr))
+    }
 }
 
 impl fmt::Display for Def {
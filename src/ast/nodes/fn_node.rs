@@ -1,18 +1,29 @@
+use std::cmp::Ordering;
 use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::rc::Rc;
 use ast::Node;
+use ast::nodes::Map;
+use chunk::Chunk;
 use utils::format_vec;
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, Clone)]
 pub struct Fn {
-    params: Vec<Node>,
-    body: Vec<Node>,
+    params: Rc<Vec<Node>>,
+    body: Rc<Vec<Node>>,
+    meta: Option<Box<Map>>,
+    code: Option<Rc<Chunk>>,
+    captures: Rc<Vec<Node>>,
 }
 
 impl Fn {
     pub fn new(params: Vec<Node>, body: Vec<Node>) -> Fn {
         Fn {
-            params: params,
-            body: body,
+            params: Rc::new(params),
+            body: Rc::new(body),
+            meta: None,
+            code: None,
+            captures: Rc::new(vec![]),
         }
     }
 
@@ -23,6 +34,71 @@ impl Fn {
     pub fn body(&self) -> &Vec<Node> {
         &self.body
     }
+
+    /// Whether both were created by the same evaluation of a fn form.
+    pub fn ptr_eq(&self, other: &Fn) -> bool {
+        Rc::ptr_eq(&self.body, &other.body) && Rc::ptr_eq(&self.captures, &other.captures)
+    }
+
+    pub fn meta(&self) -> Option<&Map> {
+        self.meta.as_ref().map(|m| &**m)
+    }
+
+    pub fn with_meta(mut self, meta: Option<Map>) -> Fn {
+        self.meta = meta.map(Box::new);
+        self
+    }
+
+    /// Bytecode compiled from the body, when the fn was created by the VM.
+    pub fn code(&self) -> Option<&Rc<Chunk>> {
+        self.code.as_ref()
+    }
+
+    pub fn with_code(mut self, code: Rc<Chunk>) -> Fn {
+        self.code = Some(code);
+        self
+    }
+
+    /// Values of the enclosing locals the body refers to, captured when the fn was created.
+    pub fn captures(&self) -> &Rc<Vec<Node>> {
+        &self.captures
+    }
+
+    pub fn with_captures(mut self, captures: Vec<Node>) -> Fn {
+        self.captures = Rc::new(captures);
+        self
+    }
+}
+
+/// Fns made by the same form are equal only if they captured equal values, so that closures
+/// over different locals tell apart.
+impl PartialEq for Fn {
+    fn eq(&self, other: &Fn) -> bool {
+        self.params == other.params && self.body == other.body && self.captures == other.captures
+    }
+}
+
+impl Eq for Fn {}
+
+impl Hash for Fn {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.params.hash(state);
+        self.body.hash(state);
+        self.captures.hash(state)
+    }
+}
+
+impl PartialOrd for Fn {
+    fn partial_cmp(&self, other: &Fn) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Fn {
+    fn cmp(&self, other: &Fn) -> Ordering {
+        (&self.params, &self.body, &self.captures)
+            .cmp(&(&other.params, &other.body, &other.captures))
+    }
 }
 
 impl fmt::Display for Fn {
@@ -0,0 +1,224 @@
+use std::fmt;
+use std::fmt::Write;
+
+macro_rules! try_opt {
+    ($e:expr) => (match $e { Some(v) => v, None => return None })
+}
+
+const MS_PER_DAY: i64 = 86_400_000;
+
+const WEEKDAYS: [&'static str; 7] = ["Sunday", "Monday", "Tuesday", "Wednesday",
+                                     "Thursday", "Friday", "Saturday"];
+
+const MONTHS: [&'static str; 12] = ["January", "February", "March", "April", "May", "June", "July",
+                                    "August", "September", "October", "November", "December"];
+
+fn div_floor(a: i64, b: i64) -> i64 {
+    let q = a / b;
+    if a % b < 0 { q - 1 } else { q }
+}
+
+fn mod_floor(a: i64, b: i64) -> i64 {
+    a - div_floor(a, b) * b
+}
+
+fn is_leap_year(year: i64) -> bool {
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Days since 1970-01-01 of a proleptic Gregorian date.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = div_floor(year, 400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Year, month and day of the date `days` after 1970-01-01.
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = div_floor(days, 146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 -
+                       day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    (year_of_era + era * 400 + if month <= 2 { 1 } else { 0 }, month, day)
+}
+
+/// Reads ISO-8601 text from left to right.
+struct Scanner<'a> {
+    chars: ::std::iter::Peekable<::std::str::Chars<'a>>,
+}
+
+impl<'a> Scanner<'a> {
+    /// Reads exactly `n` digits.
+    fn digits(&mut self, n: usize) -> Option<i64> {
+        let mut value = 0;
+        for _ in 0..n {
+            match self.chars.next().and_then(|c| c.to_digit(10)) {
+                Some(d) => value = value * 10 + d as i64,
+                None => return None,
+            }
+        }
+        Some(value)
+    }
+
+    /// Consumes the next char if it is one of `cs`.
+    fn eat(&mut self, cs: &str) -> Option<char> {
+        match self.chars.peek() {
+            Some(&c) if cs.contains(c) => {
+                self.chars.next();
+                Some(c)
+            },
+            _ => None,
+        }
+    }
+
+    fn is_done(&mut self) -> bool {
+        self.chars.peek().is_none()
+    }
+}
+
+/// A point in time, held as milliseconds since 1970-01-01T00:00:00Z.
+#[derive(Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Clone, Copy)]
+pub struct Inst {
+    millis: i64,
+}
+
+impl Inst {
+    pub fn new(millis: i64) -> Inst {
+        Inst { millis: millis }
+    }
+
+    pub fn millis(&self) -> i64 {
+        self.millis
+    }
+
+    /// Parses an ISO-8601 timestamp like `2026-10-17T12:00:00.5+02:00`.
+    ///
+    /// Everything after the year is optional, `T` may be a space, and a missing offset means UTC.
+    pub fn parse(s: &str) -> Option<Inst> {
+        let mut s = Scanner { chars: s.chars().peekable() };
+        let year = try_opt!(s.digits(4));
+        let (mut month, mut day) = (1, 1);
+        let (mut hour, mut minute, mut second, mut millis, mut offset) = (0, 0, 0, 0, 0);
+        if s.eat("-").is_some() {
+            month = try_opt!(s.digits(2));
+            if s.eat("-").is_some() {
+                day = try_opt!(s.digits(2));
+                if s.eat("Tt ").is_some() {
+                    hour = try_opt!(s.digits(2));
+                    try_opt!(s.eat(":"));
+                    minute = try_opt!(s.digits(2));
+                    if s.eat(":").is_some() {
+                        second = try_opt!(s.digits(2));
+                        if s.eat(".,").is_some() {
+                            let mut scale = 100;
+                            try_opt!(s.chars.peek().and_then(|c| c.to_digit(10)));
+                            while let Some(d) = s.chars.peek().and_then(|c| c.to_digit(10)) {
+                                s.chars.next();
+                                millis += d as i64 * scale;
+                                scale /= 10;
+                            }
+                        }
+                    }
+                    if s.eat("Zz").is_none() {
+                        if let Some(sign) = s.eat("+-") {
+                            let hours = try_opt!(s.digits(2));
+                            s.eat(":");
+                            let minutes = if s.is_done() { 0 } else { try_opt!(s.digits(2)) };
+                            if hours > 23 || minutes > 59 {
+                                return None
+                            }
+                            offset = (hours * 60 + minutes) * if sign == '-' { -1 } else { 1 };
+                        }
+                    }
+                }
+            }
+        }
+        if !s.is_done() || month < 1 || month > 12 || day < 1 || day > days_in_month(year, month) ||
+           hour > 23 || minute > 59 || second > 59 {
+            return None
+        }
+        let days = days_from_civil(year, month, day);
+        let seconds = hour * 3600 + (minute - offset) * 60 + second;
+        Some(Inst::new(days * MS_PER_DAY + seconds * 1000 + millis))
+    }
+
+    /// Formats the instant in UTC with `strftime`-style directives, `%f` giving milliseconds.
+    ///
+    /// Returns `None` on an unknown directive.
+    pub fn format(&self, format: &str) -> Option<String> {
+        let days = div_floor(self.millis, MS_PER_DAY);
+        let ms_of_day = self.millis - days * MS_PER_DAY;
+        let (year, month, day) = civil_from_days(days);
+        let (hour, minute, second) = (ms_of_day / 3_600_000, ms_of_day / 60_000 % 60,
+                                      ms_of_day / 1000 % 60);
+        let weekday = mod_floor(days + 4, 7);
+        let day_of_year = days - days_from_civil(year, 1, 1) + 1;
+        let hour12 = if hour % 12 == 0 { 12 } else { hour % 12 };
+
+        let mut s = String::new();
+        let mut chars = format.chars();
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                s.push(c);
+                continue
+            }
+            let _ = match try_opt!(chars.next()) {
+                'Y' => write!(s, "{:04}", year),
+                'y' => write!(s, "{:02}", mod_floor(year, 100)),
+                'C' => write!(s, "{:02}", div_floor(year, 100)),
+                'm' => write!(s, "{:02}", month),
+                'd' => write!(s, "{:02}", day),
+                'e' => write!(s, "{:2}", day),
+                'j' => write!(s, "{:03}", day_of_year),
+                'H' => write!(s, "{:02}", hour),
+                'I' => write!(s, "{:02}", hour12),
+                'M' => write!(s, "{:02}", minute),
+                'S' => write!(s, "{:02}", second),
+                'f' => write!(s, "{:03}", ms_of_day % 1000),
+                'p' => write!(s, "{}", if hour < 12 { "AM" } else { "PM" }),
+                'a' => write!(s, "{}", &WEEKDAYS[weekday as usize][..3]),
+                'A' => write!(s, "{}", WEEKDAYS[weekday as usize]),
+                'u' => write!(s, "{}", if weekday == 0 { 7 } else { weekday }),
+                'w' => write!(s, "{}", weekday),
+                'b' | 'h' => write!(s, "{}", &MONTHS[month as usize - 1][..3]),
+                'B' => write!(s, "{}", MONTHS[month as usize - 1]),
+                's' => write!(s, "{}", div_floor(self.millis, 1000)),
+                'F' => write!(s, "{:04}-{:02}-{:02}", year, month, day),
+                'T' => write!(s, "{:02}:{:02}:{:02}", hour, minute, second),
+                'R' => write!(s, "{:02}:{:02}", hour, minute),
+                'D' => write!(s, "{:02}/{:02}/{:02}", month, day, mod_floor(year, 100)),
+                'z' => write!(s, "+0000"),
+                'Z' => write!(s, "UTC"),
+                'n' => writeln!(s),
+                't' => write!(s, "\t"),
+                '%' => write!(s, "%"),
+                _ => return None,
+            };
+        }
+        Some(s)
+    }
+}
+
+impl fmt::Display for Inst {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let format = if self.millis % 1000 == 0 { "%FT%TZ" } else { "%FT%T.%fZ" };
+        write!(f, r#"#inst "{}""#, self.format(format).unwrap())
+    }
+}
@@ -2,7 +2,7 @@ use std::fmt;
 use ast::Node;
 use utils::format_vec;
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Clone)]
 pub struct Let {
     bindings: Vec<Node>,
     body: Vec<Node>,
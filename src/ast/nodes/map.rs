@@ -0,0 +1,120 @@
+use std::cmp::Ordering;
+use std::collections::hash_map::DefaultHasher;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use ast::Node;
+
+/// Association of nodes kept in insertion order, used for map literals and metadata.
+#[derive(Debug, Clone)]
+pub struct Map {
+    entries: Vec<(Node, Node)>,
+    meta: Option<Box<Map>>,
+}
+
+impl Map {
+    pub fn new(entries: Vec<(Node, Node)>) -> Map {
+        let mut map = Map {
+            entries: vec![],
+            meta: None,
+        };
+        for (k, v) in entries {
+            map.insert(k, v);
+        }
+        map
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn entries(&self) -> &Vec<(Node, Node)> {
+        &self.entries
+    }
+
+    pub fn get(&self, key: &Node) -> Option<&Node> {
+        self.entries.iter().find(|e| e.0 == *key).map(|e| &e.1)
+    }
+
+    pub fn insert(&mut self, key: Node, value: Node) {
+        if let Some(e) = self.entries.iter_mut().find(|e| e.0 == key) {
+            e.1 = value;
+            return
+        }
+        self.entries.push((key, value))
+    }
+
+    pub fn remove(&mut self, key: &Node) -> Option<Node> {
+        self.entries.iter()
+            .position(|e| e.0 == *key)
+            .map(|i| self.entries.remove(i).1)
+    }
+
+    pub fn merge(&mut self, other: &Map) {
+        for &(ref k, ref v) in &other.entries {
+            self.insert(k.clone(), v.clone())
+        }
+    }
+
+    pub fn meta(&self) -> Option<&Map> {
+        self.meta.as_ref().map(|m| &**m)
+    }
+
+    pub fn with_meta(mut self, meta: Option<Map>) -> Map {
+        self.meta = meta.map(Box::new);
+        self
+    }
+
+    fn sorted_entries(&self) -> Vec<&(Node, Node)> {
+        let mut entries = self.entries.iter().collect::<Vec<_>>();
+        entries.sort();
+        entries
+    }
+}
+
+impl PartialEq for Map {
+    fn eq(&self, other: &Map) -> bool {
+        self.len() == other.len() &&
+            self.entries.iter().all(|&(ref k, ref v)| other.get(k) == Some(v))
+    }
+}
+
+impl Eq for Map {}
+
+/// Independent of the insertion order, like equality.
+impl Hash for Map {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        let mut sum = 0_u64;
+        for e in &self.entries {
+            let mut hasher = DefaultHasher::new();
+            e.hash(&mut hasher);
+            sum = sum.wrapping_add(hasher.finish());
+        }
+        state.write_usize(self.len());
+        state.write_u64(sum)
+    }
+}
+
+impl PartialOrd for Map {
+    fn partial_cmp(&self, other: &Map) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Map {
+    fn cmp(&self, other: &Map) -> Ordering {
+        self.sorted_entries().cmp(&other.sorted_entries())
+    }
+}
+
+impl fmt::Display for Map {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let entries = self.entries.iter()
+            .map(|&(ref k, ref v)| format!("{} {}", k, v))
+            .collect::<Vec<_>>();
+        write!(f, "{{{}}}", entries.join(", "))
+    }
+}
@@ -1,19 +1,35 @@
 mod bool;
 mod call;
+mod case;
+mod char;
 mod def;
 mod fn_node;
+mod inst;
 mod let_node;
+mod map;
 mod number;
+mod preds;
+mod regex;
+mod set;
+mod stream;
 mod string;
 mod symbol;
 mod vector;
 
 pub use self::bool::Bool;
 pub use self::call::Call;
+pub use self::case::Case;
+pub use self::char::Char;
 pub use self::def::Def;
 pub use self::fn_node::Fn;
+pub use self::inst::Inst;
 pub use self::let_node::Let;
+pub use self::map::Map;
 pub use self::number::Number;
+pub use self::preds::Preds;
+pub use self::regex::Regex;
+pub use self::set::Set;
+pub use self::stream::Stream;
 pub use self::symbol::Symbol;
 pub use self::string::String;
 pub use self::vector::Vector;
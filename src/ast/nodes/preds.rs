@@ -0,0 +1,37 @@
+use std::fmt;
+use std::rc::Rc;
+use ast::Node;
+use utils::format_vec;
+
+/// Predicate combining the preds given to `some-fn` or `every-pred`, called on each of its
+/// args in turn.
+#[derive(Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Clone)]
+pub struct Preds {
+    is_every: bool,
+    preds: Rc<Vec<Node>>,
+}
+
+impl Preds {
+    pub fn new(is_every: bool, preds: Vec<Node>) -> Preds {
+        Preds {
+            is_every: is_every,
+            preds: Rc::new(preds),
+        }
+    }
+
+    /// Whether every pred has to hold, as for `every-pred`, rather than any of them.
+    pub fn is_every(&self) -> bool {
+        self.is_every
+    }
+
+    pub fn preds(&self) -> &Vec<Node> {
+        &self.preds
+    }
+}
+
+impl fmt::Display for Preds {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = if self.is_every { "every-pred" } else { "some-fn" };
+        write!(f, "#<{} {}>", name, format_vec(&self.preds[..]))
+    }
+}
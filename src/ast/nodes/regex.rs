@@ -0,0 +1,70 @@
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::rc::Rc;
+use regex;
+
+#[derive(Debug, Clone)]
+pub struct Regex {
+    value: regex::Regex,
+    anchored: Rc<RefCell<Option<regex::Regex>>>,
+}
+
+impl Regex {
+    pub fn new(value: regex::Regex) -> Regex {
+        Regex {
+            value: value,
+            anchored: Rc::new(RefCell::new(None)),
+        }
+    }
+
+    pub fn value(&self) -> &regex::Regex {
+        &self.value
+    }
+
+    /// The pattern matching only the whole of a string, compiled on first use. A verbose
+    /// pattern ending in a comment would comment out the anchor, so a newline closes it first.
+    pub fn anchored(&self) -> Result<regex::Regex, regex::Error> {
+        let mut anchored = self.anchored.borrow_mut();
+        if anchored.is_none() {
+            let pattern = self.value.as_str();
+            let r = regex::Regex::new(&format!("^(?:{})$", pattern))
+                .or_else(|_| regex::Regex::new(&format!("^(?:{}\n)$", pattern)));
+            *anchored = Some(try!(r));
+        }
+        Ok(anchored.as_ref().unwrap().clone())
+    }
+}
+
+impl PartialEq for Regex {
+    fn eq(&self, other: &Regex) -> bool {
+        self.value.as_str() == other.value.as_str()
+    }
+}
+
+impl Eq for Regex {}
+
+impl Hash for Regex {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.value.as_str().hash(state)
+    }
+}
+
+impl PartialOrd for Regex {
+    fn partial_cmp(&self, other: &Regex) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Regex {
+    fn cmp(&self, other: &Regex) -> Ordering {
+        self.value.as_str().cmp(other.value.as_str())
+    }
+}
+
+impl fmt::Display for Regex {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, r#"#"{}""#, self.value.as_str())
+    }
+}
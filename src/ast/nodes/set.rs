@@ -0,0 +1,104 @@
+use std::cmp::Ordering;
+use std::collections::hash_map::DefaultHasher;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use ast::Node;
+use ast::nodes::Map;
+use utils::format_vec;
+
+/// Distinct nodes kept in insertion order, used for set literals.
+#[derive(Debug, Clone)]
+pub struct Set {
+    items: Vec<Node>,
+    meta: Option<Box<Map>>,
+}
+
+impl Set {
+    pub fn new(items: Vec<Node>) -> Set {
+        let mut set = Set {
+            items: vec![],
+            meta: None,
+        };
+        for item in items {
+            set.insert(item);
+        }
+        set
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn items(&self) -> &Vec<Node> {
+        &self.items
+    }
+
+    pub fn get(&self, item: &Node) -> Option<&Node> {
+        self.items.iter().find(|i| *i == item)
+    }
+
+    pub fn insert(&mut self, item: Node) {
+        if self.get(&item).is_none() {
+            self.items.push(item)
+        }
+    }
+
+    pub fn meta(&self) -> Option<&Map> {
+        self.meta.as_ref().map(|m| &**m)
+    }
+
+    pub fn with_meta(mut self, meta: Option<Map>) -> Set {
+        self.meta = meta.map(Box::new);
+        self
+    }
+
+    fn sorted_items(&self) -> Vec<&Node> {
+        let mut items = self.items.iter().collect::<Vec<_>>();
+        items.sort();
+        items
+    }
+}
+
+impl PartialEq for Set {
+    fn eq(&self, other: &Set) -> bool {
+        self.len() == other.len() && self.items.iter().all(|i| other.get(i).is_some())
+    }
+}
+
+impl Eq for Set {}
+
+/// Independent of the insertion order, like equality.
+impl Hash for Set {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        let mut sum = 0_u64;
+        for i in &self.items {
+            let mut hasher = DefaultHasher::new();
+            i.hash(&mut hasher);
+            sum = sum.wrapping_add(hasher.finish());
+        }
+        state.write_usize(self.len());
+        state.write_u64(sum)
+    }
+}
+
+impl PartialOrd for Set {
+    fn partial_cmp(&self, other: &Set) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Set {
+    fn cmp(&self, other: &Set) -> Ordering {
+        self.sorted_items().cmp(&other.sorted_items())
+    }
+}
+
+impl fmt::Display for Set {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "#{{{}}}", format_vec(&self.items[..]))
+    }
+}
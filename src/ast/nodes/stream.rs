@@ -0,0 +1,24 @@
+use std::fmt;
+
+#[derive(Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Clone, Copy)]
+pub enum Stream {
+    In,
+    Out,
+    Err,
+}
+
+impl Stream {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Stream::In => "*in*",
+            Stream::Out => "*out*",
+            Stream::Err => "*err*",
+        }
+    }
+}
+
+impl fmt::Display for Stream {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "#<stream {}>", self.name())
+    }
+}
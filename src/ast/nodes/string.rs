@@ -1,7 +1,7 @@
 use std::fmt;
 use std::string;
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Clone)]
 pub struct String {
     value: string::String
 }
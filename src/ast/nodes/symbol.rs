@@ -1,9 +1,13 @@
+use std::cmp::Ordering;
 use std::fmt;
+use std::hash::{Hash, Hasher};
+use ast::nodes::Map;
 
-#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+#[derive(Debug, Clone)]
 pub struct Symbol {
     ns: Option<String>,
     name: String,
+    meta: Option<Box<Map>>,
 }
 
 impl Symbol {
@@ -11,6 +15,7 @@ impl Symbol {
         Symbol {
             ns: ns,
             name: name,
+            meta: None,
         }
     }
 
@@ -21,6 +26,42 @@ impl Symbol {
     pub fn ns(&self) -> Option<&String> {
         self.ns.as_ref()
     }
+
+    pub fn meta(&self) -> Option<&Map> {
+        self.meta.as_ref().map(|m| &**m)
+    }
+
+    pub fn with_meta(mut self, meta: Option<Map>) -> Symbol {
+        self.meta = meta.map(Box::new);
+        self
+    }
+}
+
+impl PartialEq for Symbol {
+    fn eq(&self, other: &Symbol) -> bool {
+        self.ns == other.ns && self.name == other.name
+    }
+}
+
+impl Eq for Symbol {}
+
+impl Hash for Symbol {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.ns.hash(state);
+        self.name.hash(state);
+    }
+}
+
+impl PartialOrd for Symbol {
+    fn partial_cmp(&self, other: &Symbol) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Symbol {
+    fn cmp(&self, other: &Symbol) -> Ordering {
+        (&self.ns, &self.name).cmp(&(&other.ns, &other.name))
+    }
 }
 
 impl fmt::Display for Symbol {
@@ -1,20 +1,68 @@
 use std::{fmt, iter, ops};
+use std::cmp::Ordering;
+use std::hash::{Hash, Hasher};
+use std::rc::Rc;
 use ast::Node;
+use ast::nodes::Map;
 use utils::format_vec;
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, Clone)]
 pub struct Vector {
-    vector: Vec<Node>,
+    vector: Rc<Vec<Node>>,
+    meta: Option<Box<Map>>,
 }
 
 impl Vector {
     pub fn new(vector: Vec<Node>) -> Vector {
-        Vector { vector: vector }
+        Vector {
+            vector: Rc::new(vector),
+            meta: None,
+        }
     }
 
     pub fn len(&self) -> usize {
         self.vector.len()
     }
+
+    /// Whether both share the same elements rather than equal copies of them.
+    pub fn ptr_eq(&self, other: &Vector) -> bool {
+        Rc::ptr_eq(&self.vector, &other.vector)
+    }
+
+    pub fn meta(&self) -> Option<&Map> {
+        self.meta.as_ref().map(|m| &**m)
+    }
+
+    pub fn with_meta(mut self, meta: Option<Map>) -> Vector {
+        self.meta = meta.map(Box::new);
+        self
+    }
+}
+
+impl PartialEq for Vector {
+    fn eq(&self, other: &Vector) -> bool {
+        self.vector == other.vector
+    }
+}
+
+impl Eq for Vector {}
+
+impl Hash for Vector {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.vector.hash(state)
+    }
+}
+
+impl PartialOrd for Vector {
+    fn partial_cmp(&self, other: &Vector) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Vector {
+    fn cmp(&self, other: &Vector) -> Ordering {
+        self.vector.cmp(&other.vector)
+    }
 }
 
 pub struct VectorIntoIterator<'a> {
@@ -10,3 +10,44 @@ fn test_format_list_with_nested_list_and_atoms() {
 fn test_format_call_expr_without_args() {
     assert_eq!(format!("{}", n_call!["+", vec![]]), "(+)");
 }
+
+#[test]
+fn test_format_chars_readably() {
+    let input = n_list![vec![n_char!['a'], n_char!['\n'], n_char![' '], n_char!['\t']]];
+    assert_eq!(format!("{}", input), r"(\a \newline \space \tab)");
+}
+
+#[test]
+fn test_nodes_as_hash_map_keys() {
+    use std::collections::HashMap;
+    use std::f64;
+    let mut m = HashMap::new();
+    m.insert(n_map![vec![(n_keyword!["a"], n_number![1.]), (n_keyword!["b"], n_number![0.])]], 1);
+    m.insert(n_vec![vec![n_string!["x"], n_nil![]]], 2);
+    m.insert(n_number![f64::NAN], 3);
+    let key = n_map![vec![(n_keyword!["b"], n_number![-0.]), (n_keyword!["a"], n_number![1.])]];
+    assert_eq!(Some(&1), m.get(&key));
+    assert_eq!(Some(&2), m.get(&n_vec![vec![n_string!["x"], n_nil![]]]));
+    assert_eq!(Some(&3), m.get(&n_number![f64::NAN]));
+    assert_eq!(None, m.get(&n_list![vec![n_string!["x"], n_nil![]]]));
+}
+
+#[test]
+fn test_sort_mixed_nodes() {
+    let mut nodes = vec![n_keyword!["k"], n_string!["b"], n_number![2.], n_nil![],
+                         n_string!["a"], n_number![-1.], n_bool![false]];
+    nodes.sort();
+    assert_eq!(vec![n_nil![], n_number![-1.], n_number![2.], n_bool![false],
+                    n_string!["a"], n_string!["b"], n_keyword!["k"]],
+               nodes);
+}
+
+#[test]
+fn test_compare_sets_regardless_of_order() {
+    let a = n_set![vec![n_keyword!["a"], n_number![1.], n_keyword!["a"]]];
+    let b = n_set![vec![n_number![1.], n_keyword!["a"]]];
+    assert_eq!(a, b);
+    assert_eq!(format!("{}", a), "#{:a 1}");
+    assert!(n_set![vec![n_number![1.]]] < n_set![vec![n_number![2.]]]);
+    assert!(a != n_set![vec![n_number![1.]]]);
+}
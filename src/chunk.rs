@@ -0,0 +1,119 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use ast::{Node, nodes};
+use ast::nodes::Symbol;
+
+/// Value cell of a global, shared with the bytecode referring to it so that redefinitions
+/// are seen without looking the name up again. Holds `None` while the var is unbound.
+pub type Var = Rc<RefCell<Option<Node>>>;
+
+/// Lexical address of a local: a slot of the running frame, or a value of an enclosing
+/// frame captured by index when the closure was created, so no lookup walks the scopes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Addr {
+    Local(usize),
+    Capture(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Arith {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Lt,
+    Gt,
+    Le,
+    Ge,
+    Eq,
+}
+
+/// Instructions of the stack VM. Operands index the side tables of the `Chunk`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Op {
+    Const(usize),
+    LoadLocal(usize),
+    StoreLocal(usize),
+    LoadCapture(usize),
+    LoadVar(usize),
+    /// Looks a global up by name, for symbols that had no var yet when compiled, until it
+    /// finds its var, which it loads from then on.
+    LoadGlobal(usize),
+    Def(usize),
+    Closure(usize),
+    Build(usize),
+    /// Operator, number of args and the call form in `consts` used for errors.
+    Arith(Arith, usize, usize),
+    Jump(usize),
+    JumpIfFalse(usize),
+    /// Jumps keeping the value on top of the stack when it is falsey, pops it otherwise.
+    JumpIfFalseOrPop(usize),
+    /// Jumps keeping the value on top of the stack when it is truthy, pops it otherwise.
+    JumpIfTrueOrPop(usize),
+    Not,
+    Pop,
+    /// Runs the expansion of the guarded call instead of the call when the callee turns out
+    /// to be a macro, then jumps past the call.
+    MacroGuard(usize, usize),
+    /// Number of args and the call form in `consts` used for errors.
+    Call(usize, usize),
+    /// Number of args, the builtin in `NATIVES` and the call form in `consts` used for errors.
+    Builtin(usize, usize, usize),
+    /// Evaluates the form in `consts` by walking it.
+    Eval(usize),
+    /// Pops a value and jumps to the result of the matching clause of a `case`.
+    Case(usize),
+    Return,
+}
+
+#[derive(Debug)]
+pub enum BuildKind {
+    List,
+    Vector(Option<nodes::Map>),
+    Map(Option<nodes::Map>),
+    Set(Option<nodes::Map>),
+}
+
+/// Collection built from the values on top of the stack. Elements coming from
+/// `unquote-splicing` keep their form, which is reported when the value can't be spliced.
+#[derive(Debug)]
+pub struct Build {
+    pub kind: BuildKind,
+    pub spliced: Vec<Option<Node>>,
+}
+
+/// Call of a global that wasn't a macro when compiled, along with the locals it refers to.
+/// Should the global hold a macro when the call runs, the call is expanded and compiled into
+/// a fn of those locals, which is kept along with the macro and run every time after until
+/// the global holds another macro.
+#[derive(Debug)]
+pub struct Guard {
+    pub node: Node,
+    pub locals: Vec<(Symbol, Addr)>,
+    pub expansion: RefCell<Option<(Node, Rc<Chunk>)>>,
+}
+
+/// Jump table of a `case`: the address of the result of each clause, then of the default.
+#[derive(Debug)]
+pub struct Dispatch {
+    pub case: nodes::Case,
+    pub targets: Vec<usize>,
+}
+
+/// Bytecode of a top-level form or of a fn body.
+#[derive(Debug, Default)]
+pub struct Chunk {
+    pub code: Vec<Op>,
+    pub consts: Vec<Node>,
+    pub vars: Vec<(Symbol, Var)>,
+    pub globals: Vec<(Symbol, RefCell<Option<Var>>)>,
+    pub defs: Vec<nodes::Def>,
+    pub fns: Vec<Node>,
+    pub builds: Vec<Build>,
+    pub guards: Vec<Guard>,
+    pub dispatches: Vec<Dispatch>,
+    pub captures: Vec<Addr>,
+    pub required: usize,
+    pub variadic: bool,
+    pub slots: usize,
+}
@@ -1,9 +1,11 @@
 use std::fmt;
+use super::Span;
 
 #[derive(Debug, PartialEq, Clone)]
 pub struct LexerError {
     line: usize,
     col: usize,
+    unterminated: Option<(&'static str, Span)>,
 }
 
 impl LexerError {
@@ -11,13 +13,36 @@ impl LexerError {
         LexerError {
             line: line,
             col: col,
+            unterminated: None,
         }
     }
+
+    /// An error for a `what` literal whose input ends before it is closed,
+    /// `span` covers its opening delimiter.
+    pub fn unterminated(what: &'static str, span: Span) -> LexerError {
+        LexerError {
+            line: span.line(),
+            col: span.col(),
+            unterminated: Some((what, span)),
+        }
+    }
+
+    /// Whether more input could still complete the literal.
+    pub fn is_unterminated(&self) -> bool {
+        self.unterminated.is_some()
+    }
 }
 
 impl fmt::Display for LexerError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "Invalid syntax at {}:{}", self.line, self.col)
+        match self.unterminated {
+            Some((what, ref span)) => {
+                write!(f, "Unterminated {} at {}", what, span)
+            },
+            None => {
+                write!(f, "Invalid syntax at {}:{}", self.line, self.col)
+            },
+        }
     }
 }
 
@@ -29,5 +54,7 @@ mod tests {
     fn test_descriptions_for_error() {
         let err = LexerError::new(1, 10);
         assert_eq!("Invalid syntax at 1:10", format!("{}", err));
+        let err = LexerError::unterminated("string", span!(2, 3, 2, 4));
+        assert_eq!("Unterminated string at 2:3-2:4", format!("{}", err));
     }
 }
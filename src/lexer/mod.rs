@@ -15,6 +15,7 @@ pub struct Lexer<I: Iterator> {
     char: Option<char>,
     line: usize,
     col: usize,
+    offset: usize,
     is_finished: bool,
 }
 
@@ -33,13 +34,41 @@ impl<I: Iterator<Item=char>> Lexer<I> {
             char: None,
             line: 1,
             col: 0,
+            offset: 0,
             is_finished: false,
         };
         l.bump();
+        l.consume_shebang();
         l
     }
 
+    /// Lexes `reader` as the continuation of an earlier input, its first char being at `line`:`col`.
+    pub fn resume(reader: I, line: usize, col: usize) -> Lexer<I> {
+        let mut reader = reader.peekable();
+        Lexer {
+            char: reader.next(),
+            reader: reader,
+            line: line,
+            col: col,
+            offset: 0,
+            is_finished: false,
+        }
+    }
+
+    /// Line and column of the first char not lexed yet.
+    pub fn position(&self) -> (usize, usize) {
+        (self.line, self.col)
+    }
+
+    /// Number of chars lexed so far.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
     fn bump(&mut self) {
+        if self.char.is_some() {
+            self.offset += 1;
+        }
         self.char = self.reader.next();
         if Some('\n') == self.char {
             self.line += 1;
@@ -86,6 +115,9 @@ impl<I: Iterator<Item=char>> Lexer<I> {
                 '"' => {
                     self.read_string()
                 },
+                '\\' => {
+                    self.read_char()
+                },
                 '(' => {
                     let (line, col) = (self.line, self.col);
                     self.bump();
@@ -106,6 +138,21 @@ impl<I: Iterator<Item=char>> Lexer<I> {
                     self.bump();
                     Some(Ok(t_vec_end!(span!(line, col, line, col + 1))))
                 },
+                '{' => {
+                    let (line, col) = (self.line, self.col);
+                    self.bump();
+                    Some(Ok(t_map_start!(span!(line, col, line, col + 1))))
+                },
+                '}' => {
+                    let (line, col) = (self.line, self.col);
+                    self.bump();
+                    Some(Ok(t_map_end!(span!(line, col, line, col + 1))))
+                },
+                '^' => {
+                    let (line, col) = (self.line, self.col);
+                    self.bump();
+                    Some(Ok(t_meta!(span!(line, col, line, col + 1))))
+                },
                 '\'' => {
                     let (line, col) = (self.line, self.col);
                     self.bump();
@@ -126,6 +173,9 @@ impl<I: Iterator<Item=char>> Lexer<I> {
                     self.bump();
                     Some(Ok(t_syntax_quote![span![line, col, line, col + 1]]))
                 },
+                '#' => {
+                    self.read_dispatch()
+                },
                 _ => {
                     Some(self.error())
                 }
@@ -135,6 +185,118 @@ impl<I: Iterator<Item=char>> Lexer<I> {
         }
     }
 
+    fn read_dispatch(&mut self) -> Option<LexerResult> {
+        let (line, col) = (self.line, self.col);
+        self.bump();
+        match self.char {
+            Some('_') => {
+                self.bump();
+                Some(Ok(t_discard![span![line, col, line, col + 2]]))
+            },
+            Some('?') => {
+                self.bump();
+                Some(Ok(t_reader_conditional![span![line, col, line, col + 2]]))
+            },
+            Some('(') => {
+                self.bump();
+                Some(Ok(t_anon_fn_start![span![line, col, line, col + 2]]))
+            },
+            Some('{') => {
+                self.bump();
+                Some(Ok(t_set_start![span![line, col, line, col + 2]]))
+            },
+            Some('\'') => {
+                self.bump();
+                Some(Ok(t_var_quote![span![line, col, line, col + 2]]))
+            },
+            Some('"') => {
+                self.read_regex(line, col)
+            },
+            Some(c) if c.is_alphabetic() => {
+                self.read_tag(line, col)
+            },
+            Some('|') => {
+                if self.consume_block_comment() {
+                    self.read()
+                } else {
+                    Some(self.unterminated("block comment", span![line, col, line, col + 2]))
+                }
+            },
+            None => {
+                Some(self.unterminated("dispatch", span![line, col, line, col + 1]))
+            },
+            _ => {
+                Some(self.error())
+            }
+        }
+    }
+
+    /// Reads the name of a tagged literal like `#inst`, the `#` being at `line`:`col`.
+    fn read_tag(&mut self, line: usize, col: usize) -> Option<LexerResult> {
+        let mut name = String::new();
+        while let Some(c) = self.char {
+            if c.is_alphanumeric() || c == '-' || c == '.' || c == '/' {
+                name.push(c);
+                self.bump();
+            } else {
+                break
+            }
+        }
+        Some(Ok(t_tag!(name, span!(line, col, self.line, self.col))))
+    }
+
+    fn read_regex(&mut self, line: usize, col: usize) -> Option<LexerResult> {
+        let mut res = String::new();
+
+        self.bump();
+
+        while let Some(c) = self.char {
+            match c {
+                '"' => {
+                    self.bump();
+                    return Some(Ok(t_regex!(res, span!(line, col, self.line, self.col))))
+                },
+                '\\' => {
+                    res.push(c);
+                    self.bump();
+                    if let Some(c) = self.char {
+                        res.push(c);
+                        self.bump();
+                    }
+                },
+                _ => {
+                    res.push(c);
+                    self.bump();
+                }
+            }
+        }
+
+        Some(self.unterminated("regex", span![line, col, line, col + 2]))
+    }
+
+    fn consume_block_comment(&mut self) -> bool {
+        let mut depth = 1;
+        self.bump();
+        while let Some(c) = self.char {
+            self.bump();
+            match (c, self.char) {
+                ('|', Some('#')) => {
+                    self.bump();
+                    depth -= 1;
+                    if depth == 0 {
+                        return true
+                    }
+                },
+                ('#', Some('|')) => {
+                    self.bump();
+                    depth += 1;
+                },
+                _ => {}
+            }
+        }
+        false
+    }
+
     fn read_symbol(&mut self) -> Option<LexerResult> {
         let (line, col) = (self.line, self.col);
         let mut ns = None;
@@ -142,7 +304,7 @@ impl<I: Iterator<Item=char>> Lexer<I> {
 
         while let Some(c) = self.char {
             match c {
-                '(' | ')' | '[' | ']' | ';' => {
+                '(' | ')' | '[' | ']' | '{' | '}' | ';' | ',' => {
                     break
                 },
                 '/' if symbol.len() > 0 => {
@@ -178,7 +340,7 @@ impl<I: Iterator<Item=char>> Lexer<I> {
         self.bump();
         while let Some(c) = self.char {
             match c {
-                '(' | ')' | '[' | ']' | ';' => {
+                '(' | ')' | '[' | ']' | '{' | '}' | ';' | ',' => {
                     break
                 },
                 '/' if keyword.len() > 0 => {
@@ -204,6 +366,59 @@ impl<I: Iterator<Item=char>> Lexer<I> {
         }
     }
 
+    fn read_char(&mut self) -> Option<LexerResult> {
+        let (line, col) = (self.line, self.col);
+        let mut name = String::new();
+
+        self.bump();
+        if let Some(c) = self.char {
+            name.push(c);
+            self.bump();
+            if c.is_alphanumeric() {
+                while let Some(c) = self.char {
+                    match c {
+                        '(' | ')' | '[' | ']' | '{' | '}' | ';' | ',' | '"' => {
+                            break
+                        },
+                        c if c.is_whitespace() => {
+                            break
+                        },
+                        _ => {
+                            name.push(c)
+                        }
+                    }
+                    self.bump();
+                }
+            }
+        } else {
+            return Some(self.unterminated("char", span![line, col, line, col + 1]))
+        }
+
+        let val = match &name[..] {
+            "newline" => Some('\n'),
+            "space" => Some(' '),
+            "tab" => Some('\t'),
+            "return" => Some('\r'),
+            "backspace" => Some('\u{8}'),
+            "formfeed" => Some('\u{c}'),
+            n if n.chars().count() == 1 => n.chars().next(),
+            n if n.starts_with('u') && n.len() == 5 => {
+                u32::from_str_radix(&n[1..], 16).ok().and_then(char::from_u32)
+            },
+            _ => None,
+        };
+
+        match val {
+            Some(val) => {
+                Some(Ok(t_char!(val, span!(line, col, self.line, self.col))))
+            },
+            None => {
+                self.is_finished = true;
+                Some(Err(LexerError::new(line, col)))
+            },
+        }
+    }
+
     fn read_number(&mut self) -> Option<LexerResult> {
         let (line, col) = (self.line, self.col);
 
@@ -241,7 +456,7 @@ impl<I: Iterator<Item=char>> Lexer<I> {
                         }
                     }
                 },
-                '(' | ')' | '[' | ']' | ';' => {
+                '(' | ')' | '[' | ']' | '{' | '}' | ';' | ',' => {
                     break
                 },
                 c if c.is_whitespace() => {
@@ -269,16 +484,27 @@ impl<I: Iterator<Item=char>> Lexer<I> {
 
         while let Some(c) = self.char {
             if c == '"' {
-                break
+                self.bump();
+                return Some(Ok(t_string!(res, span!(line, col, self.line, self.col))))
             } else {
                 res.push(c)
             }
             self.bump();
         }
 
-        self.bump();
+        Some(self.unterminated("string", span![line, col, line, col + 1]))
+    }
 
-        Some(Ok(t_string!(res, span!(line, col, self.line, self.col))))
+    fn consume_shebang(&mut self) {
+        if Some('#') == self.char && Some(&'!') == self.reader.peek() {
+            while let Some(c) = self.char {
+                if c == '\n' {
+                    break
+                } else {
+                    self.bump()
+                }
+            }
+        }
     }
 
     fn consume_comments_and_whitespaces(&mut self) {
@@ -293,6 +519,9 @@ impl<I: Iterator<Item=char>> Lexer<I> {
                         }
                     }
                 },
+                ',' => {
+                    self.bump()
+                },
                 c if c.is_whitespace() => {
                     self.bump()
                 },
@@ -307,4 +536,9 @@ impl<I: Iterator<Item=char>> Lexer<I> {
         self.is_finished = true;
         Err(LexerError::new(self.line, self.col))
     }
+
+    fn unterminated(&mut self, what: &'static str, span: Span) -> LexerResult {
+        self.is_finished = true;
+        Err(LexerError::unterminated(what, span))
+    }
 }
@@ -35,6 +35,40 @@ fn test_read_string() {
     assert_eq!(None, lexer.next());
 }
 
+#[test]
+fn test_read_unterminated_string_as_error() {
+    let mut lexer = Lexer::new("(str \"abc\n(+ 1 2)".chars());
+    assert_eq!(Some(Ok(t_list_start!(span!(1, 1, 1, 2)))), lexer.next());
+    assert_eq!(Some(Ok(t_symbol!("str", span!(1, 2, 1, 5)))), lexer.next());
+    let err = LexerError::unterminated("string", span!(1, 6, 1, 7));
+    assert!(err.is_unterminated());
+    assert_eq!(Some(Err(err)), lexer.next());
+    assert_eq!(None, lexer.next());
+}
+
+#[test]
+fn test_read_dispatch_and_char_at_end_of_input_as_unterminated() {
+    let mut lexer = Lexer::new("a #".chars());
+    assert_eq!(Some(Ok(t_symbol!("a", span!(1, 1, 1, 2)))), lexer.next());
+    assert_eq!(Some(Err(LexerError::unterminated("dispatch", span!(1, 3, 1, 4)))), lexer.next());
+    let mut lexer = Lexer::new("\\".chars());
+    assert_eq!(Some(Err(LexerError::unterminated("char", span!(1, 1, 1, 2)))), lexer.next());
+    assert!(!LexerError::new(1, 1).is_unterminated());
+}
+
+#[test]
+fn test_resume_lexing_at_position() {
+    let mut lexer = Lexer::new("(a) b".chars());
+    lexer.next();
+    lexer.next();
+    lexer.next();
+    assert_eq!((1, 4), lexer.position());
+    assert_eq!(3, lexer.offset());
+    let mut lexer = Lexer::resume(" b".chars(), 1, 4);
+    assert_eq!(Some(Ok(t_symbol!("b", span!(1, 5, 1, 6)))), lexer.next());
+    assert_eq!(2, lexer.offset());
+}
+
 #[test]
 fn test_read_symbol() {
     let sym_name = "my-symbol";
@@ -299,3 +333,126 @@ fn test_read_syntax_quoted_list() {
                                Ok(t_list_end![span![1, 8, 1, 9]])];
     assert_eq!(expected_result, lexer.collect::<Vec<LexerResult>>());
 }
+
+#[test]
+fn test_skip_shebang_line() {
+    let lexer = Lexer::new("#!/usr/bin/env lust\n(exit 1)".chars());
+    let expected_result = vec![Ok(t_list_start!(span!(2, 2, 2, 3))),
+                               Ok(t_symbol!("exit", span!(2, 3, 2, 7))),
+                               Ok(t_number!(1_f64, span!(2, 8, 2, 9))),
+                               Ok(t_list_end!(span!(2, 9, 2, 10)))];
+    assert_eq!(expected_result, lexer.collect::<Vec<LexerResult>>());
+}
+
+#[test]
+fn test_read_shebang_not_on_first_line_as_error() {
+    let mut lexer = Lexer::new("\n#!/usr/bin/env lust".chars());
+    assert_eq!(Some(Err(LexerError::new(2, 3))), lexer.next());
+}
+
+#[test]
+fn test_read_dispatch_discard_and_reader_conditional() {
+    let lexer = Lexer::new("#_a #?(:lust 1)".chars());
+    let expected_result = vec![Ok(t_discard!(span!(1, 1, 1, 3))),
+                               Ok(t_symbol!("a", span!(1, 3, 1, 4))),
+                               Ok(t_reader_conditional!(span!(1, 5, 1, 7))),
+                               Ok(t_list_start!(span!(1, 7, 1, 8))),
+                               Ok(t_keyword!("lust", span!(1, 8, 1, 13))),
+                               Ok(t_number!(1_f64, span!(1, 14, 1, 15))),
+                               Ok(t_list_end!(span!(1, 15, 1, 16)))];
+    assert_eq!(expected_result, lexer.collect::<Vec<LexerResult>>());
+}
+
+#[test]
+fn test_skip_nested_block_comments() {
+    let lexer = Lexer::new("a #| (def b #| nested |#\n 2) |# c".chars());
+    let expected_result = vec![Ok(t_symbol!("a", span!(1, 1, 1, 2))),
+                               Ok(t_symbol!("c", span!(2, 9, 2, 10)))];
+    assert_eq!(expected_result, lexer.collect::<Vec<LexerResult>>());
+}
+
+#[test]
+fn test_read_unterminated_block_comment_as_error() {
+    let mut lexer = Lexer::new("a #| (def b 2)".chars());
+    assert_eq!(Some(Ok(t_symbol!("a", span!(1, 1, 1, 2)))), lexer.next());
+    assert_eq!(Some(Err(LexerError::unterminated("block comment", span!(1, 3, 1, 5)))), lexer.next());
+    assert_eq!(None, lexer.next());
+}
+
+#[test]
+fn test_read_unknown_dispatch_as_error() {
+    let mut lexer = Lexer::new("#&".chars());
+    assert_eq!(Some(Err(LexerError::new(1, 2))), lexer.next());
+}
+
+#[test]
+fn test_read_chars() {
+    let lexer = Lexer::new(r"(\a \newline \space \u0041 \( \\)".chars());
+    let expected_result = vec![Ok(t_list_start!(span!(1, 1, 1, 2))),
+                               Ok(t_char!('a', span!(1, 2, 1, 4))),
+                               Ok(t_char!('\n', span!(1, 5, 1, 13))),
+                               Ok(t_char!(' ', span!(1, 14, 1, 20))),
+                               Ok(t_char!('A', span!(1, 21, 1, 27))),
+                               Ok(t_char!('(', span!(1, 28, 1, 30))),
+                               Ok(t_char!('\\', span!(1, 31, 1, 33))),
+                               Ok(t_list_end!(span!(1, 33, 1, 34)))];
+    assert_eq!(expected_result, lexer.collect::<Vec<LexerResult>>());
+}
+
+#[test]
+fn test_read_unknown_char_name_as_error() {
+    let mut lexer = Lexer::new(r"\unknown".chars());
+    assert_eq!(Some(Err(LexerError::new(1, 1))), lexer.next());
+    assert_eq!(None, lexer.next());
+}
+
+#[test]
+fn test_read_regex() {
+    let lexer = Lexer::new(r#"#"\d+\"\s" #"a""#.chars());
+    let expected_result = vec![Ok(t_regex!(r#"\d+\"\s"#, span!(1, 1, 1, 11))),
+                               Ok(t_regex!("a", span!(1, 12, 1, 16)))];
+    assert_eq!(expected_result, lexer.collect::<Vec<LexerResult>>());
+}
+
+#[test]
+fn test_read_tag() {
+    let lexer = Lexer::new(r#"#inst "2026-10-17""#.chars());
+    let expected_result = vec![Ok(t_tag!("inst", span!(1, 1, 1, 6))),
+                               Ok(t_string!("2026-10-17", span!(1, 7, 1, 19)))];
+    assert_eq!(expected_result, lexer.collect::<Vec<LexerResult>>());
+}
+
+#[test]
+fn test_read_unterminated_regex_as_error() {
+    let mut lexer = Lexer::new(r#"(re-find #"\d+)"#.chars());
+    assert_eq!(Some(Ok(t_list_start!(span!(1, 1, 1, 2)))), lexer.next());
+    assert_eq!(Some(Ok(t_symbol!("re-find", span!(1, 2, 1, 9)))), lexer.next());
+    assert_eq!(Some(Err(LexerError::unterminated("regex", span!(1, 10, 1, 12)))), lexer.next());
+    assert_eq!(None, lexer.next());
+}
+
+#[test]
+fn test_read_map_meta_and_var_quote() {
+    let lexer = Lexer::new("^:private {:a 1, :b x} #'x".chars());
+    let expected_result = vec![Ok(t_meta!(span!(1, 1, 1, 2))),
+                               Ok(t_keyword!("private", span!(1, 2, 1, 10))),
+                               Ok(t_map_start!(span!(1, 11, 1, 12))),
+                               Ok(t_keyword!("a", span!(1, 12, 1, 14))),
+                               Ok(t_number!(1_f64, span!(1, 15, 1, 16))),
+                               Ok(t_keyword!("b", span!(1, 18, 1, 20))),
+                               Ok(t_symbol!("x", span!(1, 21, 1, 22))),
+                               Ok(t_map_end!(span!(1, 22, 1, 23))),
+                               Ok(t_var_quote!(span!(1, 24, 1, 26))),
+                               Ok(t_symbol!("x", span!(1, 26, 1, 27)))];
+    assert_eq!(expected_result, lexer.collect::<Vec<LexerResult>>());
+}
+
+#[test]
+fn test_read_set() {
+    let lexer = Lexer::new("#{1 x}".chars());
+    let expected_result = vec![Ok(t_set_start!(span!(1, 1, 1, 3))),
+                               Ok(t_number!(1_f64, span!(1, 3, 1, 4))),
+                               Ok(t_symbol!("x", span!(1, 5, 1, 6))),
+                               Ok(t_map_end!(span!(1, 6, 1, 7)))];
+    assert_eq!(expected_result, lexer.collect::<Vec<LexerResult>>());
+}
@@ -34,6 +34,14 @@ impl Span {
             end: Pos::new(end_line, end_col),
         }
     }
+
+    pub fn line(&self) -> usize {
+        self.start.line
+    }
+
+    pub fn col(&self) -> usize {
+        self.start.col
+    }
 }
 
 impl fmt::Display for Span {
@@ -52,6 +60,18 @@ pub enum Token {
         span: Span,
         val: String,
     },
+    Char {
+        span: Span,
+        val: char,
+    },
+    Regex {
+        span: Span,
+        val: String,
+    },
+    Tag {
+        span: Span,
+        name: String,
+    },
     Symbol {
         ns: Option<String>,
         name: String,
@@ -74,6 +94,21 @@ pub enum Token {
     VecEnd {
         span: Span,
     },
+    MapStart {
+        span: Span,
+    },
+    MapEnd {
+        span: Span,
+    },
+    SetStart {
+        span: Span,
+    },
+    Meta {
+        span: Span,
+    },
+    VarQuote {
+        span: Span,
+    },
     Quote {
         span: Span,
     },
@@ -86,6 +121,15 @@ pub enum Token {
     SyntaxQuote {
         span: Span,
     },
+    Discard {
+        span: Span,
+    },
+    ReaderConditional {
+        span: Span,
+    },
+    AnonFnStart {
+        span: Span,
+    },
 }
 
 impl fmt::Display for Token {
@@ -97,6 +141,15 @@ impl fmt::Display for Token {
             Token::String { ref val, ref span } => {
                 write!(f, r#"'String "{}"' at {}"#, val, span)
             },
+            Token::Char { ref val, ref span } => {
+                write!(f, "'Char {:?}' at {}", val, span)
+            },
+            Token::Regex { ref val, ref span } => {
+                write!(f, r#"'Regex #"{}"' at {}"#, val, span)
+            },
+            Token::Tag { ref name, ref span } => {
+                write!(f, "'Tag #{}' at {}", name, span)
+            },
             Token::Symbol { ref ns, ref name, ref span } => {
                 match *ns {
                     Some(ref ns) => {
@@ -129,6 +182,21 @@ impl fmt::Display for Token {
             Token::VecEnd { ref span } => {
                 write!(f, "'Vec End' at {}", span)
             },
+            Token::MapStart { ref span } => {
+                write!(f, "'Map Start' at {}", span)
+            },
+            Token::MapEnd { ref span } => {
+                write!(f, "'Map End' at {}", span)
+            },
+            Token::SetStart { ref span } => {
+                write!(f, "'Set Start' at {}", span)
+            },
+            Token::Meta { ref span } => {
+                write!(f, "'Meta' at {}", span)
+            },
+            Token::VarQuote { ref span } => {
+                write!(f, "'Var Quote' at {}", span)
+            },
             Token::Quote { ref span } => {
                 write!(f, "'Quote' at {}", span)
             },
@@ -141,6 +209,15 @@ impl fmt::Display for Token {
             Token::SyntaxQuote { ref span } => {
                 write!(f, "'Syntax Quote' at {}", span)
             },
+            Token::Discard { ref span } => {
+                write!(f, "'Discard' at {}", span)
+            },
+            Token::ReaderConditional { ref span } => {
+                write!(f, "'Reader Conditional' at {}", span)
+            },
+            Token::AnonFnStart { ref span } => {
+                write!(f, "'Anonymous Fn Start' at {}", span)
+            },
         }
     }
 }
@@ -1,12 +1,16 @@
+extern crate regex;
+
 #[macro_use]
 mod macros;
 mod lexer;
 mod ast;
+mod chunk;
 mod parser;
 mod state;
 mod utils;
 
-pub use parser::Parser;
+pub use lexer::Lexer;
+pub use parser::{Parser, StreamParser, ParserError};
 pub use ast::Node;
 pub use ast::nodes;
-pub use state::State;
+pub use state::{State, Buffer, EvalError, Rng, XorShift};
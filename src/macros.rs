@@ -6,6 +6,18 @@ macro_rules! t_string {
     ($val:expr, $span:expr) => (::lexer::Token::String { val: $val.to_string(), span: $span, });
 }
 
+macro_rules! t_regex {
+    ($val:expr, $span:expr) => (::lexer::Token::Regex { val: $val.to_string(), span: $span, });
+}
+
+macro_rules! t_tag {
+    ($name:expr, $span:expr) => (::lexer::Token::Tag { name: $name.to_string(), span: $span, });
+}
+
+macro_rules! t_char {
+    ($val:expr, $span:expr) => (::lexer::Token::Char { val: $val, span: $span, });
+}
+
 macro_rules! t_symbol {
     ($name:expr, $span:expr) => (::lexer::Token::Symbol {
         ns: None,
@@ -48,6 +60,26 @@ macro_rules! t_vec_end {
     ($span:expr) => (::lexer::Token::VecEnd { span: $span });
 }
 
+macro_rules! t_map_start {
+    ($span:expr) => (::lexer::Token::MapStart { span: $span });
+}
+
+macro_rules! t_map_end {
+    ($span:expr) => (::lexer::Token::MapEnd { span: $span });
+}
+
+macro_rules! t_set_start {
+    ($span:expr) => (::lexer::Token::SetStart { span: $span });
+}
+
+macro_rules! t_meta {
+    ($span:expr) => (::lexer::Token::Meta { span: $span });
+}
+
+macro_rules! t_var_quote {
+    ($span:expr) => (::lexer::Token::VarQuote { span: $span });
+}
+
 macro_rules! t_quote {
     ($span:expr) => (::lexer::Token::Quote { span: $span });
 }
@@ -64,6 +96,18 @@ macro_rules! t_syntax_quote {
     ($span:expr) => (::lexer::Token::SyntaxQuote { span: $span });
 }
 
+macro_rules! t_discard {
+    ($span:expr) => (::lexer::Token::Discard { span: $span });
+}
+
+macro_rules! t_reader_conditional {
+    ($span:expr) => (::lexer::Token::ReaderConditional { span: $span });
+}
+
+macro_rules! t_anon_fn_start {
+    ($span:expr) => (::lexer::Token::AnonFnStart { span: $span });
+}
+
 macro_rules! span {
     ($start_line:expr, $start_col:expr, $end_line:expr, $end_col:expr) => (
         ::lexer::Span::new($start_line, $start_col, $end_line, $end_col);
@@ -75,6 +119,11 @@ macro_rules! n_number {
     ($e:expr) => ($crate::Node::Number($crate::nodes::Number::new($e)))
 }
 
+#[macro_export]
+macro_rules! n_nil {
+    () => ($crate::Node::Nil)
+}
+
 #[macro_export]
 macro_rules! n_bool {
     ($e:expr) => ($crate::Node::Bool($crate::nodes::Bool::new($e)))
@@ -85,6 +134,16 @@ macro_rules! n_string {
     ($e:expr) => ($crate::Node::String($crate::nodes::String::new($e.to_string())))
 }
 
+#[macro_export]
+macro_rules! n_char {
+    ($e:expr) => ($crate::Node::Char($crate::nodes::Char::new($e)))
+}
+
+#[macro_export]
+macro_rules! n_inst {
+    ($e:expr) => ($crate::Node::Inst($crate::nodes::Inst::new($e)))
+}
+
 #[macro_export]
 macro_rules! n_symbol {
     ($name:expr) => ($crate::Node::Symbol(
@@ -116,6 +175,18 @@ macro_rules! n_vec {
     ($vec:expr) => ($crate::Node::Vector($crate::nodes::Vector::new($vec)))
 }
 
+#[macro_export]
+macro_rules! n_map {
+    () => ($crate::Node::Map($crate::nodes::Map::new(vec![])));
+    ($entries:expr) => ($crate::Node::Map($crate::nodes::Map::new($entries)))
+}
+
+#[macro_export]
+macro_rules! n_set {
+    () => ($crate::Node::Set($crate::nodes::Set::new(vec![])));
+    ($items:expr) => ($crate::Node::Set($crate::nodes::Set::new($items)))
+}
+
 #[macro_export]
 macro_rules! n_def {
     ($name:expr, $e:expr) => ($crate::Node::Def($crate::nodes::Def::new(
@@ -155,11 +226,11 @@ macro_rules! n_macro {
 #[macro_export]
 macro_rules! n_call {
     ($name:expr, $args:expr) => ($crate::Node::Call($crate::nodes::Call::new(
-        $crate::nodes::Symbol::new(None, $name.to_string()),
+        $crate::Node::Symbol($crate::nodes::Symbol::new(None, $name.to_string())),
         $args,
     )));
     ($ns:expr, $name:expr, $args:expr) => ($crate::Node::Call($crate::nodes::Call::new(
-        $crate::nodes::Symbol::new($ns, $name.to_string()),
+        $crate::Node::Symbol($crate::nodes::Symbol::new($ns, $name.to_string())),
         $args,
     )));
 }
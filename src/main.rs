@@ -3,16 +3,17 @@ extern crate docopt;
 #[macro_use]
 extern crate lust;
 
+use std::env;
 use std::io::{self, Write, Read};
 use std::path::Path;
+use std::process;
 use std::fs::{File, metadata};
 use docopt::Docopt;
-use lust::Parser;
-use lust::State;
+use lust::{Parser, StreamParser, State, EvalError, Node};
 
 macro_rules! println_error {
-    ($err:expr) => (println!("Whoops, error detected.\n{}.\n\
-                              Please, try again...", $err))
+    ($err:expr) => (writeln!(io::stderr(), "Whoops, error detected.\n{}.\n\
+                                            Please, try again...", $err).ok())
 }
 
 macro_rules! try_ok {
@@ -22,7 +23,27 @@ macro_rules! try_ok {
                 res
             },
             Err(err) => {
-                return println_error!(err)
+                println_error!(err);
+                return 1
+            }
+        }
+    })
+}
+
+macro_rules! try_eval {
+    ($state:expr, $e:expr) => ({
+        match $e {
+            Ok(res) => {
+                res
+            },
+            Err(EvalError::Exit(code)) => {
+                $state.flush().ok();
+                return code
+            },
+            Err(err) => {
+                $state.flush().ok();
+                println_error!(err);
+                return 1
             }
         }
     })
@@ -30,69 +51,150 @@ macro_rules! try_ok {
 
 static USAGE: &'static str = "
 Usage:
-    lust [options] [<expr>]
+    lust [options] [-e <expr>]... [<file> [<args>...]]
 
 Options:
+    -e <expr>, --eval <expr>                    Evaluate expression, can be repeated
     -f <file_path>, --file <file_path>          Evaluate expresions from file
     -i, --interactive                           Run REPL session
+    -x, --expand                                Print expresions with all macros expanded
+                                                instead of evaluating them
 ";
 
 #[derive(RustcDecodable, Debug)]
 struct CliArgs {
-    arg_expr: Option<String>,
+    arg_file: Option<String>,
+    arg_args: Vec<String>,
+    flag_eval: Vec<String>,
     flag_file: Option<String>,
     flag_interactive: bool,
+    flag_expand: bool,
+}
+
+/// Parses `argv`, the program name first. Everything from the script file on is left to the
+/// script, even when it looks like one of our options.
+fn parse_args<I, S>(argv: I) -> Result<CliArgs, docopt::Error>
+    where I: IntoIterator<Item=S>, S: AsRef<str> {
+    Docopt::new(USAGE).and_then(|d| d.options_first(true).argv(argv).decode())
+}
+
+fn read_file(file_path: &str) -> Result<String, String> {
+    let path = Path::new(file_path);
+    let md = metadata(path);
+    if is_file_exists!(md) {
+        if is_file!(md) {
+            let mut file = try!(File::open(&path).map_err(|e| e.to_string()));
+            let mut buf = String::new();
+            try!(file.read_to_string(&mut buf).map_err(|e| e.to_string()));
+            Ok(buf)
+        } else {
+            Err("Specified path is not a file".to_string())
+        }
+    } else {
+        Err("File doesn't exist".to_string())
+    }
+}
+
+fn eval_source(state: &mut State, src: &str) -> Result<Option<Node>, EvalError> {
+    let mut last_evaled = None;
+    for parsed_expr in Parser::new(src.chars()) {
+        last_evaled = Some(try!(state.eval(&try!(parsed_expr))));
+    }
+    Ok(last_evaled)
+}
+
+/// Whether `node` defines a fn or a macro, `(def name (fn ...))` or `(def name (macro ...))`.
+fn is_fn_def(node: &Node) -> bool {
+    match *node {
+        Node::List(ref l) if l.len() >= 3 && l[0].is_symbol("def") => {
+            match l[l.len() - 1] {
+                Node::List(ref v) => v.len() >= 1 && (v[0].is_symbol("fn") || v[0].is_symbol("macro")),
+                _ => false,
+            }
+        },
+        _ => {
+            false
+        },
+    }
+}
+
+/// Expands the macros of every expression, passing the results to `emit`. Only definitions
+/// of fns and macros are evaluated, so that later expressions can use the macros, and the
+/// fns those call, without running the side effects of anything else.
+fn expand_each<F>(state: &mut State, src: &str, mut emit: F) -> Result<(), EvalError>
+    where F: FnMut(&Node) {
+    for parsed_expr in Parser::new(src.chars()) {
+        let expanded = try!(state.macroexpand_all(&try!(parsed_expr)));
+        if is_fn_def(&expanded) {
+            try!(state.eval(&expanded));
+        }
+        emit(&expanded);
+    }
+    Ok(())
+}
+
+/// Prints every expression with its macros expanded.
+fn expand_source(state: &mut State, src: &str) -> Result<Option<Node>, EvalError> {
+    try!(expand_each(state, src, |e| println!("{}", e)));
+    Ok(None)
 }
 
 #[cfg_attr(test, allow(dead_code))]
 fn main() {
-    let args = try_ok!(Docopt::new(USAGE).and_then(|d| d.decode::<CliArgs>()));
+    process::exit(run());
+}
+
+fn run() -> i32 {
+    let args = try_ok!(parse_args(env::args()));
     let ref mut state = State::new("user".to_string());
     let mut last_evaled = None;
+    let process_source = if args.flag_expand { expand_source } else { eval_source };
+
+    state.set_command_line_args(args.arg_args.clone());
 
     if let Some(ref flag_file) = args.flag_file {
-        let path = Path::new(flag_file);
-        let md = metadata(path);
-        if is_file_exists!(md) {
-            if is_file!(md) {
-                let mut file = try_ok!(File::open(&path));
-                let ref mut buf = String::new();
-                try_ok!(file.read_to_string(buf));
-                for parsed_expr in Parser::new(buf.chars()) {
-                    last_evaled = Some(try_ok!(state.eval(&try_ok!(parsed_expr))));
-                }
-            } else {
-                return println_error!("Specified path is not a file.");
-            }
-        } else {
-            return println_error!("File doesn't exist.");
-        }
+        let src = try_ok!(read_file(flag_file));
+        state.set_file(flag_file.clone());
+        last_evaled = try_eval!(state, process_source(state, &src));
     }
 
-    if let Some(ref arg_expr) = args.arg_expr {
-        for parsed_expr in Parser::new(arg_expr.chars()) {
-            last_evaled = Some(try_ok!(state.eval(&try_ok!(parsed_expr))))
-        }
+    for flag_eval in &args.flag_eval {
+        last_evaled = try_eval!(state, process_source(state, flag_eval));
     }
 
+    if let Some(ref arg_file) = args.arg_file {
+        let src = try_ok!(read_file(arg_file));
+        state.set_file(arg_file.clone());
+        try_eval!(state, process_source(state, &src));
+        last_evaled = None;
+    }
 
     if args.flag_interactive {
         let stdin = io::stdin();
         let mut stdout = io::stdout();
+        let mut parser = StreamParser::new();
 
         loop {
-            print!("-> ");
+            state.flush().ok();
+            print!("{}", if parser.is_incomplete() { ".. " } else { "-> " });
             stdout.flush().ok();
             let ref mut buf = String::new();
             if try_ok!(stdin.read_line(buf)) > 0 {
-                for expr in Parser::new(buf.chars()) {
+                parser.feed(buf);
+                for expr in &mut parser {
                     match expr {
                         Ok(parsed_expr) => {
                             match state.eval(&parsed_expr) {
                                 Ok(res) => {
+                                    state.flush().ok();
                                     println!("{}", res);
                                 },
+                                Err(EvalError::Exit(code)) => {
+                                    state.flush().ok();
+                                    return code
+                                },
                                 Err(err) => {
+                                    state.flush().ok();
                                     println_error!(err);
                                 }
                             }
@@ -103,10 +205,73 @@ fn main() {
                     }
                 }
             } else {
-                return println!("\nHope you enjoyed.\nSee you...");
+                parser.finish();
+                if let Some(Err(err)) = parser.next() {
+                    println_error!(err);
+                }
+                println!("\nHope you enjoyed.\nSee you...");
+                return 0
             }
         }
-    } else if let Some(ref expr) = last_evaled {
-        println!("{}", expr);
+    } else {
+        state.flush().ok();
+        if let Some(ref expr) = last_evaled {
+            println!("{}", expr);
+        }
+        0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lust::{State, Buffer, EvalError};
+    use super::{parse_args, expand_each};
+
+    #[test]
+    fn test_parse_args_passes_script_args_through() {
+        let argv = vec!["lust", "-e", "(+ 1 2)", "script.ls", "-x", "--eval", "foo"];
+        let args = parse_args(argv).ok().unwrap();
+        assert_eq!(vec!["(+ 1 2)".to_string()], args.flag_eval);
+        assert!(!args.flag_expand);
+        assert_eq!(Some("script.ls".to_string()), args.arg_file);
+        assert_eq!(vec!["-x".to_string(), "--eval".to_string(), "foo".to_string()], args.arg_args);
+    }
+
+    #[test]
+    fn test_parse_args_reads_options_before_script() {
+        let args = parse_args(vec!["lust", "-x", "-i", "script.ls"]).ok().unwrap();
+        assert!(args.flag_expand && args.flag_interactive);
+        assert_eq!(Some("script.ls".to_string()), args.arg_file);
+        assert!(args.arg_args.is_empty());
+    }
+
+    #[test]
+    fn test_expand_each_evaluates_defs_used_by_later_macros() {
+        let ref mut state = State::new("user".to_string());
+        let src = "(defn twice-form [x] `(+ ~x ~x))
+                   (defmacro twice [x] (twice-form x))
+                   (twice 3)";
+        let mut expanded = vec![];
+        expand_each(state, src, |e| expanded.push(format!("{}", e))).ok().unwrap();
+        assert_eq!(3, expanded.len());
+        assert_eq!("(lust.core/+ 3 3)", expanded[2]);
+        assert!(state.eval(&n_symbol!["twice-form"]).is_ok());
+    }
+
+    #[test]
+    fn test_expand_each_evaluates_no_other_defs() {
+        let ref mut state = State::new("user".to_string());
+        let out = Buffer::new();
+        state.set_out(out.clone());
+        let src = "(def x (println \"side\"))
+                   (defn f [] (println \"effect\"))
+                   (f)";
+        let mut expanded = vec![];
+        expand_each(state, src, |e| expanded.push(format!("{}", e))).ok().unwrap();
+        assert_eq!(3, expanded.len());
+        assert_eq!("(def x (println \"side\"))", expanded[0]);
+        assert_eq!("", out.contents());
+        assert_eq!(Err(EvalError::ResolveError("x".to_string())), state.eval(&n_symbol!["x"]));
+        assert!(state.eval(&n_symbol!["f"]).is_ok());
     }
 }
@@ -4,10 +4,28 @@ use lexer::{Token, LexerError};
 #[derive(Debug, PartialEq)]
 pub enum ParserError {
     UnexpectedToken(Token),
-    UnexpectedEndOfInput,
+    Unterminated(Token),
+    MalformedReaderConditional(Token),
+    NestedAnonFn(Token),
+    InvalidRegex(Token, String),
+    UnknownTag(Token),
+    InvalidInst(Token, String),
+    MalformedMap(Token),
+    MalformedMeta(Token),
     LexerError(LexerError),
 }
 
+impl ParserError {
+    /// Whether the input ended inside a form, so that more of it could still parse.
+    pub fn is_incomplete(&self) -> bool {
+        match *self {
+            ParserError::Unterminated(..) => true,
+            ParserError::LexerError(ref e) => e.is_unterminated(),
+            _ => false,
+        }
+    }
+}
+
 impl fmt::Display for ParserError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
@@ -17,8 +35,29 @@ impl fmt::Display for ParserError {
             ParserError::LexerError(ref e) => {
                 write!(f, "{}", e)
             },
-            ParserError::UnexpectedEndOfInput => {
-                write!(f, "Unexpected end of input")
+            ParserError::Unterminated(ref t) => {
+                write!(f, "Unterminated form {}", t)
+            },
+            ParserError::MalformedReaderConditional(ref t) => {
+                write!(f, "Malformed reader conditional {}", t)
+            },
+            ParserError::NestedAnonFn(ref t) => {
+                write!(f, "Nested anonymous fn {}", t)
+            },
+            ParserError::InvalidRegex(ref t, ref e) => {
+                write!(f, "Invalid regex {}: {}", t, e)
+            },
+            ParserError::UnknownTag(ref t) => {
+                write!(f, "Unknown tagged literal {}", t)
+            },
+            ParserError::InvalidInst(ref t, ref s) => {
+                write!(f, r#"Invalid instant "{}" after {}"#, s, t)
+            },
+            ParserError::MalformedMap(ref t) => {
+                write!(f, "Map literal must contain an even number of forms {}", t)
+            },
+            ParserError::MalformedMeta(ref t) => {
+                write!(f, "Malformed metadata {}", t)
             },
         }
     }
@@ -33,8 +72,24 @@ mod tests {
     fn test_descriptions_for_error_codes() {
         let err = ParserError::UnexpectedToken(t_list_end!(span!(1, 1, 1, 2)));
         assert_eq!("Unexpected token 'List End' at 1:1-1:2", format!("{}", err));
-        let err = ParserError::UnexpectedEndOfInput;
-        assert_eq!("Unexpected end of input", format!("{}", err));
+        let err = ParserError::Unterminated(t_vec_start!(span!(2, 3, 2, 4)));
+        assert_eq!("Unterminated form 'Vec Start' at 2:3-2:4", format!("{}", err));
+        let err = ParserError::MalformedReaderConditional(t_reader_conditional!(span!(1, 1, 1, 3)));
+        assert_eq!("Malformed reader conditional 'Reader Conditional' at 1:1-1:3", format!("{}", err));
+        let err = ParserError::NestedAnonFn(t_anon_fn_start!(span!(1, 4, 1, 6)));
+        assert_eq!("Nested anonymous fn 'Anonymous Fn Start' at 1:4-1:6", format!("{}", err));
+        let err = ParserError::InvalidRegex(t_regex!("(", span!(1, 1, 1, 5)),
+                                            "unclosed group".to_string());
+        assert_eq!(r#"Invalid regex 'Regex #"("' at 1:1-1:5: unclosed group"#, format!("{}", err));
+        let err = ParserError::UnknownTag(t_tag!("uuid", span!(1, 1, 1, 6)));
+        assert_eq!("Unknown tagged literal 'Tag #uuid' at 1:1-1:6", format!("{}", err));
+        let err = ParserError::InvalidInst(t_tag!("inst", span!(1, 1, 1, 6)), "2026-13-01".to_string());
+        assert_eq!(r#"Invalid instant "2026-13-01" after 'Tag #inst' at 1:1-1:6"#, format!("{}", err));
+        let err = ParserError::MalformedMap(t_map_start!(span!(1, 1, 1, 2)));
+        assert_eq!("Map literal must contain an even number of forms 'Map Start' at 1:1-1:2",
+                   format!("{}", err));
+        let err = ParserError::MalformedMeta(t_meta!(span!(1, 1, 1, 2)));
+        assert_eq!("Malformed metadata 'Meta' at 1:1-1:2", format!("{}", err));
         let err = ParserError::LexerError(LexerError::new(1, 10));
         assert_eq!("Invalid syntax at 1:10", format!("{}", err));
     }
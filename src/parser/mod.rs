@@ -1,16 +1,83 @@
 mod error;
+mod stream;
 #[cfg(test)]
 mod tests;
 
-use ast::Node;
-use lexer::{Token, Lexer, LexerResult};
+use std::cmp;
+use regex;
+use ast::{Node, nodes};
+use lexer::{Token, Span, Lexer, LexerResult};
 
 pub use self::error::ParserError;
+pub use self::stream::StreamParser;
 pub type ParserResult = Result<Node, ParserError>;
 
+fn parse_regex(token: &Token) -> ParserResult {
+    if let Token::Regex { ref val, .. } = *token {
+        regex::Regex::new(val)
+            .map(|r| Node::Regex(nodes::Regex::new(r)))
+            .map_err(|e| ParserError::InvalidRegex(token.clone(), e.to_string()))
+    } else {
+        Err(ParserError::UnexpectedToken(token.clone()))
+    }
+}
+
+fn span_meta(span: &Span) -> nodes::Map {
+    nodes::Map::new(vec![(n_keyword!["line"], n_number![span.line() as f64]),
+                         (n_keyword!["column"], n_number![span.col() as f64])])
+}
+
+fn replace_anon_fn_params(node: Node, arity: &mut usize, is_variadic: &mut bool) -> Node {
+    match node {
+        Node::Symbol(ref s) if s.ns().is_none() && s.name().starts_with('%') => {
+            match &s.name()[1..] {
+                "" => {
+                    *arity = cmp::max(*arity, 1);
+                    n_symbol!["%1"].with_meta(s.meta().cloned()).unwrap()
+                },
+                "&" => {
+                    *is_variadic = true;
+                    node.clone()
+                },
+                n => {
+                    if let Ok(i) = n.parse::<usize>() {
+                        *arity = cmp::max(*arity, i);
+                    }
+                    node.clone()
+                },
+            }
+        },
+        Node::List(ref l) => {
+            n_list![l.iter().map(|e| replace_anon_fn_params(e.clone(), arity, is_variadic)).collect()]
+                .with_meta(l.meta().cloned()).unwrap()
+        },
+        Node::Vector(ref v) => {
+            n_vec![v.iter().map(|e| replace_anon_fn_params(e.clone(), arity, is_variadic)).collect()]
+                .with_meta(v.meta().cloned()).unwrap()
+        },
+        Node::Map(ref m) => {
+            let entries = m.entries().iter()
+                .map(|&(ref k, ref v)| (replace_anon_fn_params(k.clone(), arity, is_variadic),
+                                        replace_anon_fn_params(v.clone(), arity, is_variadic)))
+                .collect();
+            Node::Map(nodes::Map::new(entries).with_meta(m.meta().cloned()))
+        },
+        Node::Set(ref s) => {
+            let items = s.items().iter()
+                .map(|e| replace_anon_fn_params(e.clone(), arity, is_variadic))
+                .collect();
+            Node::Set(nodes::Set::new(items).with_meta(s.meta().cloned()))
+        },
+        _ => {
+            node
+        },
+    }
+}
+
 pub struct Parser<I: Iterator> {
     lexer: Lexer<I>,
     token: Option<LexerResult>,
+    in_anon_fn: bool,
 }
 
 impl<I: Iterator<Item=char>> Iterator for Parser<I> {
@@ -26,6 +93,16 @@ impl<I: Iterator<Item=char>> Parser<I> {
         Parser {
             lexer: Lexer::new(src),
             token: None,
+            in_anon_fn: false,
+        }
+    }
+
+    /// Parses `src` as the continuation of an earlier input, its first char being at `line`:`col`.
+    pub fn resume(src: I, line: usize, col: usize) -> Parser<I> {
+        Parser {
+            lexer: Lexer::resume(src, line, col),
+            token: None,
+            in_anon_fn: false,
         }
     }
 
@@ -34,15 +111,60 @@ impl<I: Iterator<Item=char>> Parser<I> {
     }
 
     fn parse(&mut self) -> Option<ParserResult> {
-        self.bump();
-        if self.token.is_some() {
-            Some(self.parse_expr())
-        } else {
-            None
+        loop {
+            self.bump();
+            let open = match self.token {
+                Some(Ok(ref t)) => {
+                    t.clone()
+                },
+                Some(Err(ref e)) => {
+                    return Some(Err(ParserError::LexerError(e.clone())))
+                },
+                None => {
+                    return None
+                },
+            };
+            match self.parse_form(&open) {
+                Ok(Some(node)) => {
+                    return Some(Ok(node))
+                },
+                Ok(None) => {
+                    continue
+                },
+                Err(e) => {
+                    return Some(Err(e))
+                },
+            }
         }
     }
 
-    fn parse_expr(&mut self) -> ParserResult {
+    /// Parses the form after `open`, input ending before it is an error spanning `open`.
+    fn parse_expr(&mut self, open: &Token) -> ParserResult {
+        loop {
+            if let Some(node) = try!(self.parse_form(open)) {
+                return Ok(node)
+            }
+            self.bump();
+        }
+    }
+
+    fn parse_form(&mut self, open: &Token) -> Result<Option<Node>, ParserError> {
+        match self.token {
+            Some(Ok(ref t @ Token::Discard { .. })) => {
+                let t = t.clone();
+                self.parse_discarded(t)
+            },
+            Some(Ok(ref t @ Token::ReaderConditional { .. })) => {
+                let t = t.clone();
+                self.parse_reader_conditional(t)
+            },
+            _ => {
+                self.parse_atom_or_coll(open).map(Some)
+            },
+        }
+    }
+
+    fn parse_atom_or_coll(&mut self, open: &Token) -> ParserResult {
         match self.token {
             Some(Ok(Token::Number { val, .. })) => {
                 Ok(n_number![val])
@@ -50,82 +172,229 @@ impl<I: Iterator<Item=char>> Parser<I> {
             Some(Ok(Token::String { ref val, .. })) => {
                 Ok(n_string![val.clone()])
             },
+            Some(Ok(Token::Char { val, .. })) => {
+                Ok(n_char![val])
+            },
+            Some(Ok(ref t @ Token::Regex { .. })) => {
+                parse_regex(t)
+            },
+            Some(Ok(Token::Symbol { ns: None, ref name, .. })) if name == "nil" => {
+                Ok(n_nil![])
+            },
             Some(Ok(Token::Symbol { ref ns, ref name, .. })) => {
                 Ok(n_symbol![ns.clone(), name.clone()])
             },
             Some(Ok(Token::Keyword { ref ns, ref name, .. })) => {
                 Ok(n_keyword![ns.clone(), name.clone()])
             },
-            Some(Ok(Token::ListStart { .. })) => {
-                self.parse_list()
+            Some(Ok(Token::ListStart { ref span })) => {
+                let (t, meta) = (t_list_start!(span.clone()), span_meta(span));
+                self.parse_list(t).map(|l| l.with_meta(Some(meta)).unwrap())
+            },
+            Some(Ok(ref t @ Token::VecStart { .. })) => {
+                let t = t.clone();
+                self.parse_vec(t)
+            },
+            Some(Ok(ref t @ Token::MapStart { .. })) => {
+                let t = t.clone();
+                self.parse_map(t)
             },
-            Some(Ok(Token::VecStart { .. })) => {
-                self.parse_vec()
+            Some(Ok(ref t @ Token::SetStart { .. })) => {
+                let t = t.clone();
+                self.parse_set(t)
             },
-            Some(Ok(Token::Quote { .. })) => {
-                self.parse_quoted()
+            Some(Ok(ref t @ Token::Meta { .. })) => {
+                let t = t.clone();
+                self.parse_meta(t)
             },
-            Some(Ok(Token::Unquote { .. })) => {
-                self.parse_unquoted()
+            Some(Ok(ref t @ Token::Tag { .. })) => {
+                let t = t.clone();
+                self.parse_tagged(t)
             },
-            Some(Ok(Token::UnquoteSplicing { .. })) => {
-                self.parse_unquoted_splicing()
+            Some(Ok(ref t @ Token::VarQuote { .. })) => {
+                let t = t.clone();
+                self.parse_prefixed("var", t)
             },
-            Some(Ok(Token::SyntaxQuote { .. })) => {
-                self.parse_syntax_quote()
+            Some(Ok(ref t @ Token::AnonFnStart { .. })) => {
+                if self.in_anon_fn {
+                    Err(ParserError::NestedAnonFn(t.clone()))
+                } else {
+                    let t = t.clone();
+                    self.parse_anon_fn(t)
+                }
+            },
+            Some(Ok(ref t @ Token::Quote { .. })) => {
+                let t = t.clone();
+                self.parse_prefixed("quote", t)
+            },
+            Some(Ok(ref t @ Token::Unquote { .. })) => {
+                let t = t.clone();
+                self.parse_prefixed("unquote", t)
+            },
+            Some(Ok(ref t @ Token::UnquoteSplicing { .. })) => {
+                let t = t.clone();
+                self.parse_prefixed("unquote-splicing", t)
+            },
+            Some(Ok(ref t @ Token::SyntaxQuote { .. })) => {
+                let t = t.clone();
+                self.parse_prefixed("syntax-quote", t)
             },
             Some(Ok(ref t @ Token::ListEnd { .. })) |
-            Some(Ok(ref t @ Token::VecEnd { .. })) => {
+            Some(Ok(ref t @ Token::VecEnd { .. })) |
+            Some(Ok(ref t @ Token::MapEnd { .. })) |
+            Some(Ok(ref t @ Token::Discard { .. })) |
+            Some(Ok(ref t @ Token::ReaderConditional { .. })) => {
                 Err(ParserError::UnexpectedToken(t.clone()))
             },
             Some(Err(ref e)) => {
                 Err(ParserError::LexerError(e.clone()))
             },
             None => {
-                Err(ParserError::UnexpectedEndOfInput)
+                Err(ParserError::Unterminated(open.clone()))
             }
         }
     }
 
-    fn parse_list(&mut self) -> ParserResult {
-        let mut list = vec![];
+    /// Parses forms up to the token closing `open`.
+    fn parse_coll<F>(&mut self, open: &Token, is_end: F) -> Result<Vec<Node>, ParserError>
+        where F: Fn(&Token) -> bool {
+        let mut forms = vec![];
         loop {
             self.bump();
-            if let Some(Ok(Token::ListEnd { .. })) = self.token {
-                return Ok(n_list![list])
+            if let Some(Ok(ref t)) = self.token {
+                if is_end(t) {
+                    return Ok(forms)
+                }
+            }
+            if let Some(node) = try!(self.parse_form(open)) {
+                forms.push(node)
             }
-            list.push(try!(self.parse_expr()))
         }
     }
 
-    fn parse_vec(&mut self) -> ParserResult {
-        let mut v = Vec::new();
-        loop {
-            self.bump();
-            if let Some(Ok(Token::VecEnd { .. })) = self.token {
-                return Ok(n_vec![v])
-            }
-            v.push(try!(self.parse_expr()))
+    fn parse_list(&mut self, token: Token) -> ParserResult {
+        let list = try!(self.parse_coll(&token, |t| if let Token::ListEnd { .. } = *t { true } else { false }));
+        Ok(n_list![list])
+    }
+
+    fn parse_vec(&mut self, token: Token) -> ParserResult {
+        let v = try!(self.parse_coll(&token, |t| if let Token::VecEnd { .. } = *t { true } else { false }));
+        Ok(n_vec![v])
+    }
+
+    fn parse_map(&mut self, token: Token) -> ParserResult {
+        let forms = try!(self.parse_coll(&token, |t| if let Token::MapEnd { .. } = *t { true } else { false }));
+        if forms.len() % 2 != 0 {
+            return Err(ParserError::MalformedMap(token))
+        }
+        let mut entries = vec![];
+        let mut forms = forms.into_iter();
+        while let (Some(k), Some(v)) = (forms.next(), forms.next()) {
+            entries.push((k, v))
+        }
+        Ok(n_map![entries])
+    }
+
+    fn parse_set(&mut self, token: Token) -> ParserResult {
+        let items = try!(self.parse_coll(&token, |t| if let Token::MapEnd { .. } = *t { true } else { false }));
+        Ok(n_set![items])
+    }
+
+    fn parse_meta(&mut self, token: Token) -> ParserResult {
+        self.bump();
+        let meta = match try!(self.parse_expr(&token)) {
+            Node::Map(m) => {
+                m
+            },
+            k @ Node::Keyword(..) => {
+                nodes::Map::new(vec![(k, n_bool![true])])
+            },
+            t @ Node::Symbol(..) | t @ Node::String(..) => {
+                nodes::Map::new(vec![(n_keyword!["tag"], t)])
+            },
+            _ => {
+                return Err(ParserError::MalformedMeta(token))
+            },
+        };
+        self.bump();
+        let target = try!(self.parse_expr(&token));
+        let mut merged = target.meta().cloned().unwrap_or_else(|| nodes::Map::new(vec![]));
+        merged.merge(&meta);
+        target.with_meta(Some(merged)).ok_or(ParserError::MalformedMeta(token))
+    }
+
+    fn parse_anon_fn(&mut self, token: Token) -> ParserResult {
+        self.in_anon_fn = true;
+        let body = self.parse_list(token);
+        self.in_anon_fn = false;
+        let (mut arity, mut is_variadic) = (0, false);
+        let body = replace_anon_fn_params(try!(body), &mut arity, &mut is_variadic);
+        let mut params = (1..arity + 1).map(|i| n_symbol![format!("%{}", i)]).collect::<Vec<_>>();
+        if is_variadic {
+            params.push(n_symbol!["&"]);
+            params.push(n_symbol!["%&"]);
         }
+        Ok(n_list![vec![n_symbol!["fn"], n_vec![params], body]])
     }
 
-    fn parse_quoted(&mut self) -> ParserResult {
+    fn parse_discarded(&mut self, token: Token) -> Result<Option<Node>, ParserError> {
         self.bump();
-        Ok(n_list![vec![n_symbol!["quote"], try!(self.parse_expr())]])
+        try!(self.parse_expr(&token));
+        Ok(None)
     }
 
-    fn parse_unquoted(&mut self) -> ParserResult {
+    fn parse_reader_conditional(&mut self, token: Token) -> Result<Option<Node>, ParserError> {
         self.bump();
-        Ok(n_list![vec![n_symbol!["unquote"], try!(self.parse_expr())]])
+        match self.token {
+            Some(Ok(ref t @ Token::ListStart { .. })) => {
+                let t = t.clone();
+                if let Node::List(ref l) = try!(self.parse_list(t)) {
+                    if l.len() % 2 != 0 {
+                        return Err(ParserError::MalformedReaderConditional(token))
+                    }
+                    for c in l.chunks(2) {
+                        if let Node::Keyword(ref k) = c[0] {
+                            if k.ns().is_none() && (k.name() == "lust" || k.name() == "default") {
+                                return Ok(Some(c[1].clone()))
+                            }
+                        } else {
+                            return Err(ParserError::MalformedReaderConditional(token))
+                        }
+                    }
+                }
+                Ok(None)
+            },
+            None => {
+                Err(ParserError::Unterminated(token))
+            },
+            _ => {
+                Err(ParserError::MalformedReaderConditional(token))
+            },
+        }
     }
 
-    fn parse_unquoted_splicing(&mut self) -> ParserResult {
+    /// Parses the form after a tag like `#inst` into the value it stands for.
+    fn parse_tagged(&mut self, token: Token) -> ParserResult {
         self.bump();
-        Ok(n_list![vec![n_symbol!["unquote-splicing"], try!(self.parse_expr())]])
+        let form = try!(self.parse_expr(&token));
+        match (&token, form) {
+            (&Token::Tag { ref name, .. }, Node::String(ref s)) if name == "inst" => {
+                nodes::Inst::parse(s.value())
+                    .map(Node::Inst)
+                    .ok_or_else(|| ParserError::InvalidInst(token.clone(), s.value().clone()))
+            },
+            (&Token::Tag { ref name, .. }, _) if name == "inst" => {
+                Err(ParserError::UnexpectedToken(token.clone()))
+            },
+            _ => {
+                Err(ParserError::UnknownTag(token.clone()))
+            },
+        }
     }
 
-    fn parse_syntax_quote(&mut self) -> ParserResult {
+    /// Parses the form after a reader prefix as `(name form)`.
+    fn parse_prefixed(&mut self, name: &str, token: Token) -> ParserResult {
         self.bump();
-        Ok(n_list![vec![n_symbol!["syntax-quote"], try!(self.parse_expr())]])
+        Ok(n_list![vec![n_symbol![name], try!(self.parse_expr(&token))]])
     }
 }
@@ -0,0 +1,202 @@
+use lexer::{Lexer, Token};
+use super::{Parser, ParserError, ParserResult};
+
+/// Tokens lexed ahead of the parser: where they end, as a byte offset and a number of chars
+/// from the start of the input not parsed yet and as a position, and the delimiters they
+/// leave open.
+#[derive(Clone, Copy)]
+struct Scanned {
+    at: usize,
+    chars: usize,
+    line: usize,
+    col: usize,
+    depth: usize,
+    is_open: bool,
+}
+
+/// Parses forms from input fed in chunks, like lines typed into a REPL or reads from a socket.
+///
+/// Yields every form completed by the input so far. A form cut off by the end of the input
+/// is kept until `feed` completes it or `finish` turns it into an `Unterminated` error.
+/// After a syntax error parsing goes on past the offending token. The input following a
+/// form cut off is lexed once, the parser only running when a form may be complete.
+pub struct StreamParser {
+    buf: String,
+    start: usize,
+    len: usize,
+    scanned: Option<Scanned>,
+    line: usize,
+    col: usize,
+    is_started: bool,
+    is_finished: bool,
+    is_incomplete: bool,
+}
+
+impl Iterator for StreamParser {
+    type Item = ParserResult;
+
+    fn next(&mut self) -> Option<ParserResult> {
+        self.parse()
+    }
+}
+
+impl StreamParser {
+    pub fn new() -> StreamParser {
+        StreamParser {
+            buf: String::new(),
+            start: 0,
+            len: 0,
+            scanned: None,
+            line: 1,
+            col: 1,
+            is_started: false,
+            is_finished: false,
+            is_incomplete: false,
+        }
+    }
+
+    /// Appends `chunk` to the input.
+    pub fn feed(&mut self, chunk: &str) {
+        // The parsed input is dropped once it makes half of the buffer, so that it isn't
+        // moved on every chunk
+        if self.start > self.buf.len() / 2 {
+            self.buf.drain(..self.start);
+            self.start = 0;
+        }
+        self.buf.push_str(chunk);
+        self.len += chunk.chars().count();
+    }
+
+    /// Marks the end of the input, so that a form still open is reported as an error.
+    pub fn finish(&mut self) {
+        self.is_finished = true
+    }
+
+    /// Whether the input fed so far ends inside a form.
+    pub fn is_incomplete(&self) -> bool {
+        self.is_incomplete
+    }
+
+    /// Lexes the input following the tokens scanned so far up to the end of the first form,
+    /// returning whether there is such a form, or a syntax error, for the parser to read.
+    fn scan(&mut self) -> bool {
+        let is_resumed = self.is_started || self.scanned.is_some();
+        let mut s = self.scanned.unwrap_or(Scanned {
+            at: 0,
+            chars: 0,
+            line: self.line,
+            col: self.col,
+            depth: 0,
+            is_open: false,
+        });
+        let rest = &self.buf[self.start + s.at..];
+        let mut lexer = if is_resumed {
+            Lexer::resume(rest.chars(), s.line, s.col)
+        } else {
+            Lexer::new(rest.chars())
+        };
+        let mut chars = rest.chars();
+        let mut lexed = 0;
+        let (is_form, is_cut) = loop {
+            let token = match lexer.next() {
+                Some(Ok(token)) => token,
+                Some(Err(e)) => break (!e.is_unterminated(), e.is_unterminated()),
+                None => break (false, false),
+            };
+            let end = lexer.offset();
+            let (is_atom, is_prefix) = match token {
+                Token::Number { .. } | Token::Symbol { .. } | Token::Keyword { .. } |
+                Token::Char { .. } => (true, false),
+                Token::Tag { .. } => (true, true),
+                Token::Meta { .. } | Token::VarQuote { .. } | Token::Quote { .. } |
+                Token::Unquote { .. } | Token::UnquoteSplicing { .. } | Token::SyntaxQuote { .. } |
+                Token::Discard { .. } | Token::ReaderConditional { .. } => (false, true),
+                _ => (false, false),
+            };
+            // An atom running up to the end of the input may go on in the next chunk
+            if is_atom && s.chars + end - lexed == self.len {
+                break (false, true)
+            }
+            for c in chars.by_ref().take(end - lexed) {
+                s.at += c.len_utf8();
+            }
+            s.chars += end - lexed;
+            lexed = end;
+            let (line, col) = lexer.position();
+            s.line = line;
+            s.col = col;
+            match token {
+                Token::ListStart { .. } | Token::VecStart { .. } | Token::MapStart { .. } |
+                Token::SetStart { .. } | Token::AnonFnStart { .. } => {
+                    s.depth += 1
+                },
+                Token::ListEnd { .. } | Token::VecEnd { .. } | Token::MapEnd { .. } if s.depth > 0 => {
+                    s.depth -= 1
+                },
+                _ => {},
+            }
+            s.is_open = s.depth > 0 || is_prefix;
+            if !s.is_open {
+                break (true, false)
+            }
+        };
+        self.scanned = Some(s);
+        self.is_incomplete = !is_form && (is_cut || s.is_open);
+        is_form
+    }
+
+    fn parse(&mut self) -> Option<ParserResult> {
+        if !self.is_finished && !self.scan() {
+            // Trailing whitespace and comments are kept, a comment may go on as well
+            return None
+        }
+
+        let (result, offset, (line, col), is_cut) = {
+            let src = self.buf[self.start..].chars();
+            let mut parser = if self.is_started {
+                Parser::resume(src, self.line, self.col)
+            } else {
+                Parser::new(src)
+            };
+            let result = parser.next();
+            let offset = parser.lexer.offset();
+            let is_cut = match parser.token {
+                Some(Ok(Token::Number { .. })) | Some(Ok(Token::Symbol { .. })) |
+                Some(Ok(Token::Keyword { .. })) | Some(Ok(Token::Char { .. })) => {
+                    offset == self.len
+                },
+                _ => false,
+            };
+            (result, offset, parser.lexer.position(), is_cut)
+        };
+
+        self.is_incomplete = match result {
+            Some(Ok(..)) => is_cut,
+            Some(Err(ref e)) => e.is_incomplete(),
+            None => false,
+        };
+        if !self.is_finished && (self.is_incomplete || result.is_none()) {
+            return None
+        }
+        self.is_incomplete = false;
+
+        // The lexer stops at an invalid char, which is skipped along with the rest of its token
+        let (offset, col) = match result {
+            Some(Err(ParserError::LexerError(..))) => {
+                let skipped = self.buf[self.start..].chars().skip(offset)
+                    .take_while(|c| !c.is_whitespace())
+                    .count();
+                (offset + skipped, col + skipped)
+            },
+            _ => (offset, col),
+        };
+        self.start += self.buf[self.start..].char_indices().nth(offset)
+            .map_or(self.buf.len() - self.start, |(i, _)| i);
+        self.len -= offset;
+        self.scanned = None;
+        self.line = line;
+        self.col = col;
+        self.is_started = true;
+        result
+    }
+}
@@ -1,4 +1,6 @@
-use super::Parser;
+use super::{Parser, ParserError, StreamParser};
+use lexer::LexerError;
+use ast::{Node, nodes};
 
 #[test]
 fn test_parse_number() {
@@ -24,6 +26,14 @@ fn test_parse_symbol() {
                parser.next().unwrap().ok().unwrap())
 }
 
+#[test]
+fn test_parse_nil() {
+    let mut parser = Parser::new("nil nil? user/nil".chars());
+    assert_eq!(n_nil![], parser.next().unwrap().ok().unwrap());
+    assert_eq!(n_symbol!["nil?"], parser.next().unwrap().ok().unwrap());
+    assert_eq!(n_symbol![Some("user".to_string()), "nil"], parser.next().unwrap().ok().unwrap())
+}
+
 #[test]
 fn test_parse_ns_qualified_symbol() {
     let ns = "my-ns";
@@ -133,3 +143,311 @@ fn test_parse_syntax_quoted_list() {
     let mut parser = Parser::new("`(+ 1 2)".chars());
     assert_eq!(expected_result, parser.next().unwrap().ok().unwrap());
 }
+
+#[test]
+fn test_parse_discarded_forms() {
+    let mut parser = Parser::new("#_(def a 1) [1 #_2 3] (a #_b) #_#_x y z #_w".chars());
+    assert_eq!(n_vec![vec![n_number![1.], n_number![3.]]],
+               parser.next().unwrap().ok().unwrap());
+    assert_eq!(n_list![vec![n_symbol!["a"]]],
+               parser.next().unwrap().ok().unwrap());
+    assert_eq!(n_symbol!["z"], parser.next().unwrap().ok().unwrap());
+    assert_eq!(None, parser.next());
+}
+
+#[test]
+fn test_parse_reader_conditionals() {
+    let mut parser = Parser::new("#?(:clj 1 :lust 2 :default 3) \
+                                  #?(:clj 1 :default 3) \
+                                  [#?(:clj 1)] \
+                                  '#?(:lust a)".chars());
+    assert_eq!(n_number![2.], parser.next().unwrap().ok().unwrap());
+    assert_eq!(n_number![3.], parser.next().unwrap().ok().unwrap());
+    assert_eq!(n_vec![vec![]], parser.next().unwrap().ok().unwrap());
+    assert_eq!(n_list![vec![n_symbol!["quote"], n_symbol!["a"]]],
+               parser.next().unwrap().ok().unwrap());
+}
+
+#[test]
+fn test_parse_malformed_reader_conditional() {
+    let mut parser = Parser::new("#?(:lust)".chars());
+    assert_eq!(ParserError::MalformedReaderConditional(t_reader_conditional!(span!(1, 1, 1, 3))),
+               parser.next().unwrap().err().unwrap());
+    let mut parser = Parser::new("#?[:lust 1]".chars());
+    assert_eq!(ParserError::MalformedReaderConditional(t_reader_conditional!(span!(1, 1, 1, 3))),
+               parser.next().unwrap().err().unwrap());
+}
+
+#[test]
+fn test_parse_anon_fn() {
+    let mut parser = Parser::new("#(* % 2)".chars());
+    assert_eq!(n_list![vec![n_symbol!["fn"],
+                            n_vec![vec![n_symbol!["%1"]]],
+                            n_list![vec![n_symbol!["*"], n_symbol!["%1"], n_number![2.]]]]],
+               parser.next().unwrap().ok().unwrap());
+    let mut parser = Parser::new("#(+ %2 [%1] %&)".chars());
+    assert_eq!(n_list![vec![n_symbol!["fn"],
+                            n_vec![vec![n_symbol!["%1"], n_symbol!["%2"],
+                                        n_symbol!["&"], n_symbol!["%&"]]],
+                            n_list![vec![n_symbol!["+"],
+                                         n_symbol!["%2"],
+                                         n_vec![vec![n_symbol!["%1"]]],
+                                         n_symbol!["%&"]]]]],
+               parser.next().unwrap().ok().unwrap());
+    let mut parser = Parser::new("#(rand)".chars());
+    assert_eq!(n_list![vec![n_symbol!["fn"],
+                            n_vec![vec![]],
+                            n_list![vec![n_symbol!["rand"]]]]],
+               parser.next().unwrap().ok().unwrap());
+}
+
+#[test]
+fn test_parse_anon_fn_keeps_metadata() {
+    let mut parser = Parser::new("#(f ^{:tag x} [%] (g ^:kw %))".chars());
+    let body = match parser.next().unwrap().ok().unwrap() {
+        Node::List(ref l) => l[2].clone(),
+        other => panic!("unexpected {:?}", other),
+    };
+    if let Node::List(ref body) = body {
+        assert_eq!(Some(&nodes::Map::new(vec![(n_keyword!["tag"], n_symbol!["x"])])), body[1].meta());
+        assert_eq!(Some(&nodes::Map::new(vec![(n_keyword!["line"], n_number![1.]),
+                                              (n_keyword!["column"], n_number![19.])])),
+                   body[2].meta());
+        if let Node::List(ref g) = body[2] {
+            assert_eq!(n_symbol!["%1"], g[1]);
+            assert_eq!(Some(&nodes::Map::new(vec![(n_keyword!["kw"], n_bool![true])])), g[1].meta());
+        }
+    }
+}
+
+#[test]
+fn test_parse_nested_anon_fn() {
+    let mut parser = Parser::new("#(f #(g %))".chars());
+    assert_eq!(ParserError::NestedAnonFn(t_anon_fn_start!(span!(1, 5, 1, 7))),
+               parser.next().unwrap().err().unwrap());
+}
+
+#[test]
+fn test_parse_char() {
+    let mut parser = Parser::new(r"\x".chars());
+    assert_eq!(n_char!['x'], parser.next().unwrap().ok().unwrap());
+}
+
+#[test]
+fn test_parse_regex() {
+    let mut parser = Parser::new(r#"#"(\w+)=(\d+)""#.chars());
+    assert_eq!(r#"#"(\w+)=(\d+)""#, format!("{}", parser.next().unwrap().ok().unwrap()));
+}
+
+#[test]
+fn test_parse_invalid_regex() {
+    let mut parser = Parser::new("\n  #\"(unclosed\"".chars());
+    match parser.next().unwrap() {
+        Err(ParserError::InvalidRegex(t, _)) => {
+            assert_eq!(t_regex!("(unclosed", span!(2, 4, 2, 16)), t)
+        },
+        other => {
+            panic!("unexpected {:?}", other)
+        },
+    }
+}
+
+#[test]
+fn test_parse_inst() {
+    let input = r#"#inst "2026-10-17T12:00:00Z" #inst "2026-10-17T14:00:00.250+02:00" #inst "1969-12-31"
+                   #inst "2024-02-29 23:59""#;
+    let nodes = Parser::new(input.chars()).map(|e| e.ok().unwrap()).collect::<Vec<_>>();
+    assert_eq!(vec![n_inst![1_792_238_400_000], n_inst![1_792_238_400_250], n_inst![-86_400_000],
+                    n_inst![1_709_251_140_000]],
+               nodes);
+    assert_eq!(r#"#inst "2026-10-17T12:00:00Z""#, format!("{}", nodes[0]));
+    assert_eq!(r#"#inst "2026-10-17T12:00:00.250Z""#, format!("{}", nodes[1]));
+}
+
+#[test]
+fn test_parse_invalid_inst_and_unknown_tag() {
+    let mut parser = Parser::new(r#"#inst "2026-02-29" #inst 1 #uuid "x""#.chars());
+    assert_eq!(Some(Err(ParserError::InvalidInst(t_tag!("inst", span!(1, 1, 1, 6)),
+                                                 "2026-02-29".to_string()))),
+               parser.next());
+    assert_eq!(Some(Err(ParserError::UnexpectedToken(t_tag!("inst", span!(1, 20, 1, 25))))),
+               parser.next());
+    assert_eq!(Some(Err(ParserError::UnknownTag(t_tag!("uuid", span!(1, 28, 1, 33))))),
+               parser.next());
+}
+
+#[test]
+fn test_parse_map() {
+    let mut parser = Parser::new("{:a 1 :b [x]}".chars());
+    assert_eq!(n_map![vec![(n_keyword!["a"], n_number![1.]),
+                           (n_keyword!["b"], n_vec![vec![n_symbol!["x"]]])]],
+               parser.next().unwrap().ok().unwrap());
+    let mut parser = Parser::new("{:a 1 :b}".chars());
+    assert_eq!(ParserError::MalformedMap(t_map_start!(span!(1, 1, 1, 2))),
+               parser.next().unwrap().err().unwrap());
+}
+
+#[test]
+fn test_parse_set() {
+    let mut parser = Parser::new("#{1 :a [x] 1} #{".chars());
+    let set = parser.next().unwrap().ok().unwrap();
+    assert_eq!(n_set![vec![n_number![1.], n_keyword!["a"], n_vec![vec![n_symbol!["x"]]]]], set);
+    assert_eq!("#{1 :a [x]}", format!("{}", set));
+    assert_eq!(ParserError::Unterminated(t_set_start!(span!(1, 15, 1, 17))),
+               parser.next().unwrap().err().unwrap());
+}
+
+#[test]
+fn test_parse_meta() {
+    let mut parser = Parser::new(r#"^:private ^{:doc "d" :private false} x ^String y '^:k 1"#.chars());
+    let x = parser.next().unwrap().ok().unwrap();
+    assert_eq!(n_symbol!["x"], x);
+    assert_eq!(&nodes::Map::new(vec![(n_keyword!["doc"], n_string!["d"]),
+                                     (n_keyword!["private"], n_bool![true])]),
+               x.meta().unwrap());
+    let y = parser.next().unwrap().ok().unwrap();
+    assert_eq!(&nodes::Map::new(vec![(n_keyword!["tag"], n_symbol!["String"])]),
+               y.meta().unwrap());
+    assert_eq!(ParserError::MalformedMeta(t_meta!(span!(1, 51, 1, 52))),
+               parser.next().unwrap().err().unwrap());
+}
+
+#[test]
+fn test_parse_list_records_line_and_column() {
+    let mut parser = Parser::new("\n  (f [a] (g))".chars());
+    let l = parser.next().unwrap().ok().unwrap();
+    assert_eq!(&nodes::Map::new(vec![(n_keyword!["line"], n_number![2.]),
+                                     (n_keyword!["column"], n_number![4.])]),
+               l.meta().unwrap());
+    assert_eq!(n_list![vec![n_symbol!["f"],
+                            n_vec![vec![n_symbol!["a"]]],
+                            n_list![vec![n_symbol!["g"]]]]],
+               l);
+}
+
+#[test]
+fn test_parse_var_quote() {
+    let mut parser = Parser::new("#'x".chars());
+    assert_eq!(n_list![vec![n_symbol!["var"], n_symbol!["x"]]],
+               parser.next().unwrap().ok().unwrap());
+}
+
+#[test]
+fn test_parse_unterminated_forms_as_incomplete() {
+    let mut parser = Parser::new("(def v [1 {:a".chars());
+    let err = parser.next().unwrap().err().unwrap();
+    assert_eq!(ParserError::Unterminated(t_map_start!(span!(1, 11, 1, 12))), err);
+    assert!(err.is_incomplete());
+    let mut parser = Parser::new("(f\n  \"abc)".chars());
+    let err = parser.next().unwrap().err().unwrap();
+    assert_eq!(ParserError::LexerError(LexerError::unterminated("string", span!(2, 4, 2, 5))), err);
+    assert!(err.is_incomplete());
+    let mut parser = Parser::new("'".chars());
+    assert_eq!(ParserError::Unterminated(t_quote!(span!(1, 1, 1, 2))),
+               parser.next().unwrap().err().unwrap());
+    let mut parser = Parser::new("(a))".chars());
+    assert!(parser.next().unwrap().is_ok());
+    let err = parser.next().unwrap().err().unwrap();
+    assert_eq!(ParserError::UnexpectedToken(t_list_end!(span!(1, 4, 1, 5))), err);
+    assert!(!err.is_incomplete());
+}
+
+#[test]
+fn test_stream_parser_resumes_with_fed_chunks() {
+    let mut parser = StreamParser::new();
+    parser.feed("(def a 1) (str \"x ");
+    assert_eq!(Some(Ok(n_list![vec![n_symbol!["def"], n_symbol!["a"], n_number![1.]]])),
+               parser.next());
+    assert_eq!(None, parser.next());
+    assert!(parser.is_incomplete());
+    parser.feed("y\")\n(f");
+    let l = parser.next().unwrap().ok().unwrap();
+    assert_eq!(n_list![vec![n_symbol!["str"], n_string!["x y"]]], l);
+    assert_eq!(&nodes::Map::new(vec![(n_keyword!["line"], n_number![1.]),
+                                     (n_keyword!["column"], n_number![11.])]),
+               l.meta().unwrap());
+    assert_eq!(None, parser.next());
+    parser.feed("oo) ba");
+    let l = parser.next().unwrap().ok().unwrap();
+    assert_eq!(n_list![vec![n_symbol!["foo"]]], l);
+    assert_eq!(&nodes::Map::new(vec![(n_keyword!["line"], n_number![2.]),
+                                     (n_keyword!["column"], n_number![2.])]),
+               l.meta().unwrap());
+    assert_eq!(None, parser.next());
+    assert!(parser.is_incomplete());
+    parser.feed("r [1");
+    assert_eq!(Some(Ok(n_symbol!["bar"])), parser.next());
+    assert_eq!(None, parser.next());
+    parser.finish();
+    assert_eq!(Some(Err(ParserError::Unterminated(t_vec_start!(span!(2, 12, 2, 13))))),
+               parser.next());
+    assert_eq!(None, parser.next());
+}
+
+#[test]
+fn test_stream_parser_goes_on_after_syntax_error() {
+    let mut parser = StreamParser::new();
+    parser.feed("1 ) 2\n");
+    assert_eq!(Some(Ok(n_number![1.])), parser.next());
+    assert_eq!(Some(Err(ParserError::UnexpectedToken(t_list_end!(span!(1, 3, 1, 4))))),
+               parser.next());
+    assert_eq!(Some(Ok(n_number![2.])), parser.next());
+    assert_eq!(None, parser.next());
+    assert!(!parser.is_incomplete());
+    parser.feed("(g) ] (+ 1 2) :after $x 3 ");
+    let l = parser.next().unwrap().ok().unwrap();
+    assert_eq!(&nodes::Map::new(vec![(n_keyword!["line"], n_number![2.]),
+                                     (n_keyword!["column"], n_number![2.])]),
+               l.meta().unwrap());
+    assert_eq!(Some(Err(ParserError::UnexpectedToken(t_vec_end!(span!(2, 6, 2, 7))))),
+               parser.next());
+    assert_eq!(Some(Ok(n_list![vec![n_symbol!["+"], n_number![1.], n_number![2.]]])),
+               parser.next());
+    assert_eq!(Some(Ok(n_keyword!["after"])), parser.next());
+    match parser.next() {
+        Some(Err(ParserError::LexerError(..))) => {},
+        other => panic!("unexpected {:?}", other),
+    }
+    assert_eq!(Some(Ok(n_number![3.])), parser.next());
+    assert_eq!(None, parser.next());
+}
+
+#[test]
+fn test_stream_parser_fed_a_char_at_a_time() {
+    let mut parser = StreamParser::new();
+    let input = "(a [b \"c d\"] #_x 'e) f ";
+    let mut forms = vec![];
+    for (i, c) in input.chars().enumerate() {
+        parser.feed(&c.to_string());
+        while let Some(form) = parser.next() {
+            forms.push(form.ok().unwrap());
+        }
+        assert_eq!(i < 19 || i == 21, parser.is_incomplete());
+    }
+    let quoted = n_list![vec![n_symbol!["quote"], n_symbol!["e"]]];
+    assert_eq!(vec![n_list![vec![n_symbol!["a"], n_vec![vec![n_symbol!["b"], n_string!["c d"]]], quoted]],
+                    n_symbol!["f"]],
+               forms);
+}
+
+#[test]
+fn test_stream_parser_completes_a_prefix_fed_on_its_own() {
+    let mut parser = StreamParser::new();
+    parser.feed("'");
+    assert_eq!(None, parser.next());
+    assert!(parser.is_incomplete());
+    parser.feed("(1 2)\n");
+    assert_eq!(n_list![vec![n_symbol!["quote"], n_list![vec![n_number![1.], n_number![2.]]]]],
+               parser.next().unwrap().ok().unwrap());
+    assert!(!parser.is_incomplete());
+
+    let mut parser = StreamParser::new();
+    parser.feed("^{:a 1} ");
+    assert_eq!(None, parser.next());
+    assert!(parser.is_incomplete());
+    parser.feed("[1] ");
+    let v = parser.next().unwrap().ok().unwrap();
+    assert_eq!(n_vec![vec![n_number![1.]]], v);
+    assert_eq!(&nodes::Map::new(vec![(n_keyword!["a"], n_number![1.])]), v.meta().unwrap());
+    assert!(!parser.is_incomplete());
+}
@@ -0,0 +1,595 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use super::{State, is_constant};
+use super::error::EvalError;
+use super::error::EvalError::*;
+use super::doc::is_builtin;
+use super::native::{body_of, native};
+use ast::{Node, nodes};
+use ast::nodes::Symbol;
+use chunk::{Addr, Arith, Build, BuildKind, Chunk, Dispatch, Guard, Op};
+
+/// The builtin named by `symbol` as a fn value, unless it takes some args unevaluated.
+/// `and` and `or` are values as well, short-circuiting only when called by name.
+pub(super) fn builtin_value(symbol: &Symbol) -> Option<Node> {
+    let name = &symbol.name()[..];
+    if native(name).is_some() && body_of(name).is_none() {
+        Some(Node::Builtin(Symbol::new(None, name.to_string())))
+    } else {
+        None
+    }
+}
+
+#[derive(Default)]
+struct FnScope {
+    chunk: Chunk,
+    locals: Vec<(Symbol, usize)>,
+    captured: Vec<Symbol>,
+}
+
+struct Compiler<'a> {
+    state: &'a mut State,
+    scopes: Vec<FnScope>,
+}
+
+fn has_call(node: &Node) -> bool {
+    match *node {
+        Node::Call(..) => {
+            true
+        },
+        Node::List(ref l) | Node::Vector(ref l) => {
+            l.into_iter().any(has_call)
+        },
+        Node::Map(ref m) => {
+            m.entries().iter().any(|&(ref k, ref v)| has_call(k) || has_call(v))
+        },
+        Node::Set(ref s) => {
+            s.items().iter().any(has_call)
+        },
+        _ => {
+            false
+        },
+    }
+}
+
+fn collect_symbols<'n>(node: &'n Node, symbols: &mut Vec<&'n Symbol>) {
+    match *node {
+        Node::Symbol(ref s) => {
+            symbols.push(s)
+        },
+        Node::List(ref l) | Node::Vector(ref l) => {
+            for e in l {
+                collect_symbols(e, symbols)
+            }
+        },
+        Node::Map(ref m) => {
+            for &(ref k, ref v) in m.entries() {
+                collect_symbols(k, symbols);
+                collect_symbols(v, symbols);
+            }
+        },
+        Node::Set(ref s) => {
+            for e in s.items() {
+                collect_symbols(e, symbols)
+            }
+        },
+        Node::Call(ref c) => {
+            collect_symbols(c.callee(), symbols);
+            for a in c.args() {
+                collect_symbols(a, symbols)
+            }
+        },
+        Node::Let(ref l) => {
+            for e in l.bindings().iter().chain(l.body()) {
+                collect_symbols(e, symbols)
+            }
+        },
+        Node::Fn(ref f) | Node::Macro(ref f) => {
+            for e in f.params().iter().chain(f.body().iter()) {
+                collect_symbols(e, symbols)
+            }
+        },
+        Node::Def(ref d) => {
+            collect_symbols(d.expr(), symbols)
+        },
+        Node::Case(ref c) => {
+            collect_symbols(c.expr(), symbols);
+            for &(_, ref e) in c.clauses() {
+                collect_symbols(e, symbols)
+            }
+            if let Some(e) = c.default() {
+                collect_symbols(e, symbols)
+            }
+        },
+        _ => {},
+    }
+}
+
+impl<'a> Compiler<'a> {
+    fn new(state: &'a mut State) -> Compiler<'a> {
+        Compiler {
+            state: state,
+            scopes: vec![],
+        }
+    }
+
+    fn scope(&mut self) -> &mut FnScope {
+        self.scopes.last_mut().unwrap()
+    }
+
+    fn emit(&mut self, op: Op) -> usize {
+        let code = &mut self.scope().chunk.code;
+        code.push(op);
+        code.len() - 1
+    }
+
+    /// Points the jump at `at` to the next instruction.
+    fn patch(&mut self, at: usize) {
+        let code = &mut self.scope().chunk.code;
+        let target = code.len();
+        code[at] = match code[at] {
+            Op::Jump(_) => Op::Jump(target),
+            Op::JumpIfFalse(_) => Op::JumpIfFalse(target),
+            Op::JumpIfFalseOrPop(_) => Op::JumpIfFalseOrPop(target),
+            Op::JumpIfTrueOrPop(_) => Op::JumpIfTrueOrPop(target),
+            Op::MacroGuard(f, _) => Op::MacroGuard(f, target),
+            op => op,
+        }
+    }
+
+    fn add_const(&mut self, node: Node) -> usize {
+        let consts = &mut self.scope().chunk.consts;
+        consts.push(node);
+        consts.len() - 1
+    }
+
+    fn new_slot(&mut self) -> usize {
+        let chunk = &mut self.scope().chunk;
+        chunk.slots += 1;
+        chunk.slots - 1
+    }
+
+    /// Looks `symbol` up in the fn scope at `level` and, failing that, in the enclosing
+    /// ones, capturing it into every fn in between.
+    fn resolve(&mut self, level: usize, symbol: &Symbol) -> Option<Addr> {
+        {
+            let scope = &self.scopes[level];
+            if let Some(&(_, slot)) = scope.locals.iter().rev().find(|&&(ref s, _)| s == symbol) {
+                return Some(Addr::Local(slot))
+            }
+            if let Some(i) = scope.captured.iter().position(|s| s == symbol) {
+                return Some(Addr::Capture(i))
+            }
+        }
+        if level == 0 {
+            return None
+        }
+        self.resolve(level - 1, symbol).map(|addr| {
+            let scope = &mut self.scopes[level];
+            scope.captured.push(symbol.clone());
+            scope.chunk.captures.push(addr);
+            Addr::Capture(scope.captured.len() - 1)
+        })
+    }
+
+    fn resolve_local(&mut self, symbol: &Symbol) -> Option<Addr> {
+        let level = self.scopes.len() - 1;
+        self.resolve(level, symbol)
+    }
+
+    /// Loads a global through its var when it already exists, by name until it does otherwise.
+    fn emit_load_global(&mut self, symbol: &Symbol) {
+        match self.state.find_var(symbol) {
+            Some(var) => {
+                let vars = &mut self.scope().chunk.vars;
+                vars.push((symbol.clone(), var));
+                let i = vars.len() - 1;
+                self.emit(Op::LoadVar(i));
+            },
+            None => {
+                let globals = &mut self.scope().chunk.globals;
+                globals.push((symbol.clone(), RefCell::new(None)));
+                let i = globals.len() - 1;
+                self.emit(Op::LoadGlobal(i));
+            },
+        }
+    }
+
+    fn emit_load(&mut self, addr: Addr) {
+        match addr {
+            Addr::Local(i) => self.emit(Op::LoadLocal(i)),
+            Addr::Capture(i) => self.emit(Op::LoadCapture(i)),
+        };
+    }
+
+    fn compile_top(&mut self, node: &Node) -> Result<Chunk, EvalError> {
+        self.scopes.push(FnScope::default());
+        try!(self.compile(node));
+        self.emit(Op::Return);
+        Ok(self.scopes.pop().unwrap().chunk)
+    }
+
+    fn compile(&mut self, node: &Node) -> Result<(), EvalError> {
+        match *node {
+            Node::Symbol(ref s) => {
+                match self.resolve_local(s) {
+                    Some(addr) => {
+                        self.emit_load(addr)
+                    },
+                    None => {
+                        match builtin_value(s) {
+                            Some(ref b) if self.state.find_var(s).is_none() => {
+                                let i = self.add_const(b.clone());
+                                self.emit(Op::Const(i));
+                            },
+                            _ => {
+                                self.emit_load_global(s)
+                            },
+                        }
+                    },
+                }
+            },
+            Node::Def(ref d) => {
+                self.state.intern(d.symbol());
+                try!(self.compile(d.expr()));
+                let defs = &mut self.scope().chunk.defs;
+                defs.push(d.clone());
+                let i = defs.len() - 1;
+                self.emit(Op::Def(i));
+            },
+            Node::Let(ref l) => {
+                let mark = self.scope().locals.len();
+                for c in l.bindings().chunks(2) {
+                    if let (Some(&Node::Symbol(ref s)), Some(be)) = (c.first(), c.last()) {
+                        try!(self.compile(be));
+                        let slot = self.new_slot();
+                        self.scope().locals.push((s.clone(), slot));
+                        self.emit(Op::StoreLocal(slot));
+                    }
+                }
+                try!(self.compile_body(l.body()));
+                self.scope().locals.truncate(mark);
+            },
+            Node::Case(ref c) => {
+                try!(self.compile_case(c));
+            },
+            Node::Fn(ref f) | Node::Macro(ref f) => {
+                let code = Rc::new(try!(self.compile_fn(f)));
+                let has_captures = !code.captures.is_empty();
+                let f = f.clone().with_code(code);
+                let template = if let Node::Fn(..) = *node { Node::Fn(f) } else { Node::Macro(f) };
+                if has_captures {
+                    let fns = &mut self.scope().chunk.fns;
+                    fns.push(template);
+                    let i = fns.len() - 1;
+                    self.emit(Op::Closure(i));
+                } else {
+                    let i = self.add_const(template);
+                    self.emit(Op::Const(i));
+                }
+            },
+            Node::Vector(ref v) if !is_constant(node) => {
+                for e in v {
+                    try!(self.compile(e));
+                }
+                self.emit_build(BuildKind::Vector(v.meta().cloned()), vec![None; v.len()]);
+            },
+            Node::Map(ref m) if !is_constant(node) => {
+                for &(ref k, ref v) in m.entries() {
+                    try!(self.compile(k));
+                    try!(self.compile(v));
+                }
+                self.emit_build(BuildKind::Map(m.meta().cloned()), vec![None; m.len() * 2]);
+            },
+            Node::Set(ref s) if !is_constant(node) => {
+                for e in s.items() {
+                    try!(self.compile(e));
+                }
+                self.emit_build(BuildKind::Set(s.meta().cloned()), vec![None; s.len()]);
+            },
+            Node::Call(ref c) => {
+                try!(self.compile_call(node, c));
+            },
+            _ => {
+                let i = self.add_const(node.clone());
+                self.emit(Op::Const(i));
+            },
+        }
+        Ok(())
+    }
+
+    /// Compiles the result of every clause after the jump table, each one jumping past the others.
+    fn compile_case(&mut self, c: &nodes::Case) -> Result<(), EvalError> {
+        try!(self.compile(c.expr()));
+        let i = {
+            let dispatches = &mut self.scope().chunk.dispatches;
+            dispatches.push(Dispatch { case: c.clone(), targets: vec![] });
+            dispatches.len() - 1
+        };
+        self.emit(Op::Case(i));
+        let mut targets = vec![];
+        let mut jumps = vec![];
+        for &(_, ref e) in c.clauses() {
+            targets.push(self.scope().chunk.code.len());
+            try!(self.compile(e));
+            jumps.push(self.emit(Op::Jump(0)));
+        }
+        targets.push(self.scope().chunk.code.len());
+        if let Some(e) = c.default() {
+            try!(self.compile(e));
+        }
+        for jump in jumps {
+            self.patch(jump);
+        }
+        self.scope().chunk.dispatches[i].targets = targets;
+        Ok(())
+    }
+
+    fn compile_body(&mut self, body: &[Node]) -> Result<(), EvalError> {
+        if body.is_empty() {
+            let i = self.add_const(n_nil![]);
+            self.emit(Op::Const(i));
+        }
+        for (i, e) in body.iter().enumerate() {
+            if i > 0 {
+                self.emit(Op::Pop);
+            }
+            try!(self.compile(e));
+        }
+        Ok(())
+    }
+
+    fn compile_fn(&mut self, f: &nodes::Fn) -> Result<Chunk, EvalError> {
+        self.scopes.push(FnScope::default());
+        let mut params = f.params().iter();
+        while let Some(p) = params.next() {
+            match *p {
+                Node::Symbol(ref s) if p.is_symbol("&") => {
+                    match params.next() {
+                        Some(&Node::Symbol(ref s)) => {
+                            let slot = self.new_slot();
+                            self.scope().locals.push((s.clone(), slot));
+                            self.scope().chunk.variadic = true;
+                            break
+                        },
+                        _ => {
+                            return Err(IncorrectTypeOfArgumentError(Node::Symbol(s.clone())))
+                        },
+                    }
+                },
+                Node::Symbol(ref s) => {
+                    let slot = self.new_slot();
+                    self.scope().locals.push((s.clone(), slot));
+                    self.scope().chunk.required += 1;
+                },
+                _ => {
+                    return Err(IncorrectTypeOfArgumentError(p.clone()))
+                },
+            }
+        }
+        try!(self.compile_body(f.body()));
+        self.emit(Op::Return);
+        Ok(self.scopes.pop().unwrap().chunk)
+    }
+
+    fn compile_call(&mut self, node: &Node, c: &nodes::Call) -> Result<(), EvalError> {
+        let args = c.args();
+        let symbol = match c.symbol() {
+            Some(symbol) => symbol,
+            None => {
+                try!(self.compile(c.callee()));
+                return self.emit_call(node, args)
+            },
+        };
+        if let Some(addr) = self.resolve_local(symbol) {
+            self.emit_load(addr);
+            try!(self.emit_call(node, args));
+        } else if is_builtin(symbol.name()) && !self.state.contains(symbol) {
+            try!(self.compile_builtin(node, symbol.name(), args));
+        } else {
+            self.emit_load_global(symbol);
+            let g = self.add_guard(node);
+            let guard = self.emit(Op::MacroGuard(g, 0));
+            try!(self.emit_call(node, args));
+            self.patch(guard);
+        }
+        Ok(())
+    }
+
+    /// Compiles `args` and calls the callee loaded below them.
+    fn emit_call(&mut self, node: &Node, args: &[Node]) -> Result<(), EvalError> {
+        for a in args {
+            try!(self.compile(a));
+        }
+        let i = self.add_const(node.clone());
+        self.emit(Op::Call(args.len(), i));
+        Ok(())
+    }
+
+    /// Compiles `if`, `do`, `and`, `or`, `not`, the quoting forms and the arithmetic and
+    /// comparison ops to instructions. Other builtins are called with the values of their
+    /// compiled args, the body of `with-out-str` and `with-seed` becoming a fn. Only calls of
+    /// builtins taking unevaluated forms, and special forms with wrong args, are left to the
+    /// tree-walking evaluator through `Op::Eval`.
+    fn compile_builtin(&mut self, node: &Node, name: &str, args: &[Node]) -> Result<(), EvalError> {
+        let arith = match (name, args.len()) {
+            ("if", 2) | ("if", 3) => {
+                try!(self.compile(&args[0]));
+                let jump_if_false = self.emit(Op::JumpIfFalse(0));
+                try!(self.compile(&args[1]));
+                let jump = self.emit(Op::Jump(0));
+                self.patch(jump_if_false);
+                match args.get(2) {
+                    Some(e) => try!(self.compile(e)),
+                    None => try!(self.compile(&n_nil![])),
+                }
+                self.patch(jump);
+                return Ok(())
+            },
+            ("do", _) => {
+                return self.compile_body(args)
+            },
+            ("and", 0) => {
+                return self.compile(&n_bool![true])
+            },
+            ("or", 0) => {
+                return self.compile(&n_nil![])
+            },
+            ("and", _) | ("or", _) => {
+                let jump = if name == "and" { Op::JumpIfFalseOrPop(0) } else { Op::JumpIfTrueOrPop(0) };
+                let mut jumps = vec![];
+                try!(self.compile(&args[0]));
+                for a in &args[1..] {
+                    jumps.push(self.emit(jump));
+                    try!(self.compile(a));
+                }
+                for jump in jumps {
+                    self.patch(jump);
+                }
+                return Ok(())
+            },
+            ("not", 1) => {
+                try!(self.compile(&args[0]));
+                self.emit(Op::Not);
+                return Ok(())
+            },
+            ("quote", 1) | ("syntax-quote", 1) => {
+                return self.compile_quoted(&args[0])
+            },
+            ("unquote", 1) | ("unquote-splicing", 1) => {
+                return self.compile(&args[0])
+            },
+            ("+", _) => Some(Arith::Add),
+            ("*", _) => Some(Arith::Mul),
+            ("-", n) if n > 0 => Some(Arith::Sub),
+            ("/", n) if n > 0 => Some(Arith::Div),
+            ("<", n) if n > 0 => Some(Arith::Lt),
+            (">", n) if n > 0 => Some(Arith::Gt),
+            ("<=", n) if n > 0 => Some(Arith::Le),
+            (">=", n) if n > 0 => Some(Arith::Ge),
+            ("=", n) if n > 0 => Some(Arith::Eq),
+            _ => None,
+        };
+        match arith {
+            Some(op) => {
+                for a in args {
+                    try!(self.compile(a));
+                }
+                let i = self.add_const(node.clone());
+                self.emit(Op::Arith(op, args.len(), i));
+            },
+            None => {
+                match native(name) {
+                    Some(b) => {
+                        try!(self.compile_builtin_call(node, name, b, args))
+                    },
+                    _ => {
+                        let i = self.add_const(node.clone());
+                        self.emit(Op::Eval(i));
+                    },
+                }
+            },
+        }
+        Ok(())
+    }
+
+    /// Compiles the args of the builtin `name`, the one at `b` in `NATIVES`, its body, if it
+    /// takes one, into a single fn, and calls it.
+    fn compile_builtin_call(&mut self, node: &Node, name: &str, b: usize, args: &[Node])
+                            -> Result<(), EvalError> {
+        let body = body_of(name).map(|at| at.min(args.len()));
+        let evaluated = body.unwrap_or(args.len());
+        for a in &args[..evaluated] {
+            try!(self.compile(a));
+        }
+        if let Some(at) = body {
+            try!(self.compile(&Node::Fn(nodes::Fn::new(vec![], args[at..].to_vec()))));
+        }
+        let i = self.add_const(node.clone());
+        self.emit(Op::Builtin(evaluated + body.map_or(0, |_| 1), b, i));
+        Ok(())
+    }
+
+    /// Compiles a quoted template the way `eval_quoted` evaluates it. Parts without
+    /// unquotes become constants.
+    fn compile_quoted(&mut self, node: &Node) -> Result<(), EvalError> {
+        if !has_call(node) {
+            let value = try!(self.state.eval_quoted(node));
+            let i = self.add_const(value);
+            self.emit(Op::Const(i));
+            return Ok(())
+        }
+        match *node {
+            Node::List(ref l) => {
+                self.compile_quoted_seq(l, BuildKind::List)
+            },
+            Node::Vector(ref l) => {
+                self.compile_quoted_seq(l, BuildKind::Vector(None))
+            },
+            Node::Map(ref m) => {
+                for &(ref k, ref v) in m.entries() {
+                    try!(self.compile_quoted(k));
+                    try!(self.compile_quoted(v));
+                }
+                self.emit_build(BuildKind::Map(m.meta().cloned()), vec![None; m.len() * 2]);
+                Ok(())
+            },
+            Node::Set(ref s) => {
+                self.compile_quoted_seq(s.items(), BuildKind::Set(s.meta().cloned()))
+            },
+            _ => {
+                self.compile(node)
+            },
+        }
+    }
+
+    fn compile_quoted_seq(&mut self, l: &[Node], kind: BuildKind) -> Result<(), EvalError> {
+        let mut spliced = vec![];
+        for e in l {
+            if e.is_call_of("unquote-splicing") {
+                try!(self.compile(e));
+                spliced.push(Some(e.clone()));
+            } else {
+                try!(self.compile_quoted(e));
+                spliced.push(None);
+            }
+        }
+        self.emit_build(kind, spliced);
+        Ok(())
+    }
+
+    fn emit_build(&mut self, kind: BuildKind, spliced: Vec<Option<Node>>) {
+        let builds = &mut self.scope().chunk.builds;
+        builds.push(Build { kind: kind, spliced: spliced });
+        let i = builds.len() - 1;
+        self.emit(Op::Build(i));
+    }
+
+    fn add_guard(&mut self, node: &Node) -> usize {
+        let mut symbols = vec![];
+        collect_symbols(node, &mut symbols);
+        let mut locals: Vec<(Symbol, Addr)> = vec![];
+        for s in symbols {
+            if !locals.iter().any(|&(ref l, _)| l == s) {
+                if let Some(addr) = self.resolve_local(s) {
+                    locals.push((s.clone(), addr))
+                }
+            }
+        }
+        let guards = &mut self.scope().chunk.guards;
+        guards.push(Guard { node: node.clone(), locals: locals, expansion: RefCell::new(None) });
+        guards.len() - 1
+    }
+}
+
+impl State {
+    /// Compiles a form produced by `compile` into bytecode run without arguments.
+    pub(super) fn compile_chunk(&mut self, node: &Node) -> Result<Chunk, EvalError> {
+        Compiler::new(self).compile_top(node)
+    }
+
+    /// Compiles the body of a fn that was created outside of the VM.
+    pub(super) fn compile_fn(&mut self, f: &nodes::Fn) -> Result<Chunk, EvalError> {
+        Compiler::new(self).compile_fn(f)
+    }
+}
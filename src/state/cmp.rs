@@ -0,0 +1,63 @@
+use std::cmp::Ordering;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use super::{State, EvalResult};
+use super::error::EvalError::*;
+use ast::Node;
+
+/// Whether `a` and `b` are the same object. Only lists, vectors and fns share storage
+/// when copied, so the other values fall back to equality.
+fn is_identical(a: &Node, b: &Node) -> bool {
+    match (a, b) {
+        (&Node::List(ref a), &Node::List(ref b)) | (&Node::Vector(ref a), &Node::Vector(ref b)) => {
+            a.ptr_eq(b)
+        },
+        (&Node::Fn(ref a), &Node::Fn(ref b)) | (&Node::Macro(ref a), &Node::Macro(ref b)) => {
+            a.ptr_eq(b)
+        },
+        _ => {
+            a == b
+        },
+    }
+}
+
+impl State {
+    pub(super) fn builtin_not_eq(&mut self, args: &[Node], node: &Node) -> EvalResult {
+        if args.len() >= 1 {
+            Ok(n_bool![args[1..].iter().any(|a| *a != args[0])])
+        } else {
+            Err(IncorrectNumberOfArgumentsError(node.clone()))
+        }
+    }
+
+    pub(super) fn builtin_is_identical(&mut self, args: &[Node], node: &Node) -> EvalResult {
+        if args.len() == 2 {
+            Ok(n_bool![is_identical(&args[0], &args[1])])
+        } else {
+            Err(IncorrectNumberOfArgumentsError(node.clone()))
+        }
+    }
+
+    pub(super) fn builtin_compare(&mut self, args: &[Node], node: &Node) -> EvalResult {
+        if args.len() == 2 {
+            match args[0].cmp(&args[1]) {
+                Ordering::Less => Ok(n_number![-1.]),
+                Ordering::Equal => Ok(n_number![0.]),
+                Ordering::Greater => Ok(n_number![1.]),
+            }
+        } else {
+            Err(IncorrectNumberOfArgumentsError(node.clone()))
+        }
+    }
+
+    pub(super) fn builtin_hash(&mut self, args: &[Node], node: &Node) -> EvalResult {
+        if args.len() == 1 {
+            let mut hasher = DefaultHasher::new();
+            args[0].hash(&mut hasher);
+            // Truncated so that the code fits a number exactly
+            Ok(n_number![hasher.finish() as i32 as f64])
+        } else {
+            Err(IncorrectNumberOfArgumentsError(node.clone()))
+        }
+    }
+}
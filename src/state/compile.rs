@@ -0,0 +1,535 @@
+use std::collections::HashMap;
+use super::{State, EvalResult};
+use super::error::EvalError::*;
+use super::doc::{CORE_NS, SPECIAL_FORMS, is_builtin};
+use ast::{Node, nodes};
+use ast::nodes::Symbol;
+
+impl State {
+    /// Compiles a top-level form into core forms only: macro calls are expanded, `defn` and
+    /// `defmacro` are rewritten into `def`, `when` and `cond` into `if`, and lists become `Def`,
+    /// `Fn`, `Macro`, `Let`, `Case` and `Call` nodes. Sub-forms are expanded left to right,
+    /// so a macro must be defined by an earlier top-level form to be expanded.
+    pub fn compile(&mut self, node: &Node) -> EvalResult {
+        self.expand(node)
+    }
+
+    pub(super) fn expand(&mut self, node: &Node) -> EvalResult {
+        if let Node::List(ref l) = *node {
+            if l.len() > 0 {
+                if let Node::Symbol(ref s) = l[0] {
+                    match &s.name()[..] {
+                        "def" => {
+                            return self.expand_def(node)
+                        },
+                        "defn" => {
+                            return self.expand_defn(node, "fn")
+                        },
+                        "defmacro" => {
+                            return self.expand_defn(node, "macro")
+                        },
+                        "fn" => {
+                            return self.expand_fn(node)
+                        },
+                        "macro" => {
+                            return self.expand_macro(node)
+                        },
+                        "quote" => {
+                            return self.expand_quote(node)
+                        },
+                        "syntax-quote" => {
+                            return self.expand_syntax_quote(node)
+                        },
+                        "unquote" => {
+                            return self.expand_unquote(node)
+                        },
+                        "unquote-splicing" => {
+                            return self.expand_unquote_splicing(node)
+                        },
+                        "let" => {
+                            return self.expand_let(node)
+                        },
+                        "when" if !self.is_macro_defined(s) => {
+                            let form = try!(self.when_to_if(node, false));
+                            return self.expand(&form)
+                        },
+                        "when-not" if !self.is_macro_defined(s) => {
+                            let form = try!(self.when_to_if(node, true));
+                            return self.expand(&form)
+                        },
+                        "cond" if !self.is_macro_defined(s) => {
+                            let form = try!(self.cond_to_if(node));
+                            return self.expand(&form)
+                        },
+                        "case" if !self.is_macro_defined(s) => {
+                            return self.expand_case(node)
+                        },
+                        _ => {
+                            return self.expand_call(node)
+                        }
+                    }
+                } else {
+                    return self.expand_call(node)
+                }
+            }
+        } else if let Node::Vector(ref v) = *node {
+            let mut items = vec![];
+            for e in v {
+                items.push(try!(self.expand(e)))
+            }
+            return Ok(n_vec![items].with_meta(v.meta().cloned()).unwrap())
+        } else if let Node::Map(ref m) = *node {
+            let mut entries = vec![];
+            for &(ref k, ref v) in m.entries() {
+                entries.push((try!(self.expand(k)), try!(self.expand(v))))
+            }
+            return Ok(Node::Map(nodes::Map::new(entries).with_meta(m.meta().cloned())))
+        } else if let Node::Set(ref s) = *node {
+            let mut items = vec![];
+            for e in s.items() {
+                items.push(try!(self.expand(e)))
+            }
+            return Ok(Node::Set(nodes::Set::new(items).with_meta(s.meta().cloned())))
+        }
+        Ok(node.clone())
+    }
+
+    /// Whether `symbol` names a macro, which then takes precedence over the forms that
+    /// earlier code may have defined as macros of its own, like `when` or `cond`.
+    pub(super) fn is_macro_defined(&self, symbol: &Symbol) -> bool {
+        self.get(symbol).map_or(false, |f| f.is_macro())
+    }
+
+    fn expand_quoted(&mut self, node: &Node) -> EvalResult {
+        match *node {
+            Node::List(ref l) if l.len() > 0 => {
+                if l[0].is_symbol("unquote") || l[0].is_symbol("unquote-splicing") {
+                    self.expand(node)
+                } else {
+                    let mut v = vec![];
+                    for i in l {
+                        v.push(try!(self.expand_quoted(i)));
+                    }
+                    Ok(n_list![v])
+                }
+            },
+            Node::Vector(ref l) => {
+                let mut v = vec![];
+                for i in l {
+                    v.push(try!(self.expand_quoted(i)));
+                }
+                Ok(n_vec![v])
+            },
+            Node::Map(ref m) => {
+                let mut entries = vec![];
+                for &(ref k, ref v) in m.entries() {
+                    entries.push((try!(self.expand_quoted(k)), try!(self.expand_quoted(v))))
+                }
+                Ok(Node::Map(nodes::Map::new(entries).with_meta(m.meta().cloned())))
+            },
+            Node::Set(ref s) => {
+                let mut items = vec![];
+                for i in s.items() {
+                    items.push(try!(self.expand_quoted(i)));
+                }
+                Ok(Node::Set(nodes::Set::new(items).with_meta(s.meta().cloned())))
+            },
+            _ => {
+                self.expand(node)
+            }
+        }
+    }
+
+    /// Qualifies a symbol of a syntax-quote template: `x#` becomes a gensym shared by the
+    /// whole template, special forms stay bare, and other symbols resolve through the current
+    /// namespace and its `refer` aliases before falling back to builtins.
+    fn qualify_symbol(&mut self, s: &Symbol, gensyms: &mut HashMap<String, Symbol>) -> Symbol {
+        let name = s.name();
+        let qualified = if name.len() > 1 && name.ends_with('#') {
+            if let Some(g) = gensyms.get(name) {
+                return g.clone()
+            }
+            let g = Symbol::new(None, format!("{}__{}__auto__", &name[..name.len() - 1], self.next_id()));
+            gensyms.insert(name.clone(), g.clone());
+            g
+        } else if SPECIAL_FORMS.contains(&&name[..]) || ["&", "nil", "true", "false"].contains(&&name[..]) {
+            Symbol::new(None, name.clone())
+        } else if let Some(var) = self.resolve_var(s) {
+            var
+        } else if is_builtin(name) {
+            Symbol::new(Some(CORE_NS.to_string()), name.clone())
+        } else {
+            Symbol::new(Some(self.current.clone()), name.clone())
+        };
+        qualified.with_meta(s.meta().cloned())
+    }
+
+    fn expand_syntax_quoted(&mut self,
+                            node: &Node,
+                            level: usize,
+                            gensyms: &mut Vec<HashMap<String, Symbol>>) -> EvalResult {
+        match *node {
+            Node::Symbol(ref s) if s.ns().is_none() => {
+                Ok(Node::Symbol(self.qualify_symbol(s, &mut gensyms[level - 1])))
+            },
+            Node::List(ref l) if l.len() == 2 && (l[0].is_symbol("unquote") ||
+                                                   l[0].is_symbol("unquote-splicing")) => {
+                if level == 1 {
+                    self.expand(node)
+                } else {
+                    let e = try!(self.expand_syntax_quoted(&l[1], level - 1, gensyms));
+                    Ok(n_list![vec![l[0].clone(), e]])
+                }
+            },
+            Node::List(ref l) if l.len() == 2 && l[0].is_symbol("syntax-quote") => {
+                gensyms.truncate(level);
+                gensyms.push(HashMap::new());
+                let e = try!(self.expand_syntax_quoted(&l[1], level + 1, gensyms));
+                Ok(n_list![vec![l[0].clone(), e]])
+            },
+            Node::List(ref l) => {
+                let mut v = vec![];
+                for i in l {
+                    v.push(try!(self.expand_syntax_quoted(i, level, gensyms)));
+                }
+                Ok(n_list![v])
+            },
+            Node::Vector(ref l) => {
+                let mut v = vec![];
+                for i in l {
+                    v.push(try!(self.expand_syntax_quoted(i, level, gensyms)));
+                }
+                Ok(n_vec![v])
+            },
+            Node::Map(ref m) => {
+                let mut entries = vec![];
+                for &(ref k, ref v) in m.entries() {
+                    entries.push((try!(self.expand_syntax_quoted(k, level, gensyms)),
+                                  try!(self.expand_syntax_quoted(v, level, gensyms))))
+                }
+                Ok(Node::Map(nodes::Map::new(entries).with_meta(m.meta().cloned())))
+            },
+            Node::Set(ref s) => {
+                let mut items = vec![];
+                for i in s.items() {
+                    items.push(try!(self.expand_syntax_quoted(i, level, gensyms)));
+                }
+                Ok(Node::Set(nodes::Set::new(items).with_meta(s.meta().cloned())))
+            },
+            _ => {
+                self.expand(node)
+            }
+        }
+    }
+
+    fn expand_def(&mut self, node: &Node) -> EvalResult {
+        self.expand_def_from(node, node)
+    }
+
+    fn expand_def_from(&mut self, node: &Node, source: &Node) -> EvalResult {
+        if let Node::List(ref l) = *node {
+            let (doc, expr) = match l.len() {
+                3 => {
+                    (None, &l[2])
+                },
+                4 => {
+                    if let Node::String(..) = l[2] {
+                        (Some(l[2].clone()), &l[3])
+                    } else {
+                        return Err(IncorrectTypeOfArgumentError(l[2].clone()))
+                    }
+                },
+                _ => {
+                    return Err(IncorrectNumberOfArgumentsError(node.clone()))
+                },
+            };
+            if let Node::Symbol(ref s) = l[1] {
+                let mut meta = s.meta().cloned().unwrap_or_else(|| nodes::Map::new(vec![]));
+                if let Some(doc) = doc {
+                    meta.insert(n_keyword!["doc"], doc);
+                }
+                if let Some(m) = l.meta() {
+                    meta.merge(m);
+                }
+                meta.insert(n_keyword!["file"], n_string![self.file.clone()]);
+                let expr = try!(self.expand(expr));
+                let def = nodes::Def::new(Symbol::new(None, s.name().clone()), expr);
+                Ok(Node::Def(def.with_meta(Some(meta)).with_source(source.clone())))
+            } else {
+                Err(IncorrectTypeOfArgumentError(l[1].clone()))
+            }
+        } else {
+            Err(DispatchError(node.clone()))
+        }
+    }
+
+    /// Expands `(defn name doc? [params] body)` into a `def` of a `kind` form, recording `:arglists`.
+    fn expand_defn(&mut self, node: &Node, kind: &str) -> EvalResult {
+        let def = try!(self.defn_to_def(node, kind));
+        self.expand_def_from(&def, node)
+    }
+
+    /// Rewrites `(defn name doc? [params] body*)` into `(def name doc? (fn [params] body*))`.
+    pub(super) fn defn_to_def(&self, node: &Node, kind: &str) -> EvalResult {
+        if let Node::List(ref l) = *node {
+            if l.len() >= 3 {
+                let mut rest = &l[2..];
+                let doc = match rest[0] {
+                    Node::String(..) if rest.len() > 1 => {
+                        let doc = rest[0].clone();
+                        rest = &rest[1..];
+                        Some(doc)
+                    },
+                    _ => {
+                        None
+                    },
+                };
+                let name = match (&l[1], &rest[0]) {
+                    (&Node::Symbol(ref s), &Node::Vector(..)) => {
+                        let mut meta = s.meta().cloned().unwrap_or_else(|| nodes::Map::new(vec![]));
+                        meta.insert(n_keyword!["arglists"], n_list![vec![rest[0].clone()]]);
+                        Node::Symbol(s.clone().with_meta(Some(meta)))
+                    },
+                    (&Node::Symbol(..), _) => {
+                        return Err(IncorrectTypeOfArgumentError(rest[0].clone()))
+                    },
+                    _ => {
+                        return Err(IncorrectTypeOfArgumentError(l[1].clone()))
+                    },
+                };
+                let mut f = vec![n_symbol![kind]];
+                f.extend(rest.iter().cloned());
+                let mut def = vec![n_symbol!["def"], name];
+                def.extend(doc);
+                def.push(n_list![f]);
+                Ok(n_list![def].with_meta(l.meta().cloned()).unwrap())
+            } else {
+                Err(IncorrectNumberOfArgumentsError(node.clone()))
+            }
+        } else {
+            Err(DispatchError(node.clone()))
+        }
+    }
+
+    fn expand_fn(&mut self, node: &Node) -> EvalResult {
+        if let Node::List(ref l) = *node {
+            if l.len() >= 3 {
+                if let Node::Vector(ref params) = l[1] {
+                    let mut fn_params = vec![];
+                    for p in params {
+                        fn_params.push(try!(self.expand(p)))
+                    }
+                    let mut fn_body = vec![];
+                    for be in &l[2..] {
+                        fn_body.push(try!(self.expand(be)))
+                    }
+                    Ok(n_fn![fn_params, fn_body])
+                } else {
+                    Err(IncorrectTypeOfArgumentError(l[1].clone()))
+                }
+            } else {
+                Err(IncorrectNumberOfArgumentsError(node.clone()))
+            }
+        } else {
+            Err(DispatchError(node.clone()))
+        }
+    }
+
+    fn expand_macro(&mut self, node: &Node) -> EvalResult {
+        if let Node::List(ref l) = *node {
+            if l.len() >= 3 {
+                if let Node::Vector(ref params) = l[1] {
+                    let mut macro_params = vec![];
+                    for p in params {
+                        macro_params.push(try!(self.expand(p)))
+                    }
+                    let mut macro_body = vec![];
+                    for be in &l[2..] {
+                        macro_body.push(try!(self.expand(be)))
+                    }
+                    Ok(n_macro![macro_params, macro_body])
+                } else {
+                    Err(IncorrectTypeOfArgumentError(l[1].clone()))
+                }
+            } else {
+                Err(IncorrectNumberOfArgumentsError(node.clone()))
+            }
+        } else {
+            Err(DispatchError(node.clone()))
+        }
+    }
+
+    fn expand_quote(&mut self, node: &Node) -> EvalResult {
+        if let Node::List(ref l) = *node {
+            if l.len() == 2 {
+                Ok(n_call!["quote", vec![try!(self.expand_quoted(&l[1]))]])
+            } else {
+                Err(IncorrectNumberOfArgumentsError(node.clone()))
+            }
+        } else {
+            Err(DispatchError(node.clone()))
+        }
+    }
+
+    fn expand_syntax_quote(&mut self, node: &Node) -> EvalResult {
+        if let Node::List(ref l) = *node {
+            if l.len() == 2 {
+                let ref mut gensyms = vec![HashMap::new()];
+                Ok(n_call!["syntax-quote", vec![try!(self.expand_syntax_quoted(&l[1], 1, gensyms))]])
+            } else {
+                Err(IncorrectNumberOfArgumentsError(node.clone()))
+            }
+        } else {
+            Err(DispatchError(node.clone()))
+        }
+    }
+
+    fn expand_unquote(&mut self, node: &Node) -> EvalResult {
+        if let Node::List(ref l) = *node {
+            if l.len() == 2 {
+                Ok(n_call!["unquote", vec![try!(self.expand(&l[1]))]])
+            } else {
+                Err(IncorrectNumberOfArgumentsError(node.clone()))
+            }
+        } else {
+            Err(DispatchError(node.clone()))
+        }
+    }
+
+    fn expand_unquote_splicing(&mut self, node: &Node) -> EvalResult {
+        if let Node::List(ref l) = *node {
+            if l.len() == 2 {
+                Ok(n_call!["unquote-splicing", vec![try!(self.expand(&l[1]))]])
+            } else {
+                Err(IncorrectNumberOfArgumentsError(node.clone()))
+            }
+        } else {
+            Err(DispatchError(node.clone()))
+        }
+    }
+
+    fn expand_let(&mut self, node: &Node) -> EvalResult {
+        if let Node::List(ref l) = *node {
+            if l.len() >= 3 {
+                if let Node::Vector(ref v) = l[1] {
+                    if v.len() % 2 == 0 {
+                        let mut let_bindings = vec![];
+                        for c in v.chunks(2) {
+                            if let Some(s @ &Node::Symbol(..)) = c.first() {
+                                let_bindings.push(s.clone())
+                            } else {
+                                return Err(IncorrectTypeOfArgumentError(node.clone()))
+                            }
+                            if let Some(ref e) = c.last() {
+                                let_bindings.push(try!(self.expand(e)))
+                            } else {
+                                return Err(IncorrectTypeOfArgumentError(node.clone()))
+                            }
+                        }
+                        let mut let_body = vec![];
+                        for be in &l[2..] {
+                            let_body.push(try!(self.expand(be)))
+                        }
+                        Ok(n_let![let_bindings, let_body])
+                    } else {
+                        Err(IncorrectNumberOfArgumentsError(node.clone()))
+                    }
+                } else {
+                    Err(IncorrectTypeOfArgumentError(node.clone()))
+                }
+            } else {
+                Err(IncorrectNumberOfArgumentsError(node.clone()))
+            }
+        } else {
+            Err(DispatchError(node.clone()))
+        }
+    }
+
+    /// Rewrites `(when test body*)` into `(if test (do body*))`, or `(if test nil (do body*))`
+    /// when `is_negated`.
+    pub(super) fn when_to_if(&self, node: &Node, is_negated: bool) -> EvalResult {
+        if let Node::List(ref l) = *node {
+            if l.len() >= 2 {
+                let mut body = vec![n_symbol!["do"]];
+                body.extend(l[2..].iter().cloned());
+                let mut form = vec![n_symbol!["if"], l[1].clone()];
+                if is_negated {
+                    form.push(n_nil![]);
+                }
+                form.push(n_list![body]);
+                Ok(n_list![form].with_meta(l.meta().cloned()).unwrap())
+            } else {
+                Err(IncorrectNumberOfArgumentsError(node.clone()))
+            }
+        } else {
+            Err(DispatchError(node.clone()))
+        }
+    }
+
+    /// Rewrites `(cond test expr*)` into nested `if`s, returning nil when no test holds.
+    /// An `:else` test always holds, so the clauses after it are dropped.
+    pub(super) fn cond_to_if(&self, node: &Node) -> EvalResult {
+        if let Node::List(ref l) = *node {
+            if l.len() % 2 == 1 {
+                let mut form = n_nil![];
+                for c in l[1..].chunks(2).rev() {
+                    form = if c[0] == n_keyword!["else"] {
+                        c[1].clone()
+                    } else {
+                        n_list![vec![n_symbol!["if"], c[0].clone(), c[1].clone(), form]]
+                    };
+                }
+                Ok(form)
+            } else {
+                Err(IncorrectNumberOfArgumentsError(node.clone()))
+            }
+        } else {
+            Err(DispatchError(node.clone()))
+        }
+    }
+
+    /// Builds a `Case` from `(case expr test result* default?)`. Tests are constants taken
+    /// as written, so they are not expanded.
+    fn expand_case(&mut self, node: &Node) -> EvalResult {
+        if let Node::List(ref l) = *node {
+            if l.len() >= 2 {
+                let expr = try!(self.expand(&l[1]));
+                let mut clauses = vec![];
+                let mut default = None;
+                for c in l[2..].chunks(2) {
+                    if c.len() == 2 {
+                        clauses.push((c[0].clone(), try!(self.expand(&c[1]))))
+                    } else {
+                        default = Some(try!(self.expand(&c[0])))
+                    }
+                }
+                nodes::Case::new(expr, clauses, default)
+                    .map(Node::Case)
+                    .map_err(IncorrectTypeOfArgumentError)
+            } else {
+                Err(IncorrectNumberOfArgumentsError(node.clone()))
+            }
+        } else {
+            Err(DispatchError(node.clone()))
+        }
+    }
+
+    fn expand_call(&mut self, node: &Node) -> EvalResult {
+        if let Node::List(ref l) = *node {
+            if let Node::Symbol(ref s) = l[0] {
+                if let Some(Node::Macro(f)) = self.get(s) {
+                    let expansion = try!(self.apply_macro(&f, node, &l[1..], l.meta()));
+                    return self.expand(&expansion)
+                }
+            }
+            let callee = try!(self.expand(&l[0]));
+            let mut args = vec![];
+            for a in &l[1..] {
+                args.push(try!(self.expand(a)))
+            }
+            Ok(Node::Call(nodes::Call::new(callee, args)))
+        } else {
+            Err(DispatchError(node.clone()))
+        }
+    }
+}
@@ -0,0 +1,555 @@
+use std::fs::File;
+use std::io::Read;
+use regex;
+use super::{State, EvalResult};
+use super::error::EvalError::*;
+use super::error::EvalError;
+use ast::{Node, nodes};
+use ast::nodes::{Symbol, Stream};
+use parser::Parser;
+
+pub const CORE_NS: &'static str = "lust.core";
+
+pub const SPECIAL_FORMS: &'static [&'static str] = &[
+    "def", "defn", "defmacro", "fn", "macro", "let", "if", "do", "when", "when-not", "cond",
+    "case", "and", "or", "quote", "syntax-quote", "unquote", "unquote-splicing", "var",
+];
+
+/// Name, argument lists and docstring of every builtin and special form.
+pub const BUILTINS: &'static [(&'static str, &'static str, &'static str)] = &[
+    ("def", "([name expr] [name doc expr])",
+     "Binds name to the value of expr in the current namespace, recording doc,\n\
+      :file, :line, :ns and :name as the var metadata."),
+    ("defn", "([name doc? [params*] body*])",
+     "Same as (def name doc? (fn [params*] body*)), recording :arglists."),
+    ("defmacro", "([name doc? [params*] body*])",
+     "Same as (def name doc? (macro [params*] body*)), recording :arglists."),
+    ("fn", "([[params*] body*])",
+     "Creates a function. A & before the last param collects remaining args in a list."),
+    ("macro", "([[params*] body*])",
+     "Creates a macro, a function of unevaluated forms whose result is evaluated in\n\
+      place of the call."),
+    ("let", "([[bindings*] body*])",
+     "Evaluates body with each symbol in bindings bound to the value of its expression."),
+    ("if", "([test then] [test then else])",
+     "Evaluates then if test is neither nil nor false, otherwise else, or returns nil\n\
+      when there is no else."),
+    ("do", "([exprs*])",
+     "Evaluates the exprs in order and returns the value of the last one, or nil."),
+    ("when", "([test body*])",
+     "Same as (if test (do body*))."),
+    ("when-not", "([test body*])",
+     "Same as (if test nil (do body*))."),
+    ("cond", "([test expr*])",
+     "Evaluates the tests in order and returns the value of the expr following the\n\
+      first one that holds, or nil. :else as the last test always holds."),
+    ("case", "([expr test result* default?])",
+     "Returns the result whose test is equal to the value of expr, or default. Tests are\n\
+      unevaluated constants, a list of them matching any. Fails without a default when\n\
+      no test matches. The clause is found in constant time whatever their number."),
+    ("quote", "([form])",
+     "Returns form unevaluated, except for unquoted parts. Same as 'form."),
+    ("syntax-quote", "([form])",
+     "Returns form unevaluated with symbols qualified by the namespace they resolve to,\n\
+      builtins by lust.core, and x# replaced by a gensym unique to the template.\n\
+      Same as `form."),
+    ("unquote", "([form])",
+     "Evaluates form inside a quoted form. Same as ~form."),
+    ("unquote-splicing", "([form])",
+     "Evaluates form inside a quoted list and splices its elements in. Same as ~@form."),
+    ("var", "([symbol])",
+     "Returns the var the symbol resolves to. Same as #'symbol."),
+    ("+", "([& xs])",
+     "Returns the sum of xs, 0 when called without args."),
+    ("-", "([x & ys])",
+     "Returns x minus the sum of ys, or the negation of x when called with one arg."),
+    ("*", "([& xs])",
+     "Returns the product of xs, 1 when called without args."),
+    ("/", "([x & ys])",
+     "Returns x divided by each of ys in turn, or 1/x when called with one arg.\n\
+      Fails when dividing by zero."),
+    ("<", "([x & ys])",
+     "Returns true if the numbers, or instants, are in monotonically increasing order."),
+    (">", "([x & ys])",
+     "Returns true if the numbers, or instants, are in monotonically decreasing order."),
+    ("=", "([x & ys])",
+     "Returns true if all the values are equal. Collections are equal when their elements are,\n\
+      maps regardless of the order of their entries. Metadata is ignored."),
+    ("<=", "([x & ys])",
+     "Returns true if the numbers, or instants, are in monotonically non-decreasing order."),
+    (">=", "([x & ys])",
+     "Returns true if the numbers, or instants, are in monotonically non-increasing order."),
+    ("inc", "([x])",
+     "Returns x plus one."),
+    ("dec", "([x])",
+     "Returns x minus one."),
+    ("quot", "([num div])",
+     "Returns num divided by div, truncated towards zero. Fails when div is zero."),
+    ("rem", "([num div])",
+     "Returns the remainder of dividing num by div, with the sign of num."),
+    ("mod", "([num div])",
+     "Returns num modulo div, with the sign of div."),
+    ("abs", "([x])",
+     "Returns the absolute value of x."),
+    ("min", "([x & ys])",
+     "Returns the least of the numbers."),
+    ("max", "([x & ys])",
+     "Returns the greatest of the numbers."),
+    ("zero?", "([x])",
+     "Returns true if the number x is zero."),
+    ("pos?", "([x])",
+     "Returns true if the number x is greater than zero."),
+    ("neg?", "([x])",
+     "Returns true if the number x is less than zero."),
+    ("even?", "([n])",
+     "Returns true if the integer n is even."),
+    ("odd?", "([n])",
+     "Returns true if the integer n is odd."),
+    ("sqrt", "([x])",
+     "Returns the square root of x."),
+    ("pow", "([x y])",
+     "Returns x raised to the power y."),
+    ("exp", "([x])",
+     "Returns E raised to the power x."),
+    ("log", "([x])",
+     "Returns the natural logarithm of x."),
+    ("log10", "([x])",
+     "Returns the base 10 logarithm of x."),
+    ("sin", "([x])",
+     "Returns the sine of the angle x in radians."),
+    ("cos", "([x])",
+     "Returns the cosine of the angle x in radians."),
+    ("tan", "([x])",
+     "Returns the tangent of the angle x in radians."),
+    ("asin", "([x])",
+     "Returns the arc sine of x, in radians."),
+    ("acos", "([x])",
+     "Returns the arc cosine of x, in radians."),
+    ("atan", "([x])",
+     "Returns the arc tangent of x, in radians."),
+    ("atan2", "([y x])",
+     "Returns the angle in radians of the point (x, y) from the positive x axis."),
+    ("floor", "([x])",
+     "Returns the greatest integer not greater than x."),
+    ("ceil", "([x])",
+     "Returns the least integer not less than x."),
+    ("round", "([x])",
+     "Returns the integer closest to x, rounding halves away from zero."),
+    ("bit-and", "([x y & more])",
+     "Returns the bitwise and of the integers."),
+    ("bit-or", "([x y & more])",
+     "Returns the bitwise or of the integers."),
+    ("bit-xor", "([x y & more])",
+     "Returns the bitwise exclusive or of the integers."),
+    ("bit-not", "([x])",
+     "Returns the bitwise complement of the integer x."),
+    ("bit-shift-left", "([x n])",
+     "Returns the integer x shifted left by n bits."),
+    ("bit-shift-right", "([x n])",
+     "Returns the integer x shifted right by n bits, keeping its sign."),
+    ("rand", "([] [n])",
+     "Returns a random number between 0 (inclusive) and n (exclusive, 1 by default)."),
+    ("rand-int", "([n])",
+     "Returns a random integer between 0 (inclusive) and n (exclusive)."),
+    ("rand-nth", "([coll])",
+     "Returns a random element of the non-empty coll."),
+    ("shuffle", "([coll])",
+     "Returns a vector of the elements of coll in random order."),
+    ("set-seed!", "([seed])",
+     "Restarts the random numbers from the integer seed, the same seed giving the same\n\
+      numbers from then on."),
+    ("with-seed", "([seed & body])",
+     "Evaluates body with random numbers started from the integer seed, then resumes\n\
+      the numbers from before."),
+    ("not=", "([x & ys])",
+     "Same as (not (= x & ys))."),
+    ("identical?", "([x y])",
+     "Returns true if x and y are the same object. Lists, vectors and fns must share their\n\
+      storage, other values are compared like with =."),
+    ("compare", "([x y])",
+     "Returns -1, 0 or 1 as x is less than, equal to or greater than y. Values of different\n\
+      types are ordered nil, numbers, booleans, strings, chars, instants, regexes, symbols,\n\
+      keywords, then lists, vectors and maps, so any values can be sorted together."),
+    ("hash", "([x])",
+     "Returns the hash code of x, the same for values that are =."),
+    ("now", "([])",
+     "Returns the current instant, read from the system clock."),
+    ("inst?", "([x])",
+     "Returns true if x is an instant."),
+    ("inst-ms", "([inst])",
+     "Returns the number of milliseconds between 1970-01-01T00:00:00Z and inst."),
+    ("parse-inst", "([s])",
+     "Returns the instant of the ISO-8601 timestamp s, like \"2026-10-17T12:00:00.5+02:00\".\n\
+      Everything after the year is optional and a missing offset means UTC.\n\
+      #inst \"...\" reads the same timestamps."),
+    ("format-inst", "([inst format])",
+     "Returns inst formatted in UTC with strftime-style directives, like \"%Y-%m-%d %H:%M:%S\".\n\
+      %f gives the milliseconds. Fails on an unknown directive."),
+    ("duration", "([n unit])",
+     "Returns n units as a number of milliseconds, the unit being one of :ms, :seconds,\n\
+      :minutes, :hours, :days or :weeks."),
+    ("inst-add", "([inst & durations])",
+     "Returns inst moved forward by the durations, integral numbers of milliseconds which may\n\
+      be negative."),
+    ("inst-diff", "([a b])",
+     "Returns the number of milliseconds from instant b to instant a."),
+    ("eval", "([form])",
+     "Evaluates form and then evaluates the result."),
+    ("apply", "([f args] [f x & args])",
+     "Calls f with the leading args followed by the elements of the last one, a list or vector."),
+    ("gensym", "([prefix])",
+     "Returns a new symbol with a unique name starting with the prefix string."),
+    ("in-ns", "([ns])",
+     "Switches the current namespace to the symbol ns, returning the previous one."),
+    ("load", "([path])",
+     "Evaluates every form of the file at path, returning the last value."),
+    ("refer", "([alias target])",
+     "Makes the qualified symbol target available as alias in the current namespace."),
+    ("char", "([x])",
+     "Returns the character with code x."),
+    ("int", "([x])",
+     "Returns the code of the character x, or the number x truncated to an integer."),
+    ("char?", "([x])",
+     "Returns true if x is a character."),
+    ("str", "([& xs])",
+     "Returns the concatenation of the printed representations of xs."),
+    ("seq", "([coll])",
+     "Returns a list of the elements of coll. Strings yield their characters."),
+    ("and", "([exprs*])",
+     "Evaluates the exprs in order, stopping at the first nil or false, and returns the\n\
+      value of the last one evaluated, or true when there are none."),
+    ("or", "([exprs*])",
+     "Evaluates the exprs in order, stopping at the first that is neither nil nor false,\n\
+      and returns the value of the last one evaluated, or nil when there are none."),
+    ("not", "([x])",
+     "Returns true if x is nil or false, otherwise false."),
+    ("xor", "([& xs])",
+     "Returns true if an odd number of xs are neither nil nor false, so (xor a b) is true\n\
+      when exactly one of a and b is."),
+    ("some-fn", "([p & ps])",
+     "Returns a fn of any number of args returning the first value of (p x) that is neither\n\
+      nil nor false, for each pred p and arg x, or nil."),
+    ("every-pred", "([p & ps])",
+     "Returns a fn of any number of args returning true if (p x) is neither nil nor false\n\
+      for every pred p and arg x, otherwise false."),
+    ("some", "([pred coll])",
+     "Returns the first value of (pred x) for the elements x of coll that is neither nil\n\
+      nor false, or nil."),
+    ("every?", "([pred coll])",
+     "Returns true if (pred x) is neither nil nor false for every element x of coll."),
+    ("first", "([coll])",
+     "Returns the first element of coll, or nil when coll is empty or nil."),
+    ("nil?", "([x])",
+     "Returns true if x is nil."),
+    ("some?", "([x])",
+     "Returns true if x is not nil."),
+    ("boolean", "([x])",
+     "Returns false if x is nil or false, otherwise true."),
+    ("re-pattern", "([s])",
+     "Returns a regex compiled from the string s."),
+    ("re-find", "([re s])",
+     "Returns the first match of re in s, or a vector of the match and its groups\n\
+      when re has groups. Returns nil when there is no match."),
+    ("re-matches", "([re s])",
+     "Like re-find, but re must match the whole of s."),
+    ("re-seq", "([re s])",
+     "Returns a list of all successive matches of re in s."),
+    ("re-groups", "([re s])",
+     "Returns a vector of the first match of re in s followed by its groups."),
+    ("re-replace", "([re s replacement])",
+     "Replaces every match of re in s. $1 in replacement refers to the first group."),
+    ("macroexpand-1", "([form])",
+     "Expands form once when it is a macro call, otherwise returns form unchanged."),
+    ("macroexpand", "([form])",
+     "Repeatedly expands form until it is no longer a macro call."),
+    ("macroexpand-all", "([form])",
+     "Expands every macro call within form. Quoted forms are left untouched."),
+    ("meta", "([obj])",
+     "Returns the metadata of obj, or of the definition when obj is a var, or nil."),
+    ("with-meta", "([obj m])",
+     "Returns obj with its metadata replaced by the map m."),
+    ("vary-meta", "([obj f & args])",
+     "Returns obj with metadata (apply f (meta obj) args)."),
+    ("get", "([coll key] [coll key not-found])",
+     "Returns the value of key in the map, key itself if it is in the set, or the\n\
+      element at index key in the vector, or not-found (nil by default)."),
+    ("assoc", "([map key val & kvs])",
+     "Returns map with each key bound to its val."),
+    ("dissoc", "([map & keys])",
+     "Returns map without the keys."),
+    ("doc", "([name])",
+     "Prints the argument lists and docstring of the var or builtin called name."),
+    ("find-doc", "([re])",
+     "Prints the documentation of every var and builtin whose name or docstring\n\
+      matches the string or regex re."),
+    ("apropos", "([re])",
+     "Returns a list of the qualified names of vars and builtins matching the string\n\
+      or regex re."),
+    ("source", "([name])",
+     "Prints the source of the definition of name."),
+    ("dir", "([ns])",
+     "Prints the sorted names of the public vars of the namespace ns."),
+    ("exit", "([] [status])",
+     "Terminates the program with status, 0 by default."),
+    ("getenv", "([] [name])",
+     "Returns the value of the environment variable name, or nil if it isn't set.\n\
+      Without args returns a map of all the variables."),
+    ("setenv", "([name value])",
+     "Sets the environment variable name to the string value for this process and the\n\
+      programs it runs, or unsets it when value is nil."),
+    ("cwd", "([])",
+     "Returns the current working directory."),
+    ("sh", "([program & args-and-opts])",
+     "Runs program with the string args and waits for it to exit, returning a map of its\n\
+      :exit status, nil if it was killed by a signal, and of what it wrote to :out and :err.\n\
+      Options follow the args: :in a string fed to its standard input, :env a map of\n\
+      variables to set on top of the inherited ones, nil values unsetting them, and :dir\n\
+      its working directory. Fails only when the program can't be run."),
+    ("print", "([& xs] [stream & xs])",
+     "Prints xs to stream, *out* by default, separated by spaces, strings and chars\n\
+      without quotes."),
+    ("println", "([& xs] [stream & xs])",
+     "Same as print followed by a newline."),
+    ("pr", "([& xs] [stream & xs])",
+     "Prints xs to stream, *out* by default, separated by spaces, readably."),
+    ("prn", "([& xs] [stream & xs])",
+     "Same as pr followed by a newline."),
+    ("printf", "([fmt & args] [stream fmt & args])",
+     "Prints args to stream, *out* by default, formatted by fmt. Supports %s, %d, %f,\n\
+      %.Nf, %n and %%."),
+    ("read-line", "([] [stream])",
+     "Reads the next line from *in* without the line terminator, or nil at the end."),
+    ("flush", "([] [stream])",
+     "Flushes *out* or the given stream."),
+    ("with-out-str", "([& body])",
+     "Evaluates body with *out* bound to a buffer and returns what was printed."),
+];
+
+fn builtin_doc(name: &str) -> Option<&'static (&'static str, &'static str, &'static str)> {
+    BUILTINS.iter().find(|b| b.0 == name)
+}
+
+pub fn is_builtin(name: &str) -> bool {
+    builtin_doc(name).is_some()
+}
+
+fn format_doc(name: &str, arglists: Option<String>, doc: Option<&str>, is_special: bool) -> String {
+    let mut res = "-------------------------\n".to_string();
+    res.push_str(name);
+    res.push('\n');
+    if let Some(a) = arglists {
+        res.push_str(&a);
+        res.push('\n');
+    }
+    if is_special {
+        res.push_str("Special Form\n");
+    }
+    if let Some(d) = doc {
+        for line in d.lines() {
+            res.push_str("  ");
+            res.push_str(line.trim());
+            res.push('\n');
+        }
+    }
+    res
+}
+
+fn format_var_doc(var: &Symbol, meta: &nodes::Map) -> String {
+    let arglists = meta.get(&n_keyword!["arglists"]).map(|a| format!("{}", a));
+    let doc = match meta.get(&n_keyword!["doc"]) {
+        Some(&Node::String(ref s)) => Some(s.value().clone()),
+        _ => None,
+    };
+    format_doc(&format!("{}", var), arglists, doc.as_ref().map(|d| &d[..]), false)
+}
+
+fn format_builtin_doc(b: &(&str, &str, &str)) -> String {
+    format_doc(&format!("{}/{}", CORE_NS, b.0),
+               Some(b.1.to_string()),
+               Some(b.2),
+               SPECIAL_FORMS.iter().any(|s| *s == b.0))
+}
+
+/// Reads a single form from `file`, starting at the given position of the lexer.
+fn read_source(file: &str, line: usize, col: usize) -> Option<String> {
+    let mut buf = String::new();
+    if File::open(file).and_then(|mut f| f.read_to_string(&mut buf)).is_err() {
+        return None
+    }
+    // the lexer counts columns from 2 on every line but the first
+    let skip = if line == 1 { col - 1 } else { col - 2 };
+    let mut src = String::new();
+    for (i, l) in buf.lines().skip(line - 1).enumerate() {
+        if i == 0 {
+            src.extend(l.chars().skip(skip));
+        } else {
+            src.push_str(l);
+        }
+        src.push('\n');
+        if let Some(Ok(..)) = Parser::new(src.chars()).next() {
+            return Some(src.trim_end().to_string())
+        }
+    }
+    None
+}
+
+fn as_number(node: Option<&Node>) -> Option<usize> {
+    if let Some(&Node::Number(ref n)) = node {
+        Some(n.value() as usize)
+    } else {
+        None
+    }
+}
+
+/// The value of `arg` as a regex, a string matching itself.
+fn pattern_arg(arg: &Node) -> Result<regex::Regex, EvalError> {
+    match *arg {
+        Node::Regex(ref r) => {
+            Ok(r.value().clone())
+        },
+        Node::String(ref s) => {
+            regex::Regex::new(&regex::escape(s.value()))
+                .map_err(|_| IncorrectTypeOfArgumentError(arg.clone()))
+        },
+        _ => {
+            Err(IncorrectTypeOfArgumentError(arg.clone()))
+        },
+    }
+}
+
+impl State {
+    /// Collects every var with its metadata.
+    fn vars(&self) -> Vec<(Symbol, nodes::Map)> {
+        let mut vars = self.meta.iter().map(|(k, v)| (k.clone(), v.clone())).collect::<Vec<_>>();
+        vars.sort_by(|a, b| format!("{}", a.0).cmp(&format!("{}", b.0)));
+        vars
+    }
+
+    fn get_source(&self, symbol: &Symbol) -> Option<&Node> {
+        self.sources.get(symbol)
+    }
+
+    fn eval_symbol_arg<'a>(&self, node: &'a Node) -> Result<&'a Symbol, EvalError> {
+        if let Node::Call(ref c) = *node {
+            let args = c.args();
+            if args.len() == 1 {
+                if let Node::Symbol(ref s) = args[0] {
+                    Ok(s)
+                } else {
+                    Err(IncorrectTypeOfArgumentError(args[0].clone()))
+                }
+            } else {
+                Err(IncorrectNumberOfArgumentsError(node.clone()))
+            }
+        } else {
+            Err(DispatchError(node.clone()))
+        }
+    }
+
+    pub(super) fn eval_call_builtin_doc(&mut self, node: &Node) -> EvalResult {
+        let symbol = try!(self.eval_symbol_arg(node));
+        let doc = match self.resolve_var(symbol) {
+            Some(ref var) if self.get_meta(var).is_some() => {
+                format_var_doc(var, self.get_meta(var).unwrap())
+            },
+            _ => {
+                let is_core = symbol.ns().map_or(true, |ns| ns == CORE_NS);
+                match builtin_doc(symbol.name()) {
+                    Some(b) if is_core => format_builtin_doc(b),
+                    _ => return Err(ResolveError(symbol.name().clone())),
+                }
+            },
+        };
+        try!(self.write_to(Stream::Out, &doc));
+        Ok(n_nil![])
+    }
+
+    pub(super) fn builtin_find_doc(&mut self, args: &[Node], node: &Node) -> EvalResult {
+        if args.len() == 1 {
+            let re = try!(pattern_arg(&args[0]));
+            let mut res = String::new();
+            for &(ref var, ref meta) in &self.vars() {
+                let doc = match meta.get(&n_keyword!["doc"]) {
+                    Some(&Node::String(ref s)) => s.value().clone(),
+                    _ => String::new(),
+                };
+                if re.is_match(&format!("{}", var)) || re.is_match(&doc) {
+                    res.push_str(&format_var_doc(var, meta))
+                }
+            }
+            for b in BUILTINS {
+                if re.is_match(b.0) || re.is_match(b.2) {
+                    res.push_str(&format_builtin_doc(b))
+                }
+            }
+            try!(self.write_to(Stream::Out, &res));
+            Ok(n_nil![])
+        } else {
+            Err(IncorrectNumberOfArgumentsError(node.clone()))
+        }
+    }
+
+    pub(super) fn builtin_apropos(&mut self, args: &[Node], node: &Node) -> EvalResult {
+        if args.len() == 1 {
+            let re = try!(pattern_arg(&args[0]));
+            let mut res = self.vars().into_iter()
+                .filter(|&(ref var, _)| re.is_match(var.name()))
+                .map(|(var, _)| Node::Symbol(var))
+                .collect::<Vec<_>>();
+            res.extend(BUILTINS.iter()
+                       .filter(|b| re.is_match(b.0))
+                       .map(|b| n_symbol![Some(CORE_NS.to_string()), b.0]));
+            Ok(n_list![res])
+        } else {
+            Err(IncorrectNumberOfArgumentsError(node.clone()))
+        }
+    }
+
+    pub(super) fn eval_call_builtin_source(&mut self, node: &Node) -> EvalResult {
+        let symbol = try!(self.eval_symbol_arg(node));
+        let source = match self.resolve_var(symbol) {
+            Some(ref var) if self.get_meta(var).is_some() => {
+                let meta = self.get_meta(var).unwrap();
+                let from_file = match (meta.get(&n_keyword!["file"]),
+                                       as_number(meta.get(&n_keyword!["line"])),
+                                       as_number(meta.get(&n_keyword!["column"]))) {
+                    (Some(&Node::String(ref f)), Some(line), Some(col)) => read_source(f.value(), line, col),
+                    _ => None,
+                };
+                from_file
+                    .or_else(|| self.get_source(var).map(|s| format!("{}", s)))
+                    .unwrap_or_else(|| "Source not found".to_string())
+            },
+            _ => {
+                if builtin_doc(symbol.name()).is_some() {
+                    format!("{}/{} is implemented in Rust", CORE_NS, symbol.name())
+                } else {
+                    return Err(ResolveError(symbol.name().clone()))
+                }
+            },
+        };
+        try!(self.write_to(Stream::Out, &format!("{}\n", source)));
+        Ok(n_nil![])
+    }
+
+    pub(super) fn eval_call_builtin_dir(&mut self, node: &Node) -> EvalResult {
+        let ns = try!(self.eval_symbol_arg(node)).name().clone();
+        let mut names = if ns == CORE_NS {
+            BUILTINS.iter().map(|b| b.0.to_string()).collect::<Vec<_>>()
+        } else {
+            self.vars().into_iter()
+                .filter(|&(ref var, ref meta)| {
+                    var.ns() == Some(&ns) && meta.get(&n_keyword!["private"]) != Some(&n_bool![true])
+                })
+                .map(|(var, _)| var.name().clone())
+                .collect::<Vec<_>>()
+        };
+        names.sort();
+        let mut res = names.join("\n");
+        if !res.is_empty() {
+            res.push('\n');
+        }
+        try!(self.write_to(Stream::Out, &res));
+        Ok(n_nil![])
+    }
+}
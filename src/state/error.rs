@@ -11,8 +11,10 @@ pub enum EvalError {
     DispatchError(Node),
     IncorrectTypeOfArgumentError(Node),
     IncorrectNumberOfArgumentsError(Node),
+    DivideByZeroError(Node),
     IoError(String),
     ParserError(ParserError),
+    Exit(i32),
 }
 
 impl fmt::Display for EvalError {
@@ -30,12 +32,18 @@ impl fmt::Display for EvalError {
             EvalError::IncorrectNumberOfArgumentsError(ref expr) => {
                 write!(f, r#"Incorrect number of arguments {}"#, expr)
             },
+            EvalError::DivideByZeroError(ref expr) => {
+                write!(f, r#"Divide by zero {}"#, expr)
+            },
             EvalError::IoError(ref e) => {
                 write!(f, r#"{}"#, e)
             },
             EvalError::ParserError(ref e) => {
                 write!(f, r#"{}"#, e)
             },
+            EvalError::Exit(code) => {
+                write!(f, r#"Exit with status {}"#, code)
+            },
         }
     }
 }
@@ -69,5 +77,9 @@ mod tests {
         assert_eq!(r#"Incorrect type of argument "a""#, format!("{}", err));
         let err = EvalError::IncorrectNumberOfArgumentsError(n_call!["+", vec![]]);
         assert_eq!(r#"Incorrect number of arguments (+)"#, format!("{}", err));
+        let err = EvalError::DivideByZeroError(n_call!["/", vec![n_number![1.], n_number![0.]]]);
+        assert_eq!(r#"Divide by zero (/ 1 0)"#, format!("{}", err));
+        let err = EvalError::Exit(2);
+        assert_eq!(r#"Exit with status 2"#, format!("{}", err));
     }
 }
@@ -0,0 +1,305 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::io::{self, Write, BufRead, BufReader};
+use std::rc::Rc;
+use super::{State, EvalResult};
+use super::error::EvalError::*;
+use super::error::EvalError;
+use ast::Node;
+use ast::nodes::Stream;
+use utils::format_for_print;
+
+pub struct Streams {
+    input: Box<BufRead>,
+    out: Box<Write>,
+    err: Box<Write>,
+}
+
+impl Streams {
+    pub fn new() -> Streams {
+        Streams {
+            input: Box::new(BufReader::new(io::stdin())),
+            out: Box::new(io::stdout()),
+            err: Box::new(io::stderr()),
+        }
+    }
+}
+
+impl fmt::Debug for Streams {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Streams")
+    }
+}
+
+/// In-memory writer that can be installed as `*out*` or `*err*` and read back afterwards.
+#[derive(Debug, Clone)]
+pub struct Buffer {
+    buf: Rc<RefCell<Vec<u8>>>,
+}
+
+impl Buffer {
+    pub fn new() -> Buffer {
+        Buffer {
+            buf: Rc::new(RefCell::new(vec![])),
+        }
+    }
+
+    pub fn contents(&self) -> String {
+        String::from_utf8_lossy(&self.buf.borrow()).into_owned()
+    }
+}
+
+impl Write for Buffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buf.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// The stream that printing `values` writes to, given first like `(println *err* x)`, or
+/// `*out*`, along with the values to print.
+fn split_stream(values: &[Node]) -> (Stream, &[Node]) {
+    match values.first() {
+        Some(&Node::Stream(s)) => {
+            (s, &values[1..])
+        },
+        _ => {
+            (Stream::Out, values)
+        },
+    }
+}
+
+fn format_printf(node: &Node, fmt: &str, args: &[Node]) -> Result<String, EvalError> {
+    let mut res = String::new();
+    let mut args = args.iter();
+    let mut chars = fmt.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            res.push(c);
+            continue
+        }
+        let mut precision = None;
+        if Some(&'.') == chars.peek() {
+            chars.next();
+            let mut digits = String::new();
+            while let Some(&d) = chars.peek() {
+                if d.is_digit(10) {
+                    digits.push(d);
+                    chars.next();
+                } else {
+                    break
+                }
+            }
+            precision = digits.parse::<usize>().ok();
+        }
+        match chars.next() {
+            Some('%') => {
+                res.push('%')
+            },
+            Some('n') => {
+                res.push('\n')
+            },
+            Some('s') => {
+                let a = try!(args.next().ok_or(IncorrectNumberOfArgumentsError(node.clone())));
+                res.push_str(&format_for_print(a))
+            },
+            Some('d') => {
+                match args.next() {
+                    Some(&Node::Number(ref n)) => {
+                        res.push_str(&format!("{}", n.value() as i64))
+                    },
+                    Some(a) => {
+                        return Err(IncorrectTypeOfArgumentError(a.clone()))
+                    },
+                    None => {
+                        return Err(IncorrectNumberOfArgumentsError(node.clone()))
+                    },
+                }
+            },
+            Some('f') => {
+                match args.next() {
+                    Some(&Node::Number(ref n)) => {
+                        res.push_str(&format!("{:.*}", precision.unwrap_or(6), n.value()))
+                    },
+                    Some(a) => {
+                        return Err(IncorrectTypeOfArgumentError(a.clone()))
+                    },
+                    None => {
+                        return Err(IncorrectNumberOfArgumentsError(node.clone()))
+                    },
+                }
+            },
+            _ => {
+                return Err(IncorrectTypeOfArgumentError(n_string![fmt]))
+            },
+        }
+    }
+    if args.next().is_some() {
+        Err(IncorrectNumberOfArgumentsError(node.clone()))
+    } else {
+        Ok(res)
+    }
+}
+
+impl State {
+    pub fn set_in<R: BufRead + 'static>(&mut self, input: R) {
+        self.streams.borrow_mut().input = Box::new(input);
+    }
+
+    pub fn set_out<W: Write + 'static>(&mut self, out: W) {
+        self.streams.borrow_mut().out = Box::new(out);
+    }
+
+    pub fn set_err<W: Write + 'static>(&mut self, err: W) {
+        self.streams.borrow_mut().err = Box::new(err);
+    }
+
+    pub fn flush(&mut self) -> Result<(), EvalError> {
+        let ref mut streams = *self.streams.borrow_mut();
+        try!(streams.out.flush());
+        try!(streams.err.flush());
+        Ok(())
+    }
+
+    pub(super) fn write_to(&self, stream: Stream, s: &str) -> Result<(), EvalError> {
+        let ref mut streams = *self.streams.borrow_mut();
+        match stream {
+            Stream::Out => {
+                try!(streams.out.write_all(s.as_bytes()))
+            },
+            Stream::Err => {
+                try!(streams.err.write_all(s.as_bytes()))
+            },
+            Stream::In => {
+                return Err(IncorrectTypeOfArgumentError(Node::Stream(stream)))
+            },
+        }
+        Ok(())
+    }
+
+    /// The stream given as the only arg, or `default` when there is none.
+    fn stream_arg(&self, args: &[Node], node: &Node, default: Stream) -> Result<Stream, EvalError> {
+        match args.len() {
+            0 => {
+                Ok(default)
+            },
+            1 => {
+                if let Node::Stream(s) = args[0] {
+                    Ok(s)
+                } else {
+                    Err(IncorrectTypeOfArgumentError(args[0].clone()))
+                }
+            },
+            _ => {
+                Err(IncorrectNumberOfArgumentsError(node.clone()))
+            },
+        }
+    }
+
+    fn print_with(&mut self, args: &[Node], readably: bool, newline: bool) -> EvalResult {
+        let (stream, values) = split_stream(args);
+        let mut parts = vec![];
+        for a in values {
+            parts.push(if readably { format!("{}", a) } else { format_for_print(a) })
+        }
+        let mut s = parts.join(" ");
+        if newline {
+            s.push('\n');
+        }
+        try!(self.write_to(stream, &s));
+        Ok(n_nil![])
+    }
+
+    pub(super) fn builtin_print(&mut self, args: &[Node], _: &Node) -> EvalResult {
+        self.print_with(args, false, false)
+    }
+
+    pub(super) fn builtin_println(&mut self, args: &[Node], _: &Node) -> EvalResult {
+        self.print_with(args, false, true)
+    }
+
+    pub(super) fn builtin_pr(&mut self, args: &[Node], _: &Node) -> EvalResult {
+        self.print_with(args, true, false)
+    }
+
+    pub(super) fn builtin_prn(&mut self, args: &[Node], _: &Node) -> EvalResult {
+        self.print_with(args, true, true)
+    }
+
+    pub(super) fn builtin_printf(&mut self, args: &[Node], node: &Node) -> EvalResult {
+        let (stream, values) = split_stream(args);
+        match values.first() {
+            Some(&Node::String(ref fmt)) => {
+                let s = try!(format_printf(node, fmt.value(), &values[1..]));
+                try!(self.write_to(stream, &s));
+                Ok(n_nil![])
+            },
+            Some(fmt) => {
+                Err(IncorrectTypeOfArgumentError(fmt.clone()))
+            },
+            None => {
+                Err(IncorrectNumberOfArgumentsError(node.clone()))
+            },
+        }
+    }
+
+    pub(super) fn builtin_read_line(&mut self, args: &[Node], node: &Node) -> EvalResult {
+        match try!(self.stream_arg(args, node, Stream::In)) {
+            Stream::In => {
+                let ref mut line = String::new();
+                if try!(self.streams.borrow_mut().input.read_line(line)) > 0 {
+                    if line.ends_with('\n') {
+                        line.pop();
+                        if line.ends_with('\r') {
+                            line.pop();
+                        }
+                    }
+                    Ok(n_string![line])
+                } else {
+                    Ok(n_nil![])
+                }
+            },
+            other => {
+                Err(IncorrectTypeOfArgumentError(Node::Stream(other)))
+            },
+        }
+    }
+
+    pub(super) fn builtin_flush(&mut self, args: &[Node], node: &Node) -> EvalResult {
+        let stream = try!(self.stream_arg(args, node, Stream::Out));
+        let ref mut streams = *self.streams.borrow_mut();
+        match stream {
+            Stream::Out => {
+                try!(streams.out.flush())
+            },
+            Stream::Err => {
+                try!(streams.err.flush())
+            },
+            Stream::In => {
+                return Err(IncorrectTypeOfArgumentError(Node::Stream(Stream::In)))
+            },
+        }
+        Ok(n_nil![])
+    }
+
+    /// Calls the fn of no args compiled from the body of `with-out-str`, returning what it
+    /// printed to `*out*`.
+    pub(super) fn builtin_with_out_str(&mut self, args: &[Node], node: &Node) -> EvalResult {
+        if args.len() == 1 {
+            let buf = Buffer::new();
+            let out = {
+                let ref mut streams = *self.streams.borrow_mut();
+                ::std::mem::replace(&mut streams.out, Box::new(buf.clone()))
+            };
+            let result = self.call_value(&args[0], vec![], node);
+            self.streams.borrow_mut().out = out;
+            result.map(|_| n_string![buf.contents()])
+        } else {
+            Err(IncorrectNumberOfArgumentsError(node.clone()))
+        }
+    }
+}
@@ -0,0 +1,123 @@
+use super::{State, EvalResult};
+use super::error::EvalError::*;
+use ast::{Node, nodes};
+
+impl State {
+    /// Calls each of the preds of `p` on each of `args`, returning the first truthy result
+    /// for `some-fn`, nil if there is none, and whether all of them are for `every-pred`.
+    pub(super) fn call_preds(&mut self, p: &nodes::Preds, args: Vec<Node>, node: &Node) -> EvalResult {
+        for pred in p.preds() {
+            for a in &args {
+                let result = try!(self.call_value(pred, vec![a.clone()], node));
+                match (p.is_every(), result.as_bool()) {
+                    (false, true) => return Ok(result),
+                    (true, false) => return Ok(n_bool![false]),
+                    _ => {},
+                }
+            }
+        }
+        Ok(if p.is_every() { n_bool![true] } else { n_nil![] })
+    }
+
+    pub(super) fn builtin_is_nil(&mut self, args: &[Node], node: &Node) -> EvalResult {
+        if args.len() == 1 {
+            if let Node::Nil = args[0] {
+                Ok(n_bool![true])
+            } else {
+                Ok(n_bool![false])
+            }
+        } else {
+            Err(IncorrectNumberOfArgumentsError(node.clone()))
+        }
+    }
+
+    pub(super) fn builtin_is_some(&mut self, args: &[Node], node: &Node) -> EvalResult {
+        if args.len() == 1 {
+            if let Node::Nil = args[0] {
+                Ok(n_bool![false])
+            } else {
+                Ok(n_bool![true])
+            }
+        } else {
+            Err(IncorrectNumberOfArgumentsError(node.clone()))
+        }
+    }
+
+    pub(super) fn builtin_boolean(&mut self, args: &[Node], node: &Node) -> EvalResult {
+        if args.len() == 1 {
+            Ok(n_bool![args[0].as_bool()])
+        } else {
+            Err(IncorrectNumberOfArgumentsError(node.clone()))
+        }
+    }
+
+    /// `and` called as a value, once its args are all evaluated.
+    pub(super) fn builtin_and(&mut self, args: &[Node], _: &Node) -> EvalResult {
+        Ok(args.iter().find(|a| !a.as_bool()).or(args.last()).cloned().unwrap_or_else(|| n_bool![true]))
+    }
+
+    /// `or` called as a value, once its args are all evaluated.
+    pub(super) fn builtin_or(&mut self, args: &[Node], _: &Node) -> EvalResult {
+        Ok(args.iter().find(|a| a.as_bool()).or(args.last()).cloned().unwrap_or_else(|| n_nil![]))
+    }
+
+    /// Evaluates the args of a call of `and` by name, up to the first falsey one.
+    pub(super) fn eval_call_builtin_and(&mut self, node: &Node) -> EvalResult {
+        if let Node::Call(ref c) = *node {
+            let mut result = n_bool![true];
+            for a in c.args() {
+                result = try!(self.eval_compiled(a));
+                if !result.as_bool() {
+                    break
+                }
+            }
+            Ok(result)
+        } else {
+            Err(DispatchError(node.clone()))
+        }
+    }
+
+    /// Evaluates the args of a call of `or` by name, up to the first truthy one.
+    pub(super) fn eval_call_builtin_or(&mut self, node: &Node) -> EvalResult {
+        if let Node::Call(ref c) = *node {
+            let mut result = n_nil![];
+            for a in c.args() {
+                result = try!(self.eval_compiled(a));
+                if result.as_bool() {
+                    break
+                }
+            }
+            Ok(result)
+        } else {
+            Err(DispatchError(node.clone()))
+        }
+    }
+
+    pub(super) fn builtin_not(&mut self, args: &[Node], node: &Node) -> EvalResult {
+        if args.len() == 1 {
+            Ok(n_bool![!args[0].as_bool()])
+        } else {
+            Err(IncorrectNumberOfArgumentsError(node.clone()))
+        }
+    }
+
+    pub(super) fn builtin_xor(&mut self, args: &[Node], _: &Node) -> EvalResult {
+        Ok(n_bool![args.iter().fold(false, |result, a| result ^ a.as_bool())])
+    }
+
+    pub(super) fn builtin_some_fn(&mut self, args: &[Node], node: &Node) -> EvalResult {
+        if args.len() >= 1 {
+            Ok(Node::Preds(nodes::Preds::new(false, args.to_vec())))
+        } else {
+            Err(IncorrectNumberOfArgumentsError(node.clone()))
+        }
+    }
+
+    pub(super) fn builtin_every_pred(&mut self, args: &[Node], node: &Node) -> EvalResult {
+        if args.len() >= 1 {
+            Ok(Node::Preds(nodes::Preds::new(true, args.to_vec())))
+        } else {
+            Err(IncorrectNumberOfArgumentsError(node.clone()))
+        }
+    }
+}
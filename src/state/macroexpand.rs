@@ -0,0 +1,117 @@
+use super::{State, EvalResult};
+use super::error::EvalError;
+use super::error::EvalError::*;
+use super::doc::SPECIAL_FORMS;
+use ast::{Node, nodes};
+
+impl State {
+    /// Expands `form` once when it is a macro call, returns `None` otherwise.
+    fn macroexpand_1(&mut self, form: &Node) -> Result<Option<Node>, EvalError> {
+        if let Node::List(ref l) = *form {
+            if l.len() > 0 {
+                if let Node::Symbol(ref s) = l[0] {
+                    match &s.name()[..] {
+                        "defn" => {
+                            return self.defn_to_def(form, "fn").map(Some)
+                        },
+                        "defmacro" => {
+                            return self.defn_to_def(form, "macro").map(Some)
+                        },
+                        _ if self.is_macro_defined(s) => {},
+                        "when" => {
+                            return self.when_to_if(form, false).map(Some)
+                        },
+                        "when-not" => {
+                            return self.when_to_if(form, true).map(Some)
+                        },
+                        "cond" => {
+                            return self.cond_to_if(form).map(Some)
+                        },
+                        name if SPECIAL_FORMS.iter().any(|f| *f == name) => {
+                            return Ok(None)
+                        },
+                        _ => {}
+                    }
+                    if let Some(Node::Macro(f)) = self.get(s) {
+                        return self.apply_macro(&f, form, &l[1..], l.meta()).map(Some)
+                    }
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// Expands `form` repeatedly until it is no longer a macro call.
+    pub fn macroexpand(&mut self, form: &Node) -> EvalResult {
+        let mut form = form.clone();
+        while let Some(expansion) = try!(self.macroexpand_1(&form)) {
+            form = expansion
+        }
+        Ok(form)
+    }
+
+    /// Expands every macro call within `form`, quoted forms are left as is.
+    pub fn macroexpand_all(&mut self, form: &Node) -> EvalResult {
+        let form = try!(self.macroexpand(form));
+        match form {
+            Node::List(ref l) if l.len() > 0 && (l[0].is_symbol("quote") ||
+                                                 l[0].is_symbol("syntax-quote")) => {
+                Ok(form.clone())
+            },
+            Node::List(ref l) => {
+                let mut v = vec![];
+                for e in l {
+                    v.push(try!(self.macroexpand_all(e)))
+                }
+                Ok(n_list![v].with_meta(l.meta().cloned()).unwrap())
+            },
+            Node::Vector(ref l) => {
+                let mut v = vec![];
+                for e in l {
+                    v.push(try!(self.macroexpand_all(e)))
+                }
+                Ok(n_vec![v].with_meta(l.meta().cloned()).unwrap())
+            },
+            Node::Map(ref m) => {
+                let mut entries = vec![];
+                for &(ref k, ref v) in m.entries() {
+                    entries.push((try!(self.macroexpand_all(k)), try!(self.macroexpand_all(v))))
+                }
+                Ok(Node::Map(nodes::Map::new(entries).with_meta(m.meta().cloned())))
+            },
+            Node::Set(ref s) => {
+                let mut items = vec![];
+                for e in s.items() {
+                    items.push(try!(self.macroexpand_all(e)))
+                }
+                Ok(Node::Set(nodes::Set::new(items).with_meta(s.meta().cloned())))
+            },
+            _ => {
+                Ok(form.clone())
+            },
+        }
+    }
+
+    fn expand_with<F>(&mut self, args: &[Node], node: &Node, f: F) -> EvalResult
+        where F: FnOnce(&mut Self, &Node) -> EvalResult {
+        if args.len() == 1 {
+            f(self, &args[0])
+        } else {
+            Err(IncorrectNumberOfArgumentsError(node.clone()))
+        }
+    }
+
+    pub(super) fn builtin_macroexpand_1(&mut self, args: &[Node], node: &Node) -> EvalResult {
+        self.expand_with(args, node, |s, form| {
+            Ok(try!(s.macroexpand_1(form)).unwrap_or_else(|| form.clone()))
+        })
+    }
+
+    pub(super) fn builtin_macroexpand(&mut self, args: &[Node], node: &Node) -> EvalResult {
+        self.expand_with(args, node, |s, form| s.macroexpand(form))
+    }
+
+    pub(super) fn builtin_macroexpand_all(&mut self, args: &[Node], node: &Node) -> EvalResult {
+        self.expand_with(args, node, |s, form| s.macroexpand_all(form))
+    }
+}
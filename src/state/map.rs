@@ -0,0 +1,88 @@
+use super::{State, EvalResult};
+use super::error::EvalError::*;
+use ast::{Node, nodes};
+
+/// Value at `key` in a map or a vector, or `key` itself if it's in a set, `Err(())` when
+/// `coll` can't be looked up.
+fn lookup(coll: &Node, key: &Node) -> Result<Option<Node>, ()> {
+    match (coll, key) {
+        (&Node::Map(ref m), k) => {
+            Ok(m.get(k).cloned())
+        },
+        (&Node::Set(ref s), k) => {
+            Ok(s.get(k).cloned())
+        },
+        (&Node::Vector(ref v), &Node::Number(ref n)) => {
+            let i = n.value();
+            Ok(if i >= 0. && i.fract() == 0. { v.get(i as usize).cloned() } else { None })
+        },
+        (&Node::Vector(..), _) => {
+            Ok(None)
+        },
+        (&Node::Nil, _) => {
+            Ok(None)
+        },
+        _ => {
+            Err(())
+        },
+    }
+}
+
+/// Calls a keyword, map, set or vector as a lookup fn: `(:k m)`, `(m :k)`, `(s :k)` and
+/// `(v 0)`, each taking an optional not-found value. `args` are already evaluated.
+pub(super) fn call_lookup(f: &Node, args: &[Node], node: &Node) -> EvalResult {
+    if args.is_empty() || args.len() > 2 {
+        return Err(IncorrectNumberOfArgumentsError(node.clone()))
+    }
+    let not_found = args.get(1).cloned().unwrap_or_else(|| n_nil![]);
+    let found = match *f {
+        Node::Keyword(..) => lookup(&args[0], f).unwrap_or(None),
+        _ => try!(lookup(f, &args[0]).map_err(|_| IncorrectTypeOfArgumentError(node.clone()))),
+    };
+    Ok(found.unwrap_or(not_found))
+}
+
+impl State {
+    pub(super) fn builtin_get(&mut self, args: &[Node], node: &Node) -> EvalResult {
+        if args.len() == 2 || args.len() == 3 {
+            let not_found = args.get(2).cloned().unwrap_or_else(|| n_nil![]);
+            let found = try!(lookup(&args[0], &args[1])
+                             .map_err(|_| IncorrectTypeOfArgumentError(args[0].clone())));
+            Ok(found.unwrap_or(not_found))
+        } else {
+            Err(IncorrectNumberOfArgumentsError(node.clone()))
+        }
+    }
+
+    pub(super) fn builtin_assoc(&mut self, args: &[Node], node: &Node) -> EvalResult {
+        if args.len() >= 3 && args.len() % 2 == 1 {
+            let mut map = match args[0] {
+                Node::Map(ref m) => m.clone(),
+                Node::Nil => nodes::Map::new(vec![]),
+                _ => return Err(IncorrectTypeOfArgumentError(args[0].clone())),
+            };
+            for kv in args[1..].chunks(2) {
+                map.insert(kv[0].clone(), kv[1].clone());
+            }
+            Ok(Node::Map(map))
+        } else {
+            Err(IncorrectNumberOfArgumentsError(node.clone()))
+        }
+    }
+
+    pub(super) fn builtin_dissoc(&mut self, args: &[Node], node: &Node) -> EvalResult {
+        if args.len() >= 1 {
+            let mut map = match args[0] {
+                Node::Map(ref m) => m.clone(),
+                Node::Nil => return Ok(n_nil![]),
+                _ => return Err(IncorrectTypeOfArgumentError(args[0].clone())),
+            };
+            for k in &args[1..] {
+                map.remove(k);
+            }
+            Ok(Node::Map(map))
+        } else {
+            Err(IncorrectNumberOfArgumentsError(node.clone()))
+        }
+    }
+}
@@ -0,0 +1,158 @@
+use super::{State, EvalResult};
+use super::error::EvalError;
+use super::error::EvalError::*;
+use ast::Node;
+
+/// `n` as an integer, for the ops only defined on integers.
+pub(super) fn integer(n: f64) -> Option<i64> {
+    if n.fract() == 0. && n.abs() <= 9007199254740992. {
+        Some(n as i64)
+    } else {
+        None
+    }
+}
+
+/// Remainder of the division of `a` by `b` with the sign of `b`.
+pub(super) fn modulo(a: f64, b: f64) -> f64 {
+    let r = a % b;
+    if r != 0. && (r < 0.) != (b < 0.) { r + b } else { r }
+}
+
+/// The values of `args` as numbers, reporting the first one that isn't.
+fn numbers(args: &[Node]) -> Result<Vec<f64>, EvalError> {
+    let mut numbers = vec![];
+    for a in args {
+        match *a {
+            Node::Number(ref n) => numbers.push(n.value()),
+            _ => return Err(IncorrectTypeOfArgumentError(a.clone())),
+        }
+    }
+    Ok(numbers)
+}
+
+/// The values of `args` as integers, reporting the first one that isn't an integral number.
+fn integers(args: &[Node]) -> Result<Vec<i64>, EvalError> {
+    let numbers = try!(numbers(args));
+    let mut integers = vec![];
+    for (a, n) in args.iter().zip(numbers) {
+        match integer(n) {
+            Some(i) => integers.push(i),
+            None => return Err(IncorrectTypeOfArgumentError(a.clone())),
+        }
+    }
+    Ok(integers)
+}
+
+impl State {
+    /// Applies `f` to the number given as the only arg.
+    pub(super) fn builtin_unary(&mut self, args: &[Node], node: &Node, f: fn(f64) -> f64) -> EvalResult {
+        if args.len() == 1 {
+            let n = try!(numbers(args));
+            Ok(n_number![f(n[0])])
+        } else {
+            Err(IncorrectNumberOfArgumentsError(node.clone()))
+        }
+    }
+
+    /// Applies `f` to the two numbers given as args.
+    pub(super) fn builtin_binary(&mut self, args: &[Node], node: &Node,
+                                 f: fn(f64, f64) -> f64) -> EvalResult {
+        if args.len() == 2 {
+            let n = try!(numbers(args));
+            Ok(n_number![f(n[0], n[1])])
+        } else {
+            Err(IncorrectNumberOfArgumentsError(node.clone()))
+        }
+    }
+
+    /// Like `builtin_binary`, failing when the divisor is zero.
+    pub(super) fn builtin_division(&mut self, args: &[Node], node: &Node,
+                                   f: fn(f64, f64) -> f64) -> EvalResult {
+        if args.len() == 2 {
+            let n = try!(numbers(args));
+            if n[1] == 0. {
+                return Err(DivideByZeroError(node.clone()))
+            }
+            Ok(n_number![f(n[0], n[1])])
+        } else {
+            Err(IncorrectNumberOfArgumentsError(node.clone()))
+        }
+    }
+
+    /// Raises x to the power y, failing when a negative power of zero would divide by zero.
+    pub(super) fn builtin_pow(&mut self, args: &[Node], node: &Node) -> EvalResult {
+        if args.len() == 2 {
+            let n = try!(numbers(args));
+            if n[0] == 0. && n[1] < 0. {
+                return Err(DivideByZeroError(node.clone()))
+            }
+            Ok(n_number![n[0].powf(n[1])])
+        } else {
+            Err(IncorrectNumberOfArgumentsError(node.clone()))
+        }
+    }
+
+    /// Folds the numbers given as args, at least one, with `f`.
+    pub(super) fn builtin_fold(&mut self, args: &[Node], node: &Node,
+                               f: fn(f64, f64) -> f64) -> EvalResult {
+        if args.len() >= 1 {
+            let n = try!(numbers(args));
+            Ok(n_number![n[1..].iter().fold(n[0], |acc, &x| f(acc, x))])
+        } else {
+            Err(IncorrectNumberOfArgumentsError(node.clone()))
+        }
+    }
+
+    /// Tests the number given as the only arg with `pred`.
+    pub(super) fn builtin_number_pred(&mut self, args: &[Node], node: &Node,
+                                      pred: fn(f64) -> bool) -> EvalResult {
+        if args.len() == 1 {
+            let n = try!(numbers(args));
+            Ok(n_bool![pred(n[0])])
+        } else {
+            Err(IncorrectNumberOfArgumentsError(node.clone()))
+        }
+    }
+
+    /// Tests the integer given as the only arg with `pred`.
+    pub(super) fn builtin_integer_pred(&mut self, args: &[Node], node: &Node,
+                                       pred: fn(i64) -> bool) -> EvalResult {
+        if args.len() == 1 {
+            let i = try!(integers(args));
+            Ok(n_bool![pred(i[0])])
+        } else {
+            Err(IncorrectNumberOfArgumentsError(node.clone()))
+        }
+    }
+
+    /// Folds the integers given as args, at least two, with the bitwise op `f`.
+    pub(super) fn builtin_bit_op(&mut self, args: &[Node], node: &Node,
+                                 f: fn(i64, i64) -> i64) -> EvalResult {
+        if args.len() >= 2 {
+            let i = try!(integers(args));
+            Ok(n_number![i[1..].iter().fold(i[0], |acc, &x| f(acc, x)) as f64])
+        } else {
+            Err(IncorrectNumberOfArgumentsError(node.clone()))
+        }
+    }
+
+    pub(super) fn builtin_bit_not(&mut self, args: &[Node], node: &Node) -> EvalResult {
+        if args.len() == 1 {
+            let i = try!(integers(args));
+            Ok(n_number![!i[0] as f64])
+        } else {
+            Err(IncorrectNumberOfArgumentsError(node.clone()))
+        }
+    }
+
+    /// Shifts the integer first arg by the second with `f`, the shift taken modulo 64.
+    pub(super) fn builtin_shift(&mut self, args: &[Node], node: &Node,
+                                f: fn(i64, u32) -> i64) -> EvalResult {
+        if args.len() == 2 {
+            let i = try!(integers(args));
+            Ok(n_number![f(i[0], (i[1] & 63) as u32) as f64])
+        } else {
+            Err(IncorrectNumberOfArgumentsError(node.clone()))
+        }
+    }
+}
@@ -0,0 +1,88 @@
+use super::{State, EvalResult};
+use super::error::EvalError::*;
+use ast::{Node, nodes};
+use ast::nodes::Symbol;
+
+fn meta_to_node(meta: Option<&nodes::Map>) -> Node {
+    meta.map_or(n_nil![], |m| Node::Map(m.clone()))
+}
+
+fn node_to_meta(node: &Node) -> Option<Option<nodes::Map>> {
+    match *node {
+        Node::Map(ref m) => Some(Some(m.clone())),
+        Node::Nil => Some(None),
+        _ => None,
+    }
+}
+
+impl State {
+    pub(super) fn resolve_var(&self, symbol: &Symbol) -> Option<Symbol> {
+        let ns = symbol.ns().unwrap_or(&self.current);
+        self.var_in(ns, symbol.name()).map(|var| {
+            match *var.borrow() {
+                Some(Node::Alias(ref s)) => s.clone(),
+                _ => Symbol::new(Some(ns.clone()), symbol.name().clone()),
+            }
+        })
+    }
+
+    pub(super) fn get_meta(&self, symbol: &Symbol) -> Option<&nodes::Map> {
+        self.meta.get(symbol)
+    }
+
+    pub(super) fn eval_call_builtin_var(&mut self, node: &Node) -> EvalResult {
+        if let Node::Call(ref c) = *node {
+            let args = c.args();
+            if args.len() == 1 {
+                if let Node::Symbol(ref s) = args[0] {
+                    self.resolve_var(s)
+                        .map(|v| Ok(Node::Var(v)))
+                        .unwrap_or_else(|| Err(ResolveError(s.name().clone())))
+                } else {
+                    Err(IncorrectTypeOfArgumentError(args[0].clone()))
+                }
+            } else {
+                Err(IncorrectNumberOfArgumentsError(node.clone()))
+            }
+        } else {
+            Err(DispatchError(node.clone()))
+        }
+    }
+
+    pub(super) fn builtin_meta(&mut self, args: &[Node], node: &Node) -> EvalResult {
+        if args.len() == 1 {
+            match args[0] {
+                Node::Var(ref s) => {
+                    Ok(meta_to_node(self.get_meta(s)))
+                },
+                ref other => {
+                    Ok(meta_to_node(other.meta()))
+                },
+            }
+        } else {
+            Err(IncorrectNumberOfArgumentsError(node.clone()))
+        }
+    }
+
+    pub(super) fn builtin_with_meta(&mut self, args: &[Node], node: &Node) -> EvalResult {
+        if args.len() == 2 {
+            let meta = try!(node_to_meta(&args[1])
+                                .ok_or_else(|| IncorrectTypeOfArgumentError(args[1].clone())));
+            args[0].clone().with_meta(meta).ok_or_else(|| IncorrectTypeOfArgumentError(args[0].clone()))
+        } else {
+            Err(IncorrectNumberOfArgumentsError(node.clone()))
+        }
+    }
+
+    pub(super) fn builtin_vary_meta(&mut self, args: &[Node], node: &Node) -> EvalResult {
+        if args.len() >= 2 {
+            let mut f_args = vec![meta_to_node(args[0].meta())];
+            f_args.extend_from_slice(&args[2..]);
+            let meta = try!(node_to_meta(&try!(self.call_value(&args[1], f_args, node)))
+                                .ok_or_else(|| IncorrectTypeOfArgumentError(args[1].clone())));
+            args[0].clone().with_meta(meta).ok_or_else(|| IncorrectTypeOfArgumentError(args[0].clone()))
+        } else {
+            Err(IncorrectNumberOfArgumentsError(node.clone()))
+        }
+    }
+}
@@ -1,97 +1,207 @@
+mod bytecode;
+mod cmp;
+mod compile;
 mod error;
+mod doc;
+mod io;
+mod logic;
+mod macroexpand;
+mod map;
+mod math;
+mod meta;
+mod native;
+mod process;
+mod random;
+mod re;
+mod seq;
+mod strings;
+mod time;
+mod vm;
 #[cfg(test)]
 mod tests;
 
+use std::cell::RefCell;
 use std::collections::HashMap;
+use std::f64;
 use std::io::Read;
 use std::path::Path;
 use std::fs::{File, metadata};
+use std::rc::Rc;
 use self::error::EvalError::*;
-use self::error::EvalError;
-use ast::Node;
-use ast::nodes::Symbol;
+use self::io::Streams;
+use self::bytecode::builtin_value;
+use self::native::{NATIVES, body_of, native};
+use self::seq::seq_items;
+use self::vm::arith;
+use ast::{Node, nodes};
+use ast::nodes::{Symbol, Stream};
+use chunk::{Arith, Var};
 use parser::Parser;
 
+pub use self::error::EvalError;
+pub use self::io::Buffer;
+pub use self::random::{Rng, XorShift};
+
 pub type EvalResult = Result<Node, EvalError>;
 
+fn is_arity_matched(params: &[Node], args_len: usize) -> bool {
+    match params.iter().position(|p| p.is_symbol("&")) {
+        Some(required) => args_len >= required,
+        None => args_len == params.len(),
+    }
+}
+
+/// Whether `node` evaluates to itself, so that a collection literal made of such nodes
+/// is kept as is instead of being rebuilt.
+fn is_constant(node: &Node) -> bool {
+    match *node {
+        Node::Symbol(..) | Node::Call(..) | Node::Let(..) | Node::Case(..) | Node::Def(..) |
+        Node::Fn(..) | Node::Macro(..) => {
+            false
+        },
+        Node::Vector(ref v) => {
+            v.into_iter().all(is_constant)
+        },
+        Node::Map(ref m) => {
+            m.entries().iter().all(|&(ref k, ref v)| is_constant(k) && is_constant(v))
+        },
+        Node::Set(ref s) => {
+            s.items().iter().all(is_constant)
+        },
+        _ => {
+            true
+        },
+    }
+}
+
 #[derive(Debug)]
-pub struct State<'s> {
+pub struct State {
     current: String,
-    state: HashMap<Symbol, Node>,
-    parent: Option<&'s State<'s>>,
+    state: HashMap<String, HashMap<String, Var>>,
     id: usize,
+    streams: Rc<RefCell<Streams>>,
+    rng: Rc<RefCell<Box<Rng>>>,
+    meta: HashMap<Symbol, nodes::Map>,
+    sources: HashMap<Symbol, Node>,
+    file: String,
 }
 
-impl<'s> State<'s> {
-    pub fn new(current: String) -> State<'s> {
-        let mut state = HashMap::new();
-        state.insert(Symbol::new(Some(current.clone()), "nil".to_string()), n_list![]);
-        state.insert(Symbol::new(Some(current.clone()), "true".to_string()), n_bool!(true));
-        state.insert(Symbol::new(Some(current.clone()), "false".to_string()), n_bool!(false));
+impl State {
+    pub fn new(current: String) -> State {
+        State::with_streams(current, Rc::new(RefCell::new(Streams::new())),
+                            Rc::new(RefCell::new(Box::new(XorShift::from_time()))))
+    }
 
-        State {
+    fn with_streams(current: String, streams: Rc<RefCell<Streams>>,
+                    rng: Rc<RefCell<Box<Rng>>>) -> State {
+        let mut state = State {
             current: current,
-            state: state,
-            parent: None,
+            state: HashMap::new(),
             id: 0,
-        }
+            streams: streams,
+            rng: rng,
+            meta: HashMap::new(),
+            sources: HashMap::new(),
+            file: "NO_SOURCE_PATH".to_string(),
+        };
+        state.insert(Symbol::new(None, "nil".to_string()), n_nil![]);
+        state.insert(Symbol::new(None, "true".to_string()), n_bool!(true));
+        state.insert(Symbol::new(None, "false".to_string()), n_bool!(false));
+        state.insert(Symbol::new(None, "PI".to_string()), n_number![f64::consts::PI]);
+        state.insert(Symbol::new(None, "E".to_string()), n_number![f64::consts::E]);
+        state.insert(Symbol::new(None, "*in*".to_string()), Node::Stream(Stream::In));
+        state.insert(Symbol::new(None, "*out*".to_string()), Node::Stream(Stream::Out));
+        state.insert(Symbol::new(None, "*err*".to_string()), Node::Stream(Stream::Err));
+        state
     }
 
+    /// Compiles `node` to bytecode and runs it on the VM.
     pub fn eval(&mut self, node: &Node) -> EvalResult {
-        match try!(self.expand(node)) {
-            ref symbol_node @ Node::Symbol(..) => {
-                self.eval_symbol(symbol_node)
+        let compiled = try!(self.compile(node));
+        let chunk = try!(self.compile_chunk(&compiled));
+        self.run(Rc::new(chunk), vec![], Rc::new(vec![]))
+    }
+
+    /// Evaluates a form produced by `compile` by walking it, without expanding it again, and
+    /// runs the ones binding locals on the VM. Used by builtins to evaluate their args.
+    fn eval_compiled(&mut self, node: &Node) -> EvalResult {
+        match *node {
+            Node::Symbol(..) => {
+                self.eval_symbol(node)
+            },
+            Node::Call(..) => {
+                self.eval_call(node)
             },
-            ref def_node @ Node::Def(..) => {
-                self.eval_def(def_node)
+            Node::Def(..) | Node::Let(..) | Node::Case(..) => {
+                let chunk = try!(self.compile_chunk(node));
+                self.run(Rc::new(chunk), vec![], Rc::new(vec![]))
             },
-            ref call_node @ Node::Call(..) => {
-                self.eval_call(call_node)
+            Node::Vector(..) => {
+                self.eval_vector(node)
             },
-            ref let_node @ Node::Let(..) => {
-                self.eval_let(let_node)
+            Node::Map(..) => {
+                self.eval_map(node)
             },
-            other_node => {
-                Ok(other_node)
+            Node::Set(..) => {
+                self.eval_set(node)
+            },
+            _ => {
+                Ok(node.clone())
             },
         }
     }
 
-    fn new_chained(parent: &'s State<'s>) -> State<'s> {
-        let mut state = State::new(format!("{}_chained", parent.current));
-        state.parent = Some(parent);
-        state
+    pub fn set_command_line_args(&mut self, args: Vec<String>) {
+        let args = args.iter().map(|a| n_string![a]).collect();
+        self.insert(Symbol::new(None, "*command-line-args*".to_string()), n_vec![args]);
     }
 
-    fn insert(&mut self, symbol: Symbol, node: Node) -> Option<Node> {
-        if symbol.ns().is_some() {
-            self.state.insert(symbol, node)
-        } else {
-            self.state.insert(Symbol::new(Some(self.current.clone()), symbol.name().clone()), node)
-        }
+    /// Sets the file name that `def` records as `:file` metadata, returning the previous one.
+    pub fn set_file(&mut self, file: String) -> String {
+        ::std::mem::replace(&mut self.file, file)
     }
 
-    fn get(&self, symbol: &Symbol) -> Option<&Node> {
-        let mut state = self;
-        loop {
-            let mut v = if symbol.ns().is_some() {
-                state.state.get(symbol)
-            } else {
-                state.state.get(&Symbol::new(Some(state.current.clone()), symbol.name().clone()))
-            };
-            if let Some(&Node::Alias(ref s)) = v {
-                v = state.state.get(s);
-            }
-            if v.is_none() && state.parent.is_some() {
-                state = state.parent.unwrap();
-            } else {
-                return v
-            }
+    /// Returns the var of `symbol` in its namespace, or in the current one, creating it unbound.
+    fn intern(&mut self, symbol: &Symbol) -> Var {
+        let ns = symbol.ns().unwrap_or(&self.current).clone();
+        self.state.entry(ns).or_insert_with(HashMap::new)
+            .entry(symbol.name().clone()).or_insert_with(|| Rc::new(RefCell::new(None)))
+            .clone()
+    }
+
+    fn insert(&mut self, symbol: Symbol, node: Node) {
+        *self.intern(&symbol).borrow_mut() = Some(node);
+    }
+
+    fn var_in(&self, ns: &str, name: &str) -> Option<&Var> {
+        self.state.get(ns).and_then(|vars| vars.get(name))
+    }
+
+    /// Finds the var `symbol` refers to, following `refer` aliases.
+    fn find_var(&self, symbol: &Symbol) -> Option<Var> {
+        let var = self.var_in(symbol.ns().unwrap_or(&self.current), symbol.name()).cloned();
+        let alias = match var {
+            Some(ref v) => match *v.borrow() {
+                Some(Node::Alias(ref s)) => Some(s.clone()),
+                _ => None,
+            },
+            None => None,
+        };
+        match alias {
+            Some(s) => self.var_in(s.ns().unwrap_or(&self.current), s.name()).cloned(),
+            None => var,
         }
     }
 
+    fn get(&self, symbol: &Symbol) -> Option<Node> {
+        self.find_var(symbol).and_then(|var| var.borrow().clone())
+    }
+
     fn contains(&self, symbol: &Symbol) -> bool {
-        self.state.contains_key(symbol)
+        match symbol.ns() {
+            Some(ns) => self.var_in(ns, symbol.name()).is_some(),
+            None => false,
+        }
     }
 
     fn get_current(&self) -> &String {
@@ -112,304 +222,226 @@ impl<'s> State<'s> {
 
     fn eval_symbol(&mut self, node: &Node) -> EvalResult {
         if let Node::Symbol(ref s) = *node {
-            self.get(s)
-                .map(|e| Ok(e.clone()))
-                .unwrap_or(Err(ResolveError(s.name().clone())))
+            self.get(s).or_else(|| builtin_value(s)).ok_or_else(|| ResolveError(s.name().clone()))
         } else {
             Err(DispatchError(node.clone()))
         }
     }
 
+    fn eval_args(&mut self, args: &[Node]) -> Result<Vec<Node>, EvalError> {
+        let mut e_args = vec![];
+        for a in args {
+            e_args.push(try!(self.eval_compiled(a)))
+        }
+        Ok(e_args)
+    }
+
     fn eval_quoted(&mut self, node: &Node) -> EvalResult {
         match *node {
             Node::Symbol(..) => {
                 Ok(node.clone())
             },
             Node::List(ref l) => {
-                let mut v = vec![];
-                for e in l {
-                    if e.is_call_of("unquote-splicing") {
-                        if let Node::List(ref l) = try!(self.eval(&e)) {
-                            for e in l {
-                                v.push(e.clone())
-                            }
-                        } else {
-                            return Err(IncorrectTypeOfArgumentError(e.clone()))
-                        }
-                    } else {
-                        v.push(try!(self.eval_quoted(&e)))
-                    }
+                Ok(n_list![try!(self.eval_quoted_seq(l))])
+            },
+            Node::Vector(ref v) => {
+                Ok(n_vec![try!(self.eval_quoted_seq(v))])
+            },
+            Node::Map(ref m) => {
+                let mut entries = vec![];
+                for &(ref k, ref v) in m.entries() {
+                    entries.push((try!(self.eval_quoted(k)), try!(self.eval_quoted(v))))
                 }
-                Ok(n_list![v])
+                Ok(Node::Map(nodes::Map::new(entries).with_meta(m.meta().cloned())))
+            },
+            Node::Set(ref s) => {
+                let items = try!(self.eval_quoted_seq(s.items()));
+                Ok(Node::Set(nodes::Set::new(items).with_meta(s.meta().cloned())))
             },
             _ => {
-                self.eval(node)
+                self.eval_compiled(node)
             },
         }
     }
 
-    fn eval_def(&mut self, node: &Node) -> EvalResult {
-        if let Node::Def(ref d) = *node {
-            let e = try!(self.eval(d.expr()));
-            self.insert(d.symbol().clone(), e.clone());
-            Ok(e)
+    fn eval_quoted_seq(&mut self, l: &[Node]) -> Result<Vec<Node>, EvalError> {
+        let mut v = vec![];
+        for e in l {
+            if e.is_call_of("unquote-splicing") {
+                match try!(self.eval_compiled(&e)) {
+                    Node::List(ref l) | Node::Vector(ref l) => {
+                        for e in l {
+                            v.push(e.clone())
+                        }
+                    },
+                    _ => {
+                        return Err(IncorrectTypeOfArgumentError(e.clone()))
+                    },
+                }
+            } else {
+                v.push(try!(self.eval_quoted(&e)))
+            }
+        }
+        Ok(v)
+    }
+
+    /// Binds `e` to the var of `d` in the current namespace, recording its metadata.
+    fn define(&mut self, d: &nodes::Def, e: Node) -> EvalResult {
+        let mut meta = d.meta().cloned().unwrap_or_else(|| nodes::Map::new(vec![]));
+        meta.insert(n_keyword!["ns"], n_symbol![self.current.clone()]);
+        meta.insert(n_keyword!["name"], n_symbol![d.symbol().name().clone()]);
+        let var = Symbol::new(Some(self.current.clone()), d.symbol().name().clone());
+        if let Some(source) = d.source() {
+            self.sources.insert(var.clone(), source.clone());
+        }
+        self.meta.insert(var, meta);
+        self.insert(d.symbol().clone(), e.clone());
+        Ok(e)
+    }
+
+    fn eval_vector(&mut self, node: &Node) -> EvalResult {
+        if let Node::Vector(ref v) = *node {
+            if is_constant(node) {
+                return Ok(node.clone())
+            }
+            let items = try!(self.eval_args(v));
+            Ok(n_vec![items].with_meta(v.meta().cloned()).unwrap())
         } else {
             Err(DispatchError(node.clone()))
         }
     }
 
-    fn eval_let(&mut self, node: &Node) -> EvalResult {
-        if let Node::Let(ref l) = *node {
-            let ref mut let_state = State::new_chained(self);
-            for c in l.bindings().chunks(2) {
-                if let (Some(&Node::Symbol(ref s)), Some(be)) = (c.first(), c.last()) {
-                    let evaled_be = try!(let_state.eval(&be));
-                    let_state.insert(s.clone(), evaled_be);
-                }
+    fn eval_map(&mut self, node: &Node) -> EvalResult {
+        if let Node::Map(ref m) = *node {
+            if is_constant(node) {
+                return Ok(node.clone())
             }
-            let mut result = n_list![];
-            for e in l.body() {
-                result = try!(let_state.eval(&e));
+            let mut entries = vec![];
+            for &(ref k, ref v) in m.entries() {
+                entries.push((try!(self.eval_compiled(k)), try!(self.eval_compiled(v))))
             }
-            Ok(result)
+            Ok(Node::Map(nodes::Map::new(entries).with_meta(m.meta().cloned())))
         } else {
             Err(DispatchError(node.clone()))
         }
     }
 
+    fn eval_set(&mut self, node: &Node) -> EvalResult {
+        if let Node::Set(ref s) = *node {
+            if is_constant(node) {
+                return Ok(node.clone())
+            }
+            let items = try!(self.eval_args(s.items()));
+            Ok(Node::Set(nodes::Set::new(items).with_meta(s.meta().cloned())))
+        } else {
+            Err(DispatchError(node.clone()))
+        }
+    }
+
+    /// Calls the builtin the callee of `node` names, unless a var shadows it, with the values
+    /// of its args. Special forms and builtins taking forms get them unevaluated instead.
     fn eval_call(&mut self, node: &Node) -> EvalResult {
         if let Node::Call(ref c) = *node {
-            let is_contains = self.contains(c.symbol());
-            match &c.symbol().name()[..] {
-                "+" if !is_contains => {
-                    self.eval_call_builtin_plus(node)
-                },
-                "-" if !is_contains => {
-                    self.eval_call_builtin_minus(node)
-                },
-                "*" if !is_contains => {
-                    self.eval_call_builtin_mul(node)
-                },
-                "/" if !is_contains => {
-                    self.eval_call_builtin_div(node)
-                },
-                "<" if !is_contains => {
-                    self.eval_call_builtin_lt(node)
-                },
-                ">" if !is_contains => {
-                    self.eval_call_builtin_gt(node)
-                },
-                "=" if !is_contains => {
-                    self.eval_call_builtin_eq(node)
-                },
-                "if" if !is_contains => {
+            let symbol = match c.symbol() {
+                Some(symbol) if !self.contains(symbol) => symbol,
+                _ => return self.eval_call_custom(node),
+            };
+            match &symbol.name()[..] {
+                "if" => {
                     self.eval_call_builtin_if(node)
                 },
-                "quote" if !is_contains => {
+                "do" => {
+                    self.eval_call_builtin_do(node)
+                },
+                "quote" => {
                     self.eval_call_builtin_quote(node)
                 },
-                "syntax-quote" if !is_contains => {
+                "syntax-quote" => {
                     self.eval_call_builtin_syntax_quote(node)
                 },
-                "unquote" if !is_contains => {
+                "unquote" => {
                     self.eval_call_builtin_unquote(node)
                 },
-                "unquote-splicing" if !is_contains => {
+                "unquote-splicing" => {
                     self.eval_call_builtin_unquote_splicing(node)
                 },
-                "eval" if !is_contains => {
-                    self.eval_call_builtin_eval(node)
+                "and" => {
+                    self.eval_call_builtin_and(node)
+                },
+                "or" => {
+                    self.eval_call_builtin_or(node)
                 },
-                "apply" if !is_contains => {
-                    self.eval_call_builtin_apply(node)
+                "var" => {
+                    self.eval_call_builtin_var(node)
                 },
-                "gensym" if !is_contains => {
+                "gensym" => {
                     self.eval_call_builtin_gensym(node)
                 },
-                "in-ns" if !is_contains => {
-                    self.eval_call_builtin_in_ns(node)
+                "refer" => {
+                    self.eval_call_builtin_refer(node)
                 },
-                "load" if !is_contains => {
-                    self.eval_call_builtin_load(node)
+                "doc" => {
+                    self.eval_call_builtin_doc(node)
                 },
-                "refer" if !is_contains => {
-                    self.eval_call_builtin_refer(node)
+                "source" => {
+                    self.eval_call_builtin_source(node)
                 },
-                _ => {
-                    self.eval_call_custom(node)
+                "dir" => {
+                    self.eval_call_builtin_dir(node)
                 },
-            }
-        } else {
-            Err(DispatchError(node.clone()))
-        }
-    }
-
-    fn eval_call_builtin_plus(&mut self, node: &Node) -> EvalResult {
-        if let Node::Call(ref c) = *node {
-            let mut result = 0_f64;
-            for a in c.args() {
-                if let Node::Number(n) = try!(self.eval(&a)) {
-                    result += n.value();
-                } else {
-                    return Err(IncorrectTypeOfArgumentError(a.clone()))
-                }
-            }
-            Ok(n_number![result])
-        } else {
-            Err(DispatchError(node.clone()))
-        }
-    }
-
-    fn eval_call_builtin_minus(&mut self, node: &Node) -> EvalResult {
-        if let Node::Call(ref c) = *node {
-            let args = c.args();
-            if args.len() >= 1 {
-                if let Node::Number(n) = try!(self.eval(&args[0])) {
-                    let value = n.value();
-                    let mut result = if args.len() == 1 { -value } else { value };
-                    for a in &args[1..] {
-                        if let Node::Number(n) = try!(self.eval(&a)) {
-                            result -= n.value()
-                        } else {
-                            return Err(IncorrectTypeOfArgumentError(a.clone()))
-                        }
-                    }
-                    Ok(n_number![result])
-                } else {
-                    Err(IncorrectTypeOfArgumentError(args[0].clone()))
-                }
-            } else {
-                Err(IncorrectNumberOfArgumentsError(node.clone()))
-            }
-        } else {
-            Err(DispatchError(node.clone()))
-        }
-    }
-
-    fn eval_call_builtin_mul(&mut self, node: &Node) -> EvalResult {
-        if let Node::Call(ref c) = *node {
-            let args = c.args();
-            let mut result = 1_f64;
-            for a in args {
-                if let Node::Number(n) = try!(self.eval(&a)) {
-                    result *= n.value()
-                } else {
-                    return Err(IncorrectTypeOfArgumentError(a.clone()))
-                }
-            }
-            Ok(n_number![result])
-        } else {
-            Err(DispatchError(node.clone()))
-        }
-    }
-
-    fn eval_call_builtin_div(&mut self, node: &Node) -> EvalResult {
-        if let Node::Call(ref c) = *node {
-            let args = c.args();
-            if args.len() >= 1 {
-                if let Node::Number(n) = try!(self.eval(&args[0])) {
-                    let value = n.value();
-                    let mut result = if args.len() == 1 { 1. / value } else { value };
-                    for a in &args[1..] {
-                        if let Node::Number(n) = try!(self.eval(&a)) {
-                            result /= n.value()
-                        } else {
-                            return Err(IncorrectTypeOfArgumentError(a.clone()))
-                        }
+                name => {
+                    match native(name) {
+                        Some(i) => {
+                            let args = try!(self.eval_native_args(name, c.args()));
+                            (NATIVES[i].1)(self, &args, node)
+                        },
+                        None => {
+                            self.eval_call_custom(node)
+                        },
                     }
-                    Ok(n_number![result])
-                } else {
-                    Err(IncorrectTypeOfArgumentError(args[0].clone()))
-                }
-            } else {
-                Err(IncorrectNumberOfArgumentsError(node.clone()))
+                },
             }
         } else {
             Err(DispatchError(node.clone()))
         }
     }
 
-    fn eval_call_builtin_lt(&mut self, node: &Node) -> EvalResult {
-        if let Node::Call(ref c) = *node {
-            let args = c.args();
-            if args.len() >= 1 {
-                if let Node::Number(n) = try!(self.eval(&args[0])) {
-                    let mut temp = n.value();
-                    for a in &args[1..] {
-                        if let Node::Number(n) = try!(self.eval(&a)) {
-                            let value = n.value();
-                            if temp < value {
-                                temp = value
-                            } else {
-                                return Ok(n_bool![false])
-                            }
-                        } else {
-                            return Err(IncorrectTypeOfArgumentError(a.clone()))
-                        }
-                    }
-                    Ok(n_bool![true])
-                } else {
-                    Err(IncorrectTypeOfArgumentError(args[0].clone()))
-                }
-            } else {
-                Err(IncorrectNumberOfArgumentsError(node.clone()))
-            }
-        } else {
-            Err(DispatchError(node.clone()))
+    /// Evaluates the args of a call of the native builtin `name`, turning its body, if it
+    /// takes one, into a fn of no args.
+    fn eval_native_args(&mut self, name: &str, args: &[Node]) -> Result<Vec<Node>, EvalError> {
+        match body_of(name) {
+            Some(at) => {
+                let at = at.min(args.len());
+                let mut values = try!(self.eval_args(&args[..at]));
+                values.push(Node::Fn(nodes::Fn::new(vec![], args[at..].to_vec())));
+                Ok(values)
+            },
+            None => {
+                self.eval_args(args)
+            },
         }
     }
 
-    fn eval_call_builtin_gt(&mut self, node: &Node) -> EvalResult {
-        if let Node::Call(ref c) = *node {
-            let args = c.args();
-            if args.len() >= 1 {
-                if let Node::Number(n) = try!(self.eval(&args[0])) {
-                    let mut temp = n.value();
-                    for a in &args[1..] {
-                        if let Node::Number(n) = try!(self.eval(&a)) {
-                            let value = n.value();
-                            if temp > value {
-                                temp = value
-                            } else {
-                                return Ok(n_bool![false])
-                            }
-                        } else {
-                            return Err(IncorrectTypeOfArgumentError(a.clone()))
-                        }
-                    }
-                    Ok(n_bool![true])
-                } else {
-                    Err(IncorrectTypeOfArgumentError(args[0].clone()))
-                }
-            } else {
-                Err(IncorrectNumberOfArgumentsError(node.clone()))
-            }
+    /// `+`, `-`, `*`, `/`, `=` and the ordering ops, all of them but `+` and `*` taking at
+    /// least one arg.
+    fn builtin_arith(&mut self, args: &[Node], node: &Node, op: Arith) -> EvalResult {
+        if args.is_empty() && op != Arith::Add && op != Arith::Mul {
+            Err(IncorrectNumberOfArgumentsError(node.clone()))
         } else {
-            Err(DispatchError(node.clone()))
+            arith(op, args, node, |i| args[i].clone())
         }
     }
 
-    fn eval_call_builtin_eq(&mut self, node: &Node) -> EvalResult {
+    fn eval_call_builtin_if(&mut self, node: &Node) -> EvalResult {
         if let Node::Call(ref c) = *node {
             let args = c.args();
-            if args.len() >= 1 {
-                if let Node::Number(n) = try!(self.eval(&args[0])) {
-                    let mut temp = n.value();
-                    for a in &args[1..] {
-                        if let Node::Number(n) = try!(self.eval(&a)) {
-                            let value = n.value();
-                            if temp == value {
-                                temp = value
-                            } else {
-                                return Ok(n_bool![false])
-                            }
-                        } else {
-                            return Err(IncorrectTypeOfArgumentError(a.clone()))
-                        }
-                    }
-                    Ok(n_bool![true])
+            if args.len() == 2 || args.len() == 3 {
+                if try!(self.eval_compiled(&args[0])).as_bool() {
+                    self.eval_compiled(&args[1])
+                } else if args.len() == 3 {
+                    self.eval_compiled(&args[2])
                 } else {
-                    Err(IncorrectTypeOfArgumentError(args[0].clone()))
+                    Ok(n_nil![])
                 }
             } else {
                 Err(IncorrectNumberOfArgumentsError(node.clone()))
@@ -419,18 +451,13 @@ impl<'s> State<'s> {
         }
     }
 
-    fn eval_call_builtin_if(&mut self, node: &Node) -> EvalResult {
+    fn eval_call_builtin_do(&mut self, node: &Node) -> EvalResult {
         if let Node::Call(ref c) = *node {
-            let args = c.args();
-            if args.len() == 3 {
-                if try!(self.eval(&args[0])).as_bool() {
-                    self.eval(&args[1])
-                } else {
-                    self.eval(&args[2])
-                }
-            } else {
-                Err(IncorrectNumberOfArgumentsError(node.clone()))
+            let mut result = n_nil![];
+            for e in c.args() {
+                result = try!(self.eval_compiled(e));
             }
+            Ok(result)
         } else {
             Err(DispatchError(node.clone()))
         }
@@ -466,7 +493,7 @@ impl<'s> State<'s> {
         if let Node::Call(ref c) = *node {
             let args = c.args();
             if args.len() == 1 {
-                self.eval(&args[0])
+                self.eval_compiled(&args[0])
             } else {
                 Err(IncorrectNumberOfArgumentsError(node.clone()))
             }
@@ -479,7 +506,7 @@ impl<'s> State<'s> {
         if let Node::Call(ref c) = *node {
             let args = c.args();
             if args.len() == 1 {
-                self.eval(&args[0])
+                self.eval_compiled(&args[0])
             } else {
                 Err(IncorrectNumberOfArgumentsError(node.clone()))
             }
@@ -488,39 +515,25 @@ impl<'s> State<'s> {
         }
     }
 
-    fn eval_call_builtin_eval(&mut self, node: &Node) -> EvalResult {
-        if let Node::Call(ref c) = *node {
-            let args = c.args();
-            if args.len() == 1 {
-                self.eval(&args[0]).and_then(|e| self.eval(&e))
-            } else {
-                Err(IncorrectNumberOfArgumentsError(node.clone()))
-            }
+    fn builtin_eval(&mut self, args: &[Node], node: &Node) -> EvalResult {
+        if args.len() == 1 {
+            self.eval(&args[0])
         } else {
-            Err(DispatchError(node.clone()))
+            Err(IncorrectNumberOfArgumentsError(node.clone()))
         }
     }
 
-    fn eval_call_builtin_apply(&mut self, node: &Node) -> EvalResult {
-        if let Node::Call(ref c) = *node {
-            let args = c.args();
-            if args.len() == 2 {
-                if let Node::Symbol(ref s) = args[0] {
-                    if let Node::Vector(ref v) = try!(self.eval(&args[1])) {
-                        let mut vec = vec![];
-                        vec.extend(v.into_iter().map(|n| n.clone()));
-                        self.eval(&n_call![s.ns().map(|ns| ns.clone()), s.name().clone(), vec])
-                    } else {
-                        Err(IncorrectTypeOfArgumentError(args[1].clone()))
-                    }
-                } else {
-                    Err(IncorrectTypeOfArgumentError(args[0].clone()))
-                }
-            } else {
-                Err(IncorrectNumberOfArgumentsError(node.clone()))
+    fn builtin_apply(&mut self, args: &[Node], node: &Node) -> EvalResult {
+        if args.len() >= 2 {
+            let last = args.len() - 1;
+            let mut values = args[1..last].to_vec();
+            match seq_items(&args[last]) {
+                Some(items) => values.extend(items),
+                None => return Err(IncorrectTypeOfArgumentError(args[last].clone())),
             }
+            self.call_value(&args[0], values, node)
         } else {
-            Err(DispatchError(node.clone()))
+            Err(IncorrectNumberOfArgumentsError(node.clone()))
         }
     }
 
@@ -541,56 +554,51 @@ impl<'s> State<'s> {
         }
     }
 
-    fn eval_call_builtin_in_ns(&mut self, node: &Node) -> EvalResult {
-        if let Node::Call(ref c) = *node {
-            let args = c.args();
-            if args.len() == 1 {
-                if let Node::Symbol(ref s) = try!(self.eval(&args[0])) {
-                    let old_current = self.get_current().clone();
-                    self.set_current(s.name().clone());
-                    Ok(n_symbol![old_current])
-                } else {
-                    Err(IncorrectTypeOfArgumentError(args[0].clone()))
-                }
+    fn builtin_in_ns(&mut self, args: &[Node], node: &Node) -> EvalResult {
+        if args.len() == 1 {
+            if let Node::Symbol(ref s) = args[0] {
+                let old_current = self.get_current().clone();
+                self.set_current(s.name().clone());
+                Ok(n_symbol![old_current])
             } else {
-                Err(IncorrectNumberOfArgumentsError(node.clone()))
+                Err(IncorrectTypeOfArgumentError(args[0].clone()))
             }
         } else {
-            Err(DispatchError(node.clone()))
+            Err(IncorrectNumberOfArgumentsError(node.clone()))
         }
     }
 
-    fn eval_call_builtin_load(&mut self, node: &Node) -> EvalResult {
-        if let Node::Call(ref c) = *node {
-            let args = c.args();
-            if args.len() == 1 {
-                if let Node::String(ref s) = try!(self.eval(&args[0])) {
-                    let path = Path::new(s.value());
-                    let md = metadata(path);
-                    if is_file_exists!(md) {
-                        if is_file!(md) {
-                            let mut file = try!(File::open(&path));
-                            let ref mut buf = String::new();
-                            try!(file.read_to_string(buf));
-                            let mut last_evaled = None;
-                            for parsed_expr in Parser::new(buf.chars()) {
-                                last_evaled = Some(try!(self.eval(&try!(parsed_expr))));
+    fn builtin_load(&mut self, args: &[Node], node: &Node) -> EvalResult {
+        if args.len() == 1 {
+            if let Node::String(ref s) = args[0] {
+                let path = Path::new(s.value());
+                let md = metadata(path);
+                if is_file_exists!(md) {
+                    if is_file!(md) {
+                        let mut file = try!(File::open(&path));
+                        let ref mut buf = String::new();
+                        try!(file.read_to_string(buf));
+                        let old_file = self.set_file(s.value().clone());
+                        let mut result = Ok(n_nil![]);
+                        for parsed_expr in Parser::new(buf.chars()) {
+                            result = parsed_expr.map_err(From::from).and_then(|e| self.eval(&e));
+                            if result.is_err() {
+                                break
                             }
-                            last_evaled.map_or(Ok(n_list![]), |e| Ok(e))
-                        } else {
-                            Err(IncorrectTypeOfArgumentError(Node::String(s.clone())))
                         }
+                        self.set_file(old_file);
+                        result
                     } else {
                         Err(IncorrectTypeOfArgumentError(Node::String(s.clone())))
                     }
                 } else {
-                    Err(IncorrectTypeOfArgumentError(args[0].clone()))
+                    Err(IncorrectTypeOfArgumentError(Node::String(s.clone())))
                 }
             } else {
-                Err(IncorrectNumberOfArgumentsError(node.clone()))
+                Err(IncorrectTypeOfArgumentError(args[0].clone()))
             }
         } else {
-            Err(DispatchError(node.clone()))
+            Err(IncorrectNumberOfArgumentsError(node.clone()))
         }
     }
 
@@ -601,7 +609,7 @@ impl<'s> State<'s> {
                 if let Node::Symbol(ref s) = args[0] {
                     if let Node::Symbol(ref to_s) = args[1] {
                         self.insert(s.clone(), n_alias![to_s.ns().unwrap(), to_s.name().clone()]);
-                        Ok(n_list![])
+                        Ok(n_nil![])
                     } else {
                         Err(IncorrectTypeOfArgumentError(args[1].clone()))
                     }
@@ -616,60 +624,49 @@ impl<'s> State<'s> {
         }
     }
 
+    fn builtin_exit(&mut self, args: &[Node], node: &Node) -> EvalResult {
+        match args.len() {
+            0 => {
+                Err(Exit(0))
+            },
+            1 => {
+                if let Node::Number(ref n) = args[0] {
+                    Err(Exit(n.value() as i32))
+                } else {
+                    Err(IncorrectTypeOfArgumentError(args[0].clone()))
+                }
+            },
+            _ => {
+                Err(IncorrectNumberOfArgumentsError(node.clone()))
+            },
+        }
+    }
+
     fn eval_call_custom(&mut self, node: &Node) -> EvalResult {
         if let Node::Call(ref c) = *node {
-            let (symbol, args) = (c.symbol(), c.args());
-            let func = try!(self.get(symbol)
-                                .map(|e| Ok(e.clone()))
-                                .unwrap_or_else(|| Err(ResolveError(symbol.name().clone()))));
+            let args = c.args();
+            let func = match *c.callee() {
+                Node::Symbol(ref s) => try!(self.get(s).ok_or_else(|| ResolveError(s.name().clone()))),
+                ref callee => try!(self.eval_compiled(callee)),
+            };
             match func {
                 Node::Fn(ref f) => {
-                    if args.len() != f.params().len() {
+                    if !is_arity_matched(f.params(), args.len()) {
                         return Err(IncorrectNumberOfArgumentsError(node.clone()))
                     }
 
-                    let mut e_args = vec![];
-                    for a in args {
-                        e_args.push(try!(self.eval(a)))
-                    }
-
-                    let ref mut fn_state = State::new_chained(self);
-                    for (p, a) in f.params().iter().zip(e_args.iter()) {
-                        if let &Node::Symbol(ref s) = p {
-                            fn_state.insert(s.clone(), a.clone());
-                        } else {
-                            return Err(IncorrectTypeOfArgumentError(p.clone()))
-                        }
-                    }
-
-                    let mut result = n_list![];
-                    for e in f.body() {
-                        result = try!(fn_state.eval(e));
-                    }
-
-                    Ok(result)
-
+                    let e_args = try!(self.eval_args(args));
+                    self.call_fn(f, e_args)
                 },
                 Node::Macro(ref f) => {
-                    if args.len() != f.params().len() {
-                        return Err(IncorrectNumberOfArgumentsError(node.clone()))
-                    }
-
-                    let ref mut macro_state = State::new_chained(self);
-                    for (p, a) in f.params().iter().zip(args.iter()) {
-                        if let Node::Symbol(ref s) = *p {
-                            macro_state.insert(s.clone(), a.clone());
-                        } else {
-                            return Err(IncorrectTypeOfArgumentError(p.clone()))
-                        }
-                    }
-
-                    let mut result = n_list![];
-                    for e in f.body() {
-                        result = try!(macro_state.eval(&e));
-                    }
-
-                    Ok(try!(macro_state.expand(&result)))
+                    let expansion = try!(self.apply_macro(f, node, args, None));
+                    let compiled = try!(self.compile(&expansion));
+                    self.eval_compiled(&compiled)
+                },
+                Node::Builtin(..) | Node::Preds(..) | Node::Keyword(..) | Node::Map(..) |
+                Node::Set(..) | Node::Vector(..) => {
+                    let e_args = try!(self.eval_args(args));
+                    self.call_value(&func, e_args, node)
                 },
                 _ => {
                     Err(IncorrectTypeOfArgumentError(node.clone()))
@@ -680,255 +677,22 @@ impl<'s> State<'s> {
         }
     }
 
-    fn expand(&mut self, node: &Node) -> EvalResult {
-        if let Node::List(ref l) = *node {
-            if l.len() > 0 {
-                if let Node::Symbol(ref s) = l[0] {
-                    match &s.name()[..] {
-                        "def" => {
-                            return self.expand_def(node)
-                        },
-                        "fn" => {
-                            return self.expand_fn(node)
-                        },
-                        "macro" => {
-                            return self.expand_macro(node)
-                        },
-                        "quote" => {
-                            return self.expand_quote(node)
-                        },
-                        "syntax-quote" => {
-                            return self.expand_syntax_quote(node)
-                        },
-                        "unquote" => {
-                            return self.expand_unquote(node)
-                        },
-                        "unquote-splicing" => {
-                            return self.expand_unquote_splicing(node)
-                        },
-                        "let" => {
-                            return self.expand_let(node)
-                        },
-                        _ => {
-                            return self.expand_call(node)
-                        }
-                    }
-                } else {
-                    return Err(DispatchError(l[0].clone()))
-                }
-            }
+    /// Runs the macro body over the unevaluated argument forms and returns the
+    /// expansion as data, without expanding it any further.
+    fn apply_macro(&mut self, f: &nodes::Fn, node: &Node, args: &[Node],
+                   meta: Option<&nodes::Map>) -> EvalResult {
+        if !is_arity_matched(f.params(), args.len()) {
+            return Err(IncorrectNumberOfArgumentsError(node.clone()))
         }
-        Ok(node.clone())
-    }
 
-    fn expand_quoted(&mut self, node: &Node) -> EvalResult {
-        match *node {
-            Node::List(ref l) if l.len() > 0 => {
-                if l[0].is_symbol("unquote") || l[0].is_symbol("unquote-splicing") {
-                    self.expand(node)
-                } else {
-                    let mut v = vec![];
-                    for i in l {
-                        v.push(try!(self.expand_quoted(i)));
-                    }
-                    Ok(n_list![v])
-                }
-            },
-            _ => {
-                self.expand(node)
-            }
-        }
-    }
+        let mut result = try!(self.call_fn(f, args.to_vec()));
 
-    fn expand_syntax_quoted(&mut self, node: &Node) -> EvalResult {
-        match *node {
-            Node::Symbol(ref s) if s.ns().is_none() => {
-                Ok(n_symbol![Some(self.get_current().clone()), s.name()])
-            },
-            Node::List(ref l) if l.len() > 0 => {
-                if l[0].is_symbol("unquote") || l[0].is_symbol("unquote-splicing") {
-                    self.expand(node)
-                } else {
-                    let mut v = vec![];
-                    for i in l {
-                        v.push(try!(self.expand_syntax_quoted(i)));
-                    }
-                    Ok(n_list![v])
-                }
-            },
-            _ => {
-                self.expand(node)
+        if let (Some(m), None) = (meta, result.meta()) {
+            if let Some(r) = result.with_meta(Some(m.clone())) {
+                result = r
             }
         }
-    }
 
-    fn expand_def(&mut self, node: &Node) -> EvalResult {
-        if let Node::List(ref l) = *node {
-            if l.len() == 3 {
-                if let Node::Symbol(ref s) = l[1] {
-                    Ok(n_def![s.name(), try!(self.expand(&l[2]))])
-                } else {
-                    Err(IncorrectTypeOfArgumentError(l[1].clone()))
-                }
-            } else {
-                Err(IncorrectNumberOfArgumentsError(node.clone()))
-            }
-        } else {
-            Err(DispatchError(node.clone()))
-        }
-    }
-
-    fn expand_fn(&mut self, node: &Node) -> EvalResult {
-        if let Node::List(ref l) = *node {
-            if l.len() >= 3 {
-                if let Node::Vector(ref params) = l[1] {
-                    let mut fn_params = vec![];
-                    for p in params {
-                        fn_params.push(try!(self.expand(p)))
-                    }
-                    let mut fn_body = vec![];
-                    for be in &l[2..] {
-                        fn_body.push(try!(self.expand(be)))
-                    }
-                    Ok(n_fn![fn_params, fn_body])
-                } else {
-                    Err(IncorrectTypeOfArgumentError(l[1].clone()))
-                }
-            } else {
-                Err(IncorrectNumberOfArgumentsError(node.clone()))
-            }
-        } else {
-            Err(DispatchError(node.clone()))
-        }
-    }
-
-    fn expand_macro(&mut self, node: &Node) -> EvalResult {
-        if let Node::List(ref l) = *node {
-            if l.len() >= 3 {
-                if let Node::Vector(ref params) = l[1] {
-                    let mut macro_params = vec![];
-                    for p in params {
-                        macro_params.push(try!(self.expand(p)))
-                    }
-                    let mut macro_body = vec![];
-                    for be in &l[2..] {
-                        macro_body.push(try!(self.expand(be)))
-                    }
-                    Ok(n_macro![macro_params, macro_body])
-                } else {
-                    Err(IncorrectTypeOfArgumentError(l[1].clone()))
-                }
-            } else {
-                Err(IncorrectNumberOfArgumentsError(node.clone()))
-            }
-        } else {
-            Err(DispatchError(node.clone()))
-        }
-    }
-
-    fn expand_quote(&mut self, node: &Node) -> EvalResult {
-        if let Node::List(ref l) = *node {
-            if l.len() == 2 {
-                Ok(n_call!["quote", vec![try!(self.expand_quoted(&l[1]))]])
-            } else {
-                Err(IncorrectNumberOfArgumentsError(node.clone()))
-            }
-        } else {
-            Err(DispatchError(node.clone()))
-        }
-    }
-
-    fn expand_syntax_quote(&mut self, node: &Node) -> EvalResult {
-        if let Node::List(ref l) = *node {
-            if l.len() == 2 {
-                Ok(n_call!["syntax-quote", vec![try!(self.expand_syntax_quoted(&l[1]))]])
-            } else {
-                Err(IncorrectNumberOfArgumentsError(node.clone()))
-            }
-        } else {
-            Err(DispatchError(node.clone()))
-        }
-    }
-
-    fn expand_unquote(&mut self, node: &Node) -> EvalResult {
-        if let Node::List(ref l) = *node {
-            if l.len() == 2 {
-                Ok(n_call!["unquote", vec![try!(self.expand(&l[1]))]])
-            } else {
-                Err(IncorrectNumberOfArgumentsError(node.clone()))
-            }
-        } else {
-            Err(DispatchError(node.clone()))
-        }
-    }
-
-    fn expand_unquote_splicing(&mut self, node: &Node) -> EvalResult {
-        if let Node::List(ref l) = *node {
-            if l.len() == 2 {
-                Ok(n_call!["unquote-splicing", vec![try!(self.expand(&l[1]))]])
-            } else {
-                Err(IncorrectNumberOfArgumentsError(node.clone()))
-            }
-        } else {
-            Err(DispatchError(node.clone()))
-        }
-    }
-
-    fn expand_let(&mut self, node: &Node) -> EvalResult {
-        if let Node::List(ref l) = *node {
-            if l.len() >= 3 {
-                if let Node::Vector(ref v) = l[1] {
-                    if v.len() % 2 == 0 {
-                        let mut let_bindings = vec![];
-                        for c in v.chunks(2) {
-                            if let Some(s @ &Node::Symbol(..)) = c.first() {
-                                let_bindings.push(s.clone())
-                            } else {
-                                return Err(IncorrectTypeOfArgumentError(node.clone()))
-                            }
-                            if let Some(ref e) = c.last() {
-                                let_bindings.push(try!(self.expand(e)))
-                            } else {
-                                return Err(IncorrectTypeOfArgumentError(node.clone()))
-                            }
-                        }
-                        let mut let_body = vec![];
-                        for be in &l[2..] {
-                            let_body.push(try!(self.expand(be)))
-                        }
-                        Ok(n_let![let_bindings, let_body])
-                    } else {
-                        Err(IncorrectNumberOfArgumentsError(node.clone()))
-                    }
-                } else {
-                    Err(IncorrectTypeOfArgumentError(node.clone()))
-                }
-            } else {
-                Err(IncorrectNumberOfArgumentsError(node.clone()))
-            }
-        } else {
-            Err(DispatchError(node.clone()))
-        }
-    }
-
-    fn expand_call(&mut self, node: &Node) -> EvalResult {
-        if let Node::List(ref l) = *node {
-            if let Node::Symbol(ref s) = l[0] {
-                let mut args = vec![];
-                for a in &l[1..] {
-                    args.push(try!(self.expand(a)))
-                }
-                let call = n_call![s.ns().map(|ns| ns.clone()), s.name().clone(), args];
-                if self.get(s).map_or(false, |e| e.is_macro()) {
-                    Ok(try!(self.eval(&call)))
-                } else {
-                    Ok(call)
-                }
-            } else {
-                Err(IncorrectTypeOfArgumentError(l[0].clone()))
-            }
-        } else {
-            Err(DispatchError(node.clone()))
-        }
+        Ok(result)
     }
 }
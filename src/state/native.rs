@@ -0,0 +1,139 @@
+use super::{State, EvalResult};
+use super::math::modulo;
+use ast::Node;
+use chunk::Arith;
+
+/// Builtin taking the values of its args, and the call being made, which errors report when
+/// it isn't an arg that is wrong.
+pub(super) type Native = fn(&mut State, &[Node], &Node) -> EvalResult;
+
+/// Builtins taking a body of forms from the given arg on, evaluated in the context they set
+/// up. They get the body as a fn of no args, after the values of the args before it.
+const BODY_BUILTINS: &'static [(&'static str, usize)] = &[("with-out-str", 0), ("with-seed", 1)];
+
+/// The builtins called with the values of their args, whether by the VM or by the
+/// tree-walking evaluator, or as values.
+pub(super) static NATIVES: &'static [(&'static str, Native)] = &[
+    ("+", |s, args, node| s.builtin_arith(args, node, Arith::Add)),
+    ("-", |s, args, node| s.builtin_arith(args, node, Arith::Sub)),
+    ("*", |s, args, node| s.builtin_arith(args, node, Arith::Mul)),
+    ("/", |s, args, node| s.builtin_arith(args, node, Arith::Div)),
+    ("<", |s, args, node| s.builtin_arith(args, node, Arith::Lt)),
+    (">", |s, args, node| s.builtin_arith(args, node, Arith::Gt)),
+    ("=", |s, args, node| s.builtin_arith(args, node, Arith::Eq)),
+    ("<=", |s, args, node| s.builtin_arith(args, node, Arith::Le)),
+    (">=", |s, args, node| s.builtin_arith(args, node, Arith::Ge)),
+    ("inc", |s, args, node| s.builtin_unary(args, node, |n| n + 1.)),
+    ("dec", |s, args, node| s.builtin_unary(args, node, |n| n - 1.)),
+    ("quot", |s, args, node| s.builtin_division(args, node, |a, b| (a / b).trunc())),
+    ("rem", |s, args, node| s.builtin_division(args, node, |a, b| a % b)),
+    ("mod", |s, args, node| s.builtin_division(args, node, modulo)),
+    ("abs", |s, args, node| s.builtin_unary(args, node, f64::abs)),
+    ("min", |s, args, node| s.builtin_fold(args, node, f64::min)),
+    ("max", |s, args, node| s.builtin_fold(args, node, f64::max)),
+    ("zero?", |s, args, node| s.builtin_number_pred(args, node, |n| n == 0.)),
+    ("pos?", |s, args, node| s.builtin_number_pred(args, node, |n| n > 0.)),
+    ("neg?", |s, args, node| s.builtin_number_pred(args, node, |n| n < 0.)),
+    ("even?", |s, args, node| s.builtin_integer_pred(args, node, |i| i % 2 == 0)),
+    ("odd?", |s, args, node| s.builtin_integer_pred(args, node, |i| i % 2 != 0)),
+    ("sqrt", |s, args, node| s.builtin_unary(args, node, f64::sqrt)),
+    ("pow", |s, args, node| s.builtin_pow(args, node)),
+    ("exp", |s, args, node| s.builtin_unary(args, node, f64::exp)),
+    ("log", |s, args, node| s.builtin_unary(args, node, f64::ln)),
+    ("log10", |s, args, node| s.builtin_unary(args, node, f64::log10)),
+    ("sin", |s, args, node| s.builtin_unary(args, node, f64::sin)),
+    ("cos", |s, args, node| s.builtin_unary(args, node, f64::cos)),
+    ("tan", |s, args, node| s.builtin_unary(args, node, f64::tan)),
+    ("asin", |s, args, node| s.builtin_unary(args, node, f64::asin)),
+    ("acos", |s, args, node| s.builtin_unary(args, node, f64::acos)),
+    ("atan", |s, args, node| s.builtin_unary(args, node, f64::atan)),
+    ("atan2", |s, args, node| s.builtin_binary(args, node, f64::atan2)),
+    ("floor", |s, args, node| s.builtin_unary(args, node, f64::floor)),
+    ("ceil", |s, args, node| s.builtin_unary(args, node, f64::ceil)),
+    ("round", |s, args, node| s.builtin_unary(args, node, f64::round)),
+    ("bit-and", |s, args, node| s.builtin_bit_op(args, node, |a, b| a & b)),
+    ("bit-or", |s, args, node| s.builtin_bit_op(args, node, |a, b| a | b)),
+    ("bit-xor", |s, args, node| s.builtin_bit_op(args, node, |a, b| a ^ b)),
+    ("bit-not", |s, args, node| s.builtin_bit_not(args, node)),
+    ("bit-shift-left", |s, args, node| s.builtin_shift(args, node, i64::wrapping_shl)),
+    ("bit-shift-right", |s, args, node| s.builtin_shift(args, node, i64::wrapping_shr)),
+    ("rand", |s, args, node| s.builtin_rand(args, node)),
+    ("rand-int", |s, args, node| s.builtin_rand_int(args, node)),
+    ("rand-nth", |s, args, node| s.builtin_rand_nth(args, node)),
+    ("shuffle", |s, args, node| s.builtin_shuffle(args, node)),
+    ("set-seed!", |s, args, node| s.builtin_set_seed(args, node)),
+    ("with-seed", |s, args, node| s.builtin_with_seed(args, node)),
+    ("not=", |s, args, node| s.builtin_not_eq(args, node)),
+    ("identical?", |s, args, node| s.builtin_is_identical(args, node)),
+    ("compare", |s, args, node| s.builtin_compare(args, node)),
+    ("hash", |s, args, node| s.builtin_hash(args, node)),
+    ("now", |s, args, node| s.builtin_now(args, node)),
+    ("inst?", |s, args, node| s.builtin_is_inst(args, node)),
+    ("inst-ms", |s, args, node| s.builtin_inst_ms(args, node)),
+    ("parse-inst", |s, args, node| s.builtin_parse_inst(args, node)),
+    ("format-inst", |s, args, node| s.builtin_format_inst(args, node)),
+    ("duration", |s, args, node| s.builtin_duration(args, node)),
+    ("inst-add", |s, args, node| s.builtin_inst_add(args, node)),
+    ("inst-diff", |s, args, node| s.builtin_inst_diff(args, node)),
+    ("eval", |s, args, node| s.builtin_eval(args, node)),
+    ("apply", |s, args, node| s.builtin_apply(args, node)),
+    ("in-ns", |s, args, node| s.builtin_in_ns(args, node)),
+    ("load", |s, args, node| s.builtin_load(args, node)),
+    ("char", |s, args, node| s.builtin_char(args, node)),
+    ("int", |s, args, node| s.builtin_int(args, node)),
+    ("char?", |s, args, node| s.builtin_is_char(args, node)),
+    ("str", |s, args, node| s.builtin_str(args, node)),
+    ("seq", |s, args, node| s.builtin_seq(args, node)),
+    ("and", |s, args, node| s.builtin_and(args, node)),
+    ("or", |s, args, node| s.builtin_or(args, node)),
+    ("not", |s, args, node| s.builtin_not(args, node)),
+    ("xor", |s, args, node| s.builtin_xor(args, node)),
+    ("some-fn", |s, args, node| s.builtin_some_fn(args, node)),
+    ("every-pred", |s, args, node| s.builtin_every_pred(args, node)),
+    ("some", |s, args, node| s.builtin_some(args, node)),
+    ("every?", |s, args, node| s.builtin_is_every(args, node)),
+    ("first", |s, args, node| s.builtin_first(args, node)),
+    ("nil?", |s, args, node| s.builtin_is_nil(args, node)),
+    ("some?", |s, args, node| s.builtin_is_some(args, node)),
+    ("boolean", |s, args, node| s.builtin_boolean(args, node)),
+    ("re-pattern", |s, args, node| s.builtin_re_pattern(args, node)),
+    ("re-find", |s, args, node| s.builtin_re_find(args, node)),
+    ("re-matches", |s, args, node| s.builtin_re_matches(args, node)),
+    ("re-seq", |s, args, node| s.builtin_re_seq(args, node)),
+    ("re-groups", |s, args, node| s.builtin_re_groups(args, node)),
+    ("re-replace", |s, args, node| s.builtin_re_replace(args, node)),
+    ("macroexpand-1", |s, args, node| s.builtin_macroexpand_1(args, node)),
+    ("macroexpand", |s, args, node| s.builtin_macroexpand(args, node)),
+    ("macroexpand-all", |s, args, node| s.builtin_macroexpand_all(args, node)),
+    ("meta", |s, args, node| s.builtin_meta(args, node)),
+    ("with-meta", |s, args, node| s.builtin_with_meta(args, node)),
+    ("vary-meta", |s, args, node| s.builtin_vary_meta(args, node)),
+    ("get", |s, args, node| s.builtin_get(args, node)),
+    ("assoc", |s, args, node| s.builtin_assoc(args, node)),
+    ("dissoc", |s, args, node| s.builtin_dissoc(args, node)),
+    ("find-doc", |s, args, node| s.builtin_find_doc(args, node)),
+    ("apropos", |s, args, node| s.builtin_apropos(args, node)),
+    ("exit", |s, args, node| s.builtin_exit(args, node)),
+    ("getenv", |s, args, node| s.builtin_getenv(args, node)),
+    ("setenv", |s, args, node| s.builtin_setenv(args, node)),
+    ("cwd", |s, args, node| s.builtin_cwd(args, node)),
+    ("sh", |s, args, node| s.builtin_sh(args, node)),
+    ("print", |s, args, node| s.builtin_print(args, node)),
+    ("println", |s, args, node| s.builtin_println(args, node)),
+    ("pr", |s, args, node| s.builtin_pr(args, node)),
+    ("prn", |s, args, node| s.builtin_prn(args, node)),
+    ("printf", |s, args, node| s.builtin_printf(args, node)),
+    ("read-line", |s, args, node| s.builtin_read_line(args, node)),
+    ("flush", |s, args, node| s.builtin_flush(args, node)),
+    ("with-out-str", |s, args, node| s.builtin_with_out_str(args, node)),
+];
+
+/// Index in `NATIVES` of the builtin `name`.
+pub(super) fn native(name: &str) -> Option<usize> {
+    NATIVES.iter().position(|n| n.0 == name)
+}
+
+/// The arg from which the builtin `name` takes a body, if it takes one.
+pub(super) fn body_of(name: &str) -> Option<usize> {
+    BODY_BUILTINS.iter().find(|b| b.0 == name).map(|b| b.1)
+}
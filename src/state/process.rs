@@ -0,0 +1,175 @@
+use std::env;
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+use std::thread;
+use super::{State, EvalResult};
+use super::error::EvalError::*;
+use super::error::EvalError;
+use ast::Node;
+
+/// Name of an environment variable given as a string or keyword.
+fn env_name(node: &Node) -> Option<String> {
+    match *node {
+        Node::String(ref s) => Some(s.value().clone()),
+        Node::Keyword(ref k) if k.ns().is_none() => Some(k.name().clone()),
+        _ => None,
+    }
+}
+
+/// Whether `name` can be set as an environment variable, which panics otherwise.
+fn is_valid_env_name(name: &str) -> bool {
+    !name.is_empty() && !name.contains('=') && !name.contains('\0')
+}
+
+/// The value of `arg` as a string.
+fn string_arg(arg: &Node) -> Result<String, EvalError> {
+    match *arg {
+        Node::String(ref s) => Ok(s.value().clone()),
+        _ => Err(IncorrectTypeOfArgumentError(arg.clone())),
+    }
+}
+
+impl State {
+    pub(super) fn builtin_getenv(&mut self, args: &[Node], node: &Node) -> EvalResult {
+        match args.len() {
+            0 => {
+                let mut vars = env::vars_os()
+                    .map(|(k, v)| (n_string![k.to_string_lossy().into_owned()],
+                                   n_string![v.to_string_lossy().into_owned()]))
+                    .collect::<Vec<_>>();
+                vars.sort();
+                Ok(n_map![vars])
+            },
+            1 => {
+                let name = try!(string_arg(&args[0]));
+                match env::var_os(&name) {
+                    Some(value) => Ok(n_string![value.to_string_lossy().into_owned()]),
+                    None => Ok(n_nil![]),
+                }
+            },
+            _ => {
+                Err(IncorrectNumberOfArgumentsError(node.clone()))
+            },
+        }
+    }
+
+    pub(super) fn builtin_setenv(&mut self, args: &[Node], node: &Node) -> EvalResult {
+        if args.len() == 2 {
+            let name = try!(string_arg(&args[0]));
+            if !is_valid_env_name(&name) {
+                return Err(IncorrectTypeOfArgumentError(args[0].clone()))
+            }
+            match args[1] {
+                Node::String(ref s) if !s.value().contains('\0') => {
+                    env::set_var(&name, s.value())
+                },
+                Node::Nil => {
+                    env::remove_var(&name)
+                },
+                _ => {
+                    return Err(IncorrectTypeOfArgumentError(args[1].clone()))
+                },
+            }
+            Ok(n_nil![])
+        } else {
+            Err(IncorrectNumberOfArgumentsError(node.clone()))
+        }
+    }
+
+    pub(super) fn builtin_cwd(&mut self, args: &[Node], node: &Node) -> EvalResult {
+        if args.is_empty() {
+            let dir = try!(env::current_dir());
+            Ok(n_string![dir.to_string_lossy().into_owned()])
+        } else {
+            Err(IncorrectNumberOfArgumentsError(node.clone()))
+        }
+    }
+
+    /// Runs a program given as strings followed by `:in`, `:env` and `:dir` options, waiting
+    /// for it to exit. A program that fails doesn't make an error, only one that can't run.
+    pub(super) fn builtin_sh(&mut self, args: &[Node], node: &Node) -> EvalResult {
+        if args.is_empty() {
+            return Err(IncorrectNumberOfArgumentsError(node.clone()))
+        }
+        let program = try!(string_arg(&args[0]));
+        let mut command = Command::new(&program);
+        let mut input = None;
+        let mut i = 1;
+        while i < args.len() {
+            match args[i] {
+                Node::String(ref s) => {
+                    command.arg(s.value());
+                },
+                Node::Keyword(ref k) if k.ns().is_none() => {
+                    if i + 1 == args.len() {
+                        return Err(IncorrectNumberOfArgumentsError(node.clone()))
+                    }
+                    i += 1;
+                    match (&k.name()[..], &args[i]) {
+                        ("in", &Node::String(ref s)) => {
+                            input = Some(s.value().clone())
+                        },
+                        ("dir", &Node::String(ref s)) => {
+                            command.current_dir(s.value());
+                        },
+                        ("env", &Node::Map(ref m)) => {
+                            for &(ref k, ref v) in m.entries() {
+                                let name = match env_name(k) {
+                                    Some(ref name) if is_valid_env_name(name) => name.clone(),
+                                    _ => return Err(IncorrectTypeOfArgumentError(args[i].clone())),
+                                };
+                                match *v {
+                                    Node::String(ref s) if !s.value().contains('\0') => {
+                                        command.env(name, s.value());
+                                    },
+                                    Node::Nil => {
+                                        command.env_remove(name);
+                                    },
+                                    _ => {
+                                        return Err(IncorrectTypeOfArgumentError(args[i].clone()))
+                                    },
+                                }
+                            }
+                        },
+                        _ => {
+                            return Err(IncorrectTypeOfArgumentError(args[i].clone()))
+                        },
+                    }
+                },
+                _ => {
+                    return Err(IncorrectTypeOfArgumentError(args[i].clone()))
+                },
+            }
+            i += 1;
+        }
+
+        let unable = |e: io::Error| IoError(format!(r#"Unable to run "{}": {}"#, program, e));
+        let stdin = if input.is_some() { Stdio::piped() } else { Stdio::null() };
+        let mut child = try!(command.stdin(stdin)
+                             .stdout(Stdio::piped())
+                             .stderr(Stdio::piped())
+                             .spawn()
+                             .map_err(&unable));
+        // Written from another thread, so that a program filling its output before reading
+        // all of its input can't block us both
+        let writer = match (child.stdin.take(), input) {
+            (Some(mut stdin), Some(input)) => {
+                Some(thread::spawn(move || stdin.write_all(input.as_bytes())))
+            },
+            _ => None,
+        };
+        let output = try!(child.wait_with_output().map_err(&unable));
+        if let Some(Ok(Err(e))) = writer.map(|w| w.join()) {
+            // The program may exit without reading all of its input
+            if e.kind() != io::ErrorKind::BrokenPipe {
+                return Err(unable(e))
+            }
+        }
+        let exit = output.status.code().map_or(n_nil![], |code| n_number![code as f64]);
+        let out = String::from_utf8_lossy(&output.stdout).into_owned();
+        let err = String::from_utf8_lossy(&output.stderr).into_owned();
+        Ok(n_map![vec![(n_keyword!["exit"], exit),
+                       (n_keyword!["out"], n_string![out]),
+                       (n_keyword!["err"], n_string![err])]])
+    }
+}
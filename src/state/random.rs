@@ -0,0 +1,178 @@
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+use super::{State, EvalResult};
+use super::error::EvalError::*;
+use super::error::EvalError;
+use super::math::integer;
+use super::seq::seq_items;
+use ast::Node;
+
+/// Source of the randomness of `rand` and friends. A `State` can be given one with
+/// `State::set_rng`, e.g. to replay a fixed sequence in tests.
+pub trait Rng {
+    /// Returns the next 64 random bits.
+    fn next_u64(&mut self) -> u64;
+}
+
+impl fmt::Debug for Rng {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Rng")
+    }
+}
+
+/// Default generator, a xorshift64* whose sequence is determined by the seed.
+/// Fast and reproducible, but not suitable for cryptography.
+pub struct XorShift {
+    state: u64,
+}
+
+impl XorShift {
+    pub fn new(seed: u64) -> XorShift {
+        // Spreads the bits of small seeds, and the state must not be zero
+        let mut z = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^= z >> 31;
+        XorShift { state: if z == 0 { 0x9e37_79b9_7f4a_7c15 } else { z } }
+    }
+
+    /// Seeded by the current time, so that every run differs.
+    pub fn from_time() -> XorShift {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+        XorShift::new(now.as_secs() ^ ((now.subsec_nanos() as u64) << 32))
+    }
+}
+
+impl Rng for XorShift {
+    fn next_u64(&mut self) -> u64 {
+        let mut x = self.state;
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        self.state = x;
+        x.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+}
+
+/// The value of `arg` as a seed, an integral number.
+fn seed_arg(arg: &Node) -> Result<u64, EvalError> {
+    match *arg {
+        Node::Number(ref n) => integer(n.value()).map(|i| i as u64)
+            .ok_or_else(|| IncorrectTypeOfArgumentError(arg.clone())),
+        _ => Err(IncorrectTypeOfArgumentError(arg.clone())),
+    }
+}
+
+impl State {
+    /// Replaces the generator of this state and of the states sharing it.
+    pub fn set_rng<R: Rng + 'static>(&mut self, rng: R) {
+        *self.rng.borrow_mut() = Box::new(rng);
+    }
+
+    /// Restarts the randomness from `seed`, the same seed giving the same values.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.set_rng(XorShift::new(seed))
+    }
+
+    /// Returns a number uniformly distributed in [0, 1).
+    fn next_f64(&mut self) -> f64 {
+        (self.rng.borrow_mut().next_u64() >> 11) as f64 / (1_u64 << 53) as f64
+    }
+
+    /// Returns an index uniformly distributed in [0, n).
+    fn next_index(&mut self, n: usize) -> usize {
+        ((self.next_f64() * n as f64) as usize).min(n - 1)
+    }
+
+    pub(super) fn builtin_rand(&mut self, args: &[Node], node: &Node) -> EvalResult {
+        match args.len() {
+            0 => {
+                Ok(n_number![self.next_f64()])
+            },
+            1 => {
+                if let Node::Number(ref n) = args[0] {
+                    Ok(n_number![self.next_f64() * n.value()])
+                } else {
+                    Err(IncorrectTypeOfArgumentError(args[0].clone()))
+                }
+            },
+            _ => {
+                Err(IncorrectNumberOfArgumentsError(node.clone()))
+            },
+        }
+    }
+
+    pub(super) fn builtin_rand_int(&mut self, args: &[Node], node: &Node) -> EvalResult {
+        if args.len() == 1 {
+            if let Node::Number(ref n) = args[0] {
+                Ok(n_number![(self.next_f64() * n.value()).floor()])
+            } else {
+                Err(IncorrectTypeOfArgumentError(args[0].clone()))
+            }
+        } else {
+            Err(IncorrectNumberOfArgumentsError(node.clone()))
+        }
+    }
+
+    pub(super) fn builtin_rand_nth(&mut self, args: &[Node], node: &Node) -> EvalResult {
+        if args.len() == 1 {
+            match seq_items(&args[0]) {
+                Some(ref items) if !items.is_empty() => {
+                    let i = self.next_index(items.len());
+                    Ok(items[i].clone())
+                },
+                _ => {
+                    Err(IncorrectTypeOfArgumentError(args[0].clone()))
+                },
+            }
+        } else {
+            Err(IncorrectNumberOfArgumentsError(node.clone()))
+        }
+    }
+
+    pub(super) fn builtin_shuffle(&mut self, args: &[Node], node: &Node) -> EvalResult {
+        if args.len() == 1 {
+            match seq_items(&args[0]) {
+                Some(mut items) => {
+                    for i in (1..items.len()).rev() {
+                        let j = self.next_index(i + 1);
+                        items.swap(i, j);
+                    }
+                    Ok(n_vec![items])
+                },
+                None => {
+                    Err(IncorrectTypeOfArgumentError(args[0].clone()))
+                },
+            }
+        } else {
+            Err(IncorrectNumberOfArgumentsError(node.clone()))
+        }
+    }
+
+    pub(super) fn builtin_set_seed(&mut self, args: &[Node], node: &Node) -> EvalResult {
+        if args.len() == 1 {
+            let seed = try!(seed_arg(&args[0]));
+            self.set_seed(seed);
+            Ok(n_nil![])
+        } else {
+            Err(IncorrectNumberOfArgumentsError(node.clone()))
+        }
+    }
+
+    /// Calls the fn of no args compiled from the body of `with-seed` with a generator seeded
+    /// by the first arg, restoring the previous one afterwards.
+    pub(super) fn builtin_with_seed(&mut self, args: &[Node], node: &Node) -> EvalResult {
+        if args.len() == 2 {
+            let seed = try!(seed_arg(&args[0]));
+            let rng = {
+                let ref mut rng = *self.rng.borrow_mut();
+                ::std::mem::replace(rng, Box::new(XorShift::new(seed)))
+            };
+            let result = self.call_value(&args[1], vec![], node);
+            *self.rng.borrow_mut() = rng;
+            result
+        } else {
+            Err(IncorrectNumberOfArgumentsError(node.clone()))
+        }
+    }
+}
@@ -0,0 +1,104 @@
+use regex::{self, Captures};
+use super::{State, EvalResult};
+use super::error::EvalError::*;
+use super::error::EvalError;
+use ast::{Node, nodes};
+
+fn captures_to_node(caps: &Captures) -> Node {
+    if caps.len() == 1 {
+        n_string![&caps[0]]
+    } else {
+        captures_to_vec(caps)
+    }
+}
+
+fn captures_to_vec(caps: &Captures) -> Node {
+    n_vec![caps.iter().map(|m| m.map_or(n_nil![], |m| n_string![m.as_str()])).collect()]
+}
+
+/// The values of `r` and `s` as a regex and a string.
+fn regex_and_string<'a>(r: &'a Node, s: &'a Node) -> Result<(&'a nodes::Regex, &'a str), EvalError> {
+    match (r, s) {
+        (&Node::Regex(ref r), &Node::String(ref s)) => {
+            Ok((r, s.value()))
+        },
+        (&Node::Regex(..), _) => {
+            Err(IncorrectTypeOfArgumentError(s.clone()))
+        },
+        _ => {
+            Err(IncorrectTypeOfArgumentError(r.clone()))
+        },
+    }
+}
+
+impl State {
+    fn regex_with<F>(&mut self, args: &[Node], node: &Node, f: F) -> EvalResult
+        where F: FnOnce(&nodes::Regex, &str) -> EvalResult
+    {
+        if args.len() == 2 {
+            let (r, s) = try!(regex_and_string(&args[0], &args[1]));
+            f(r, s)
+        } else {
+            Err(IncorrectNumberOfArgumentsError(node.clone()))
+        }
+    }
+
+    pub(super) fn builtin_re_pattern(&mut self, args: &[Node], node: &Node) -> EvalResult {
+        if args.len() == 1 {
+            match args[0] {
+                Node::String(ref s) => {
+                    regex::Regex::new(s.value())
+                        .map(|r| Node::Regex(nodes::Regex::new(r)))
+                        .map_err(|_| IncorrectTypeOfArgumentError(Node::String(s.clone())))
+                },
+                Node::Regex(..) => {
+                    Ok(args[0].clone())
+                },
+                _ => {
+                    Err(IncorrectTypeOfArgumentError(args[0].clone()))
+                },
+            }
+        } else {
+            Err(IncorrectNumberOfArgumentsError(node.clone()))
+        }
+    }
+
+    pub(super) fn builtin_re_find(&mut self, args: &[Node], node: &Node) -> EvalResult {
+        self.regex_with(args, node, |r, s| {
+            Ok(r.value().captures(s).map_or(n_nil![], |caps| captures_to_node(&caps)))
+        })
+    }
+
+    pub(super) fn builtin_re_matches(&mut self, args: &[Node], node: &Node) -> EvalResult {
+        self.regex_with(args, node, |r, s| {
+            let anchored = try!(r.anchored()
+                                .map_err(|_| IncorrectTypeOfArgumentError(Node::Regex(r.clone()))));
+            Ok(anchored.captures(s).map_or(n_nil![], |caps| captures_to_node(&caps)))
+        })
+    }
+
+    pub(super) fn builtin_re_seq(&mut self, args: &[Node], node: &Node) -> EvalResult {
+        self.regex_with(args, node, |r, s| {
+            Ok(n_list![r.value().captures_iter(s).map(|caps| captures_to_node(&caps)).collect()])
+        })
+    }
+
+    pub(super) fn builtin_re_groups(&mut self, args: &[Node], node: &Node) -> EvalResult {
+        self.regex_with(args, node, |r, s| {
+            Ok(r.value().captures(s).map_or(n_nil![], |caps| captures_to_vec(&caps)))
+        })
+    }
+
+    pub(super) fn builtin_re_replace(&mut self, args: &[Node], node: &Node) -> EvalResult {
+        if args.len() == 3 {
+            let (r, s) = try!(regex_and_string(&args[0], &args[1]));
+            if let Node::String(ref rep) = args[2] {
+                Ok(n_string![r.value().replace_all(s, &rep.value()[..])])
+            } else {
+                Err(IncorrectTypeOfArgumentError(args[2].clone()))
+            }
+        } else {
+            Err(IncorrectNumberOfArgumentsError(node.clone()))
+        }
+    }
+}
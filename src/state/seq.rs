@@ -0,0 +1,83 @@
+use super::{State, EvalResult};
+use super::error::EvalError::*;
+use ast::Node;
+
+/// Elements of a string, list, vector or set, none for nil, `None` for other nodes.
+pub(super) fn seq_items(node: &Node) -> Option<Vec<Node>> {
+    match *node {
+        Node::Nil => {
+            Some(vec![])
+        },
+        Node::String(ref s) => {
+            Some(s.value().chars().map(|c| n_char![c]).collect())
+        },
+        Node::List(ref l) | Node::Vector(ref l) => {
+            Some(l.to_vec())
+        },
+        Node::Set(ref s) => {
+            Some(s.items().clone())
+        },
+        _ => {
+            None
+        },
+    }
+}
+
+impl State {
+    pub(super) fn builtin_seq(&mut self, args: &[Node], node: &Node) -> EvalResult {
+        if args.len() == 1 {
+            match seq_items(&args[0]) {
+                Some(items) => Ok(n_list![items]),
+                None => Err(IncorrectTypeOfArgumentError(args[0].clone())),
+            }
+        } else {
+            Err(IncorrectNumberOfArgumentsError(node.clone()))
+        }
+    }
+
+    pub(super) fn builtin_first(&mut self, args: &[Node], node: &Node) -> EvalResult {
+        if args.len() == 1 {
+            match seq_items(&args[0]) {
+                Some(items) => Ok(items.into_iter().next().unwrap_or(n_nil![])),
+                None => Err(IncorrectTypeOfArgumentError(args[0].clone())),
+            }
+        } else {
+            Err(IncorrectNumberOfArgumentsError(node.clone()))
+        }
+    }
+
+    pub(super) fn builtin_some(&mut self, args: &[Node], node: &Node) -> EvalResult {
+        if args.len() == 2 {
+            let items = match seq_items(&args[1]) {
+                Some(items) => items,
+                None => return Err(IncorrectTypeOfArgumentError(args[1].clone())),
+            };
+            for item in items {
+                let result = try!(self.call_value(&args[0], vec![item], node));
+                if result.as_bool() {
+                    return Ok(result)
+                }
+            }
+            Ok(n_nil![])
+        } else {
+            Err(IncorrectNumberOfArgumentsError(node.clone()))
+        }
+    }
+
+    pub(super) fn builtin_is_every(&mut self, args: &[Node], node: &Node) -> EvalResult {
+        if args.len() == 2 {
+            let items = match seq_items(&args[1]) {
+                Some(items) => items,
+                None => return Err(IncorrectTypeOfArgumentError(args[1].clone())),
+            };
+            for item in items {
+                if !try!(self.call_value(&args[0], vec![item], node)).as_bool() {
+                    return Ok(n_bool![false])
+                }
+            }
+            Ok(n_bool![true])
+        } else {
+            Err(IncorrectNumberOfArgumentsError(node.clone()))
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use std::char;
+use super::{State, EvalResult};
+use super::error::EvalError::*;
+use ast::Node;
+use utils::format_for_print;
+
+impl State {
+    pub(super) fn builtin_char(&mut self, args: &[Node], node: &Node) -> EvalResult {
+        if args.len() == 1 {
+            match args[0] {
+                Node::Number(ref n) => {
+                    let code = n.value();
+                    if code >= 0. && code.fract() == 0. {
+                        if let Some(c) = char::from_u32(code as u32) {
+                            return Ok(n_char![c])
+                        }
+                    }
+                    Err(IncorrectTypeOfArgumentError(args[0].clone()))
+                },
+                Node::Char(..) => {
+                    Ok(args[0].clone())
+                },
+                _ => {
+                    Err(IncorrectTypeOfArgumentError(args[0].clone()))
+                },
+            }
+        } else {
+            Err(IncorrectNumberOfArgumentsError(node.clone()))
+        }
+    }
+
+    pub(super) fn builtin_int(&mut self, args: &[Node], node: &Node) -> EvalResult {
+        if args.len() == 1 {
+            match args[0] {
+                Node::Number(ref n) => {
+                    Ok(n_number![n.value().trunc()])
+                },
+                Node::Char(ref c) => {
+                    Ok(n_number![c.value() as u32 as f64])
+                },
+                _ => {
+                    Err(IncorrectTypeOfArgumentError(args[0].clone()))
+                },
+            }
+        } else {
+            Err(IncorrectNumberOfArgumentsError(node.clone()))
+        }
+    }
+
+    pub(super) fn builtin_is_char(&mut self, args: &[Node], node: &Node) -> EvalResult {
+        if args.len() == 1 {
+            if let Node::Char(..) = args[0] {
+                Ok(n_bool![true])
+            } else {
+                Ok(n_bool![false])
+            }
+        } else {
+            Err(IncorrectNumberOfArgumentsError(node.clone()))
+        }
+    }
+
+    pub(super) fn builtin_str(&mut self, args: &[Node], _: &Node) -> EvalResult {
+        let mut res = String::new();
+        for a in args {
+            res.push_str(&format_for_print(a))
+        }
+        Ok(n_string![res])
+    }
+}
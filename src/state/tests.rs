@@ -1,6 +1,12 @@
-use super::State;
+use std::io::Cursor;
+use super::{State, Buffer, Rng};
+use super::doc::BUILTINS;
 use super::error::EvalError::*;
-use ast::nodes::Symbol;
+use super::error::EvalError;
+use ast::{Node, nodes};
+use ast::nodes::{Symbol, Stream};
+use chunk::Op;
+use parser::Parser;
 
 #[test]
 fn test_insert_to_and_get_from_root_state() {
@@ -8,49 +14,7 @@ fn test_insert_to_and_get_from_root_state() {
     let key = "rust-is-terrific".to_string();
     let val = n_number!(10.5);
     state.insert(Symbol::new(None, key.clone()), val.clone());
-    assert_eq!(&val, state.get(&Symbol::new(None, key.clone())).unwrap());
-}
-
-#[test]
-fn test_insert_to_and_get_from_child_state() {
-    let root_state = State::new("user".to_string());
-    let mut state = State::new_chained(&root_state);
-    let key = "rust-is-terrific".to_string();
-    let val = n_number!(10.5);
-    state.insert(Symbol::new(None, key.clone()), val.clone());
-    assert_eq!(&val, state.get(&Symbol::new(None, key.clone())).unwrap());
-}
-
-#[test]
-fn test_insert_to_root_state_and_get_from_child_state() {
-    let mut root_state = State::new("user".to_string());
-    let key = "rust-is-terrific".to_string();
-    let val = n_number!(10.5);
-    root_state.insert(Symbol::new(None, key.clone()), val.clone());
-    let state = State::new_chained(&root_state);
-    assert_eq!(&val, state.get(&Symbol::new(None, key.clone())).unwrap());
-}
-
-#[test]
-fn test_insert_to_child_state_and_get_none_from_root_state() {
-    let root_state = State::new("user".to_string());
-    let key = "rust-is-terrific".to_string();
-    let val = n_number!(10.5);
-    let mut state = State::new_chained(&root_state);
-    state.insert(Symbol::new(None, key.clone()), val.clone());
-    assert!(root_state.get(&Symbol::new(None, key.clone())).is_none());
-}
-
-#[test]
-fn test_shadow_val_in_root_state() {
-    let mut root_state = State::new("user".to_string());
-    let key = "rust-is-terrific".to_string();
-    let val_in_root = n_number!(10.5);
-    root_state.insert(Symbol::new(None, key.clone()), val_in_root);
-    let val_in_child = n_number!(0_f64);
-    let mut state = State::new_chained(&root_state);
-    state.insert(Symbol::new(None, key.clone()), val_in_child.clone());
-    assert_eq!(&val_in_child, state.get(&Symbol::new(None, key.clone())).unwrap());
+    assert_eq!(val, state.get(&Symbol::new(None, key.clone())).unwrap());
 }
 
 #[test]
@@ -59,49 +23,7 @@ fn test_insert_to_and_get_from_root_state_with_namespace() {
     let key = "rust-is-terrific".to_string();
     let val = n_number!(10.5);
     state.insert(Symbol::new(None, key.clone()), val.clone());
-    assert_eq!(&val, state.get(&Symbol::new(Some("user".to_string()), key.clone())).unwrap());
-}
-
-#[test]
-fn test_insert_to_and_get_from_child_state_with_namespace() {
-    let root_state = State::new("user".to_string());
-    let mut state = State::new_chained(&root_state);
-    let key = "rust-is-terrific".to_string();
-    let val = n_number!(10.5);
-    state.insert(Symbol::new(None, key.clone()), val.clone());
-    assert_eq!(&val, state.get(&Symbol::new(Some("user_chained".to_string()), key.clone())).unwrap());
-}
-
-#[test]
-fn test_insert_to_root_state_and_get_from_child_state_with_namespace() {
-    let mut root_state = State::new("user".to_string());
-    let key = "rust-is-terrific".to_string();
-    let val = n_number!(10.5);
-    root_state.insert(Symbol::new(None, key.clone()), val.clone());
-    let state = State::new_chained(&root_state);
-    assert_eq!(&val, state.get(&Symbol::new(Some("user".to_string()), key.clone())).unwrap());
-}
-
-#[test]
-fn test_insert_to_child_state_and_get_none_from_root_state_with_namespace() {
-    let root_state = State::new("user".to_string());
-    let key = "rust-is-terrific".to_string();
-    let val = n_number!(10.5);
-    let mut state = State::new_chained(&root_state);
-    state.insert(Symbol::new(None, key.clone()), val.clone());
-    assert!(root_state.get(&Symbol::new(Some("user".to_string()), key.clone())).is_none());
-}
-
-#[test]
-fn test_shadow_val_in_root_state_with_namespace() {
-    let mut root_state = State::new("user".to_string());
-    let key = "rust-is-terrific".to_string();
-    let val_in_root = n_number!(10.5);
-    root_state.insert(Symbol::new(None, key.clone()), val_in_root);
-    let val_in_child = n_number!(0_f64);
-    let mut state = State::new_chained(&root_state);
-    state.insert(Symbol::new(None, key.clone()), val_in_child.clone());
-    assert_eq!(&val_in_child, state.get(&Symbol::new(None, key.clone())).unwrap());
+    assert_eq!(val, state.get(&Symbol::new(Some("user".to_string()), key.clone())).unwrap());
 }
 
 #[test]
@@ -176,6 +98,14 @@ fn test_expand_call_fn() {
     assert_eq!(e, state.expand(&n).ok().unwrap());
 }
 
+#[test]
+fn test_expand_call_of_non_symbol_callee() {
+    let ref mut state = State::new("user".to_string());
+    let e = Node::Call(nodes::Call::new(n_keyword!["a"], vec![n_symbol!["m"]]));
+    let n = n_list![vec![n_keyword!["a"], n_symbol!["m"]]];
+    assert_eq!(e, state.expand(&n).ok().unwrap());
+}
+
 #[test]
 fn test_expand_call_macro() {
     let ref mut state = State::new("user".to_string());
@@ -297,13 +227,32 @@ fn test_eval_false_to_matching_bool() {
 }
 
 #[test]
-fn test_eval_nil_to_empty_list() {
+fn test_eval_nil_to_nil() {
     let ref mut state = State::new("user".to_string());
-    let expected_result = n_list![];
+    let expected_result = n_nil![];
     let actual_result = state.eval(&n_symbol!("nil"));
     assert_eq!(expected_result, actual_result.ok().unwrap());
 }
 
+#[test]
+fn test_eval_nil_is_falsey_and_distinct() {
+    let ref mut state = State::new("user".to_string());
+    let input = r#"(if nil 1 2) (if false 1 2) (if '() 1 2) (if 0 1 2)
+                   (nil? nil) (nil? '()) (some? false) (some? nil)
+                   (boolean nil) (boolean '()) (boolean false)
+                   (get {:a 1} :b) (first []) (first nil) (first "ab") (seq nil)"#;
+    let results = Parser::new(input.chars())
+        .map(|e| state.eval(&e.ok().unwrap()).ok().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(vec![n_number![2.], n_number![2.], n_number![1.], n_number![1.],
+                    n_bool![true], n_bool![false], n_bool![true], n_bool![false],
+                    n_bool![false], n_bool![true], n_bool![false],
+                    n_nil![], n_nil![], n_nil![], n_char!['a'], n_list![]],
+               results);
+    let expr = n_call!["first", vec![n_number![1.]]];
+    assert_eq!(IncorrectTypeOfArgumentError(n_number![1.]), state.eval(&expr).err().unwrap());
+}
+
 #[test]
 fn test_eval_def_special_form() {
     let num = 1_f64;
@@ -530,6 +479,82 @@ fn test_eval_eq_builtin_fn_negative_case() {
     assert_eq!(expected_result, actual_result.ok().unwrap());
 }
 
+#[test]
+fn test_eval_equality_builtin_fns_on_any_values() {
+    let ref mut state = State::new("user".to_string());
+    let input = r#"(def v [1 "a" {:k :v, :n nil}])
+                   (= v [1 "a" {:n nil, :k :v}] (with-meta v {:m 1}))
+                   (= "a" "b") (= :a :a) (= '(1 2) [1 2]) (= nil nil) (= 1)
+                   (not= 1 1 1) (not= 'a 'b)
+                   (identical? v v) (identical? v [1 "a" {:k :v, :n nil}]) (identical? :a :a)
+                   (compare 1 2) (compare "b" "a") (compare [1 2] [1 2]) (compare nil 0)
+                   (compare :a "a")
+                   (= (hash {:a 1, :b [2]}) (hash {:b [2], :a 1}))
+                   (let [f (fn [x] x)] (identical? f f))
+                   (identical? (fn [x] x) (fn [x] x))
+                   (defn make-adder [n] (fn [x] (+ x n)))
+                   (= (make-adder 1) (make-adder 2)) (= (make-adder 1) (make-adder 1))
+                   (compare (make-adder 1) (make-adder 2))"#;
+    let results = Parser::new(input.chars())
+        .map(|e| state.eval(&e.ok().unwrap()).ok().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(vec![n_bool![true], n_bool![false], n_bool![true], n_bool![false],
+                    n_bool![true], n_bool![true], n_bool![false], n_bool![true],
+                    n_bool![true], n_bool![false], n_bool![true],
+                    n_number![-1.], n_number![1.], n_number![0.], n_number![-1.], n_number![1.],
+                    n_bool![true], n_bool![true], n_bool![false]],
+               results[1..20].to_vec());
+    assert_eq!(vec![n_bool![false], n_bool![true], n_number![-1.]], results[21..].to_vec());
+}
+
+#[test]
+fn test_eval_math_builtin_fns() {
+    let ref mut state = State::new("user".to_string());
+    let input = "(inc 1) (dec 1) (quot -7 2) (rem -7 2) (mod -7 2) (mod 7 -2)
+                 (abs -3) (min 3 1 2) (max 3 1 2) (<= 1 1 2) (>= 2 3) (zero? 0) (pos? -1) (neg? -1) (even? 4) (odd? 4)
+                 (sqrt 16) (pow 2 10) (exp 0) (log E) (sin 0) (cos PI) (atan2 1 1)
+                 (floor -1.5) (ceil 1.2) (round 2.5) (round -2.5)
+                 (bit-and 12 10) (bit-or 12 10 1) (bit-xor 12 10) (bit-not 0)
+                 (bit-shift-left 1 4) (bit-shift-right -16 2)";
+    let results = Parser::new(input.chars())
+        .map(|e| state.eval(&e.ok().unwrap()).ok().unwrap())
+        .collect::<Vec<_>>();
+    let expected = vec![n_number![2.], n_number![0.], n_number![-3.], n_number![-1.], n_number![1.],
+                        n_number![-1.], n_number![3.], n_number![1.], n_number![3.],
+                        n_bool![true], n_bool![false], n_bool![true], n_bool![false], n_bool![true],
+                        n_bool![true], n_bool![false],
+                        n_number![4.], n_number![1024.], n_number![1.], n_number![1.], n_number![0.],
+                        n_number![-1.], n_number![::std::f64::consts::FRAC_PI_4],
+                        n_number![-2.], n_number![2.], n_number![3.], n_number![-3.],
+                        n_number![8.], n_number![15.], n_number![6.], n_number![-1.],
+                        n_number![16.], n_number![-4.]];
+    assert_eq!(expected, results);
+}
+
+#[test]
+fn test_eval_math_errors() {
+    let ref mut state = State::new("user".to_string());
+    let input = r#"(/ 1 0) (/ 0) (quot 1 0) (mod 1 0)
+                   (defn f [x] (rem x 0)) (f 1) (defn g [x] (/ x 0)) (g 1)
+                   (even? 1.5) (bit-and 1 "2") (sqrt :a) (min) (pow 0 -1) (pow 0 0)"#;
+    let results = Parser::new(input.chars())
+        .map(|e| state.eval(&e.ok().unwrap()))
+        .collect::<Vec<_>>();
+    let div = |f: &str, x: Node| DivideByZeroError(n_call![f, vec![x, n_number![0.]]]);
+    assert_eq!(Err(div("/", n_number![1.])), results[0]);
+    assert_eq!(Err(DivideByZeroError(n_call!["/", vec![n_number![0.]]])), results[1]);
+    assert_eq!(Err(div("quot", n_number![1.])), results[2]);
+    assert_eq!(Err(div("mod", n_number![1.])), results[3]);
+    assert_eq!(Err(div("rem", n_symbol!["x"])), results[5]);
+    assert_eq!(Err(div("/", n_symbol!["x"])), results[7]);
+    assert_eq!(Err(IncorrectTypeOfArgumentError(n_number![1.5])), results[8]);
+    assert_eq!(Err(IncorrectTypeOfArgumentError(n_string!["2"])), results[9]);
+    assert_eq!(Err(IncorrectTypeOfArgumentError(n_keyword!["a"])), results[10]);
+    assert_eq!(Err(IncorrectNumberOfArgumentsError(n_call!["min", vec![]])), results[11]);
+    assert_eq!(Err(DivideByZeroError(n_call!["pow", vec![n_number![0.], n_number![-1.]]])), results[12]);
+    assert_eq!(Ok(n_number![1.]), results[13]);
+}
+
 #[test]
 fn test_eval_if_builtin_fn() {
     let ref mut state = State::new("user".to_string());
@@ -546,3 +571,998 @@ fn test_eval_if_builtin_fn() {
     let expected_result = n_number![0.];
     assert_eq!(expected_result, actual_result.ok().unwrap());
 }
+
+#[test]
+fn test_eval_print_builtin_fns_write_to_out() {
+    let ref mut state = State::new("user".to_string());
+    let buf = Buffer::new();
+    state.set_out(buf.clone());
+    let input = r#"(print "a" 1 :k) (println "b") (pr "c" 2) (prn "d")"#;
+    for expr in Parser::new(input.chars()) {
+        assert_eq!(n_nil![], state.eval(&expr.ok().unwrap()).ok().unwrap());
+    }
+    assert_eq!("a 1 :kb\n\"c\" 2\"d\"\n", buf.contents());
+}
+
+#[test]
+fn test_eval_print_builtin_fns_write_to_a_given_stream() {
+    let ref mut state = State::new("user".to_string());
+    let (out, err) = (Buffer::new(), Buffer::new());
+    state.set_out(out.clone());
+    state.set_err(err.clone());
+    let input = r#"(println *err* "warn:" 1) (pr *err* "e") (print *out* "o")
+                   (printf *err* "%d%%%n" 5) (defn warn [& xs] (apply println *err* xs)) (warn :a)"#;
+    for expr in Parser::new(input.chars()) {
+        state.eval(&expr.ok().unwrap()).ok().unwrap();
+    }
+    assert_eq!("o", out.contents());
+    assert_eq!("warn: 1\n\"e\"5%\n:a\n", err.contents());
+    let expr = Parser::new("(println *in* 1)".chars()).next().unwrap().ok().unwrap();
+    assert_eq!(IncorrectTypeOfArgumentError(Node::Stream(Stream::In)),
+               state.eval(&expr).err().unwrap());
+}
+
+#[test]
+fn test_eval_printf_builtin_fn() {
+    let ref mut state = State::new("user".to_string());
+    let buf = Buffer::new();
+    state.set_out(buf.clone());
+    let input = r#"(printf "%s has %d items, %.2f%% done%n" "queue" 3 12.5)"#;
+    let expr = Parser::new(input.chars()).next().unwrap().ok().unwrap();
+    state.eval(&expr).ok().unwrap();
+    assert_eq!("queue has 3 items, 12.50% done\n", buf.contents());
+    let input = r#"(printf "%d" "queue")"#;
+    let expr = Parser::new(input.chars()).next().unwrap().ok().unwrap();
+    assert_eq!(IncorrectTypeOfArgumentError(n_string!["queue"]),
+               state.eval(&expr).err().unwrap());
+}
+
+#[test]
+fn test_eval_read_line_builtin_fn() {
+    let ref mut state = State::new("user".to_string());
+    state.set_in(Cursor::new("first\r\nsecond".as_bytes().to_vec()));
+    let expr = n_call!["read-line", vec![]];
+    assert_eq!(n_string!["first"], state.eval(&expr).ok().unwrap());
+    let expr = n_call!["read-line", vec![n_symbol!["*in*"]]];
+    assert_eq!(n_string!["second"], state.eval(&expr).ok().unwrap());
+    assert_eq!(n_nil![], state.eval(&expr).ok().unwrap());
+    let expr = n_call!["read-line", vec![n_symbol!["*out*"]]];
+    assert_eq!(IncorrectTypeOfArgumentError(Node::Stream(Stream::Out)),
+               state.eval(&expr).err().unwrap());
+}
+
+#[test]
+fn test_eval_with_out_str_builtin_fn_captures_and_restores_out() {
+    let ref mut state = State::new("user".to_string());
+    let buf = Buffer::new();
+    state.set_out(buf.clone());
+    let input = r#"(def f (fn [x] (print "in f" x)))
+                   (with-out-str (print "a") (f 1))
+                   (print "after")"#;
+    let results = Parser::new(input.chars())
+        .map(|e| state.eval(&e.ok().unwrap()).ok().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(n_string!["ain f 1"], results[1]);
+    assert_eq!("after", buf.contents());
+}
+
+#[test]
+fn test_eval_command_line_args() {
+    let ref mut state = State::new("user".to_string());
+    state.set_command_line_args(vec!["-v".to_string(), "log.txt".to_string()]);
+    assert_eq!(n_vec![vec![n_string!["-v"], n_string!["log.txt"]]],
+               state.eval(&n_symbol!["*command-line-args*"]).ok().unwrap());
+}
+
+#[test]
+fn test_eval_exit_builtin_fn() {
+    let ref mut state = State::new("user".to_string());
+    assert_eq!(Exit(0), state.eval(&n_call!["exit", vec![]]).err().unwrap());
+    let expr = n_call!["if", vec![n_bool![true],
+                                  n_call!["exit", vec![n_number![3.]]],
+                                  n_number![1.]]];
+    assert_eq!(Exit(3), state.eval(&expr).err().unwrap());
+    let expr = n_call!["exit", vec![n_string!["3"]]];
+    assert_eq!(IncorrectTypeOfArgumentError(n_string!["3"]), state.eval(&expr).err().unwrap());
+}
+
+#[test]
+fn test_eval_anon_fn_shorthand() {
+    let ref mut state = State::new("user".to_string());
+    let input = "(def twice #(* % 2))
+                 (twice 4)
+                 (def sub #(- %2 %1))
+                 (sub 1 5)
+                 (def tail #(quote (~@%&)))
+                 (tail 1 2 3)";
+    let results = Parser::new(input.chars())
+        .map(|e| state.eval(&e.ok().unwrap()))
+        .collect::<Vec<_>>();
+    assert_eq!(Ok(n_number![8.]), results[1]);
+    assert_eq!(Ok(n_number![4.]), results[3]);
+    assert_eq!(Ok(n_list![vec![n_number![1.], n_number![2.], n_number![3.]]]), results[5]);
+}
+
+#[test]
+fn test_eval_fn_with_rest_params() {
+    let ref mut state = State::new("user".to_string());
+    let input = "(def f (fn [a & more] (quote (~a ~@more))))
+                 (f 1)
+                 (f 1 2 3)
+                 (f)";
+    let results = Parser::new(input.chars())
+        .map(|e| state.eval(&e.ok().unwrap()))
+        .collect::<Vec<_>>();
+    assert_eq!(Ok(n_list![vec![n_number![1.]]]), results[1]);
+    assert_eq!(Ok(n_list![vec![n_number![1.], n_number![2.], n_number![3.]]]), results[2]);
+    assert_eq!(Err(IncorrectNumberOfArgumentsError(n_call!["f", vec![]])), results[3]);
+}
+
+#[test]
+fn test_eval_char_builtin_fns() {
+    let ref mut state = State::new("user".to_string());
+    let input = r#"(char 65) (int \A) (char? \a) (char? "a") (char \b) (char -1)"#;
+    let results = Parser::new(input.chars())
+        .map(|e| state.eval(&e.ok().unwrap()))
+        .collect::<Vec<_>>();
+    assert_eq!(Ok(n_char!['A']), results[0]);
+    assert_eq!(Ok(n_number![65.]), results[1]);
+    assert_eq!(Ok(n_bool![true]), results[2]);
+    assert_eq!(Ok(n_bool![false]), results[3]);
+    assert_eq!(Ok(n_char!['b']), results[4]);
+    assert_eq!(Err(IncorrectTypeOfArgumentError(n_number![-1.])), results[5]);
+}
+
+#[test]
+fn test_eval_seq_over_string_yields_chars() {
+    let ref mut state = State::new("user".to_string());
+    let input = r#"(seq "ab") (seq "") (str "x" \y 1 :z)"#;
+    let results = Parser::new(input.chars())
+        .map(|e| state.eval(&e.ok().unwrap()))
+        .collect::<Vec<_>>();
+    assert_eq!(Ok(n_list![vec![n_char!['a'], n_char!['b']]]), results[0]);
+    assert_eq!(Ok(n_list![]), results[1]);
+    assert_eq!(Ok(n_string!["xy1:z"]), results[2]);
+}
+
+#[test]
+fn test_eval_regex_builtin_fns() {
+    let ref mut state = State::new("user".to_string());
+    let input = r#"(def line "2026-10-17 ERROR disk=93 cpu=12")
+                   (re-find #"\d+" line)
+                   (re-find #"(\w+)=(\d+)" line)
+                   (re-matches #"\d+" line)
+                   (re-matches #"(\d+)-.*" line)
+                   (re-seq #"\w+=\d+" line)
+                   (re-groups #"ERROR" line)
+                   (re-replace #"(\w+)=(\d+)" line "$1:$2")
+                   (re-find #"WARN" line)
+                   (re-find (re-pattern "c.u") line)
+                   (re-matches #"a|ab" "ab")
+                   (re-matches #"(a|ab)(c|bcd)" "abcd")
+                   (re-matches #"(?x)a # comment" "a")
+                   (re-matches #"(?x)a # comment" "ab")"#;
+    let results = Parser::new(input.chars())
+        .map(|e| state.eval(&e.ok().unwrap()).ok().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(n_string!["2026"], results[1]);
+    assert_eq!(n_vec![vec![n_string!["disk=93"], n_string!["disk"], n_string!["93"]]], results[2]);
+    assert_eq!(n_nil![], results[3]);
+    assert_eq!(n_vec![vec![n_string!["2026-10-17 ERROR disk=93 cpu=12"], n_string!["2026"]]],
+               results[4]);
+    assert_eq!(n_list![vec![n_string!["disk=93"], n_string!["cpu=12"]]], results[5]);
+    assert_eq!(n_vec![vec![n_string!["ERROR"]]], results[6]);
+    assert_eq!(n_string!["2026-10-17 ERROR disk:93 cpu:12"], results[7]);
+    assert_eq!(n_nil![], results[8]);
+    assert_eq!(n_string!["cpu"], results[9]);
+    assert_eq!(n_string!["ab"], results[10]);
+    assert_eq!(n_vec![vec![n_string!["abcd"], n_string!["a"], n_string!["bcd"]]], results[11]);
+    assert_eq!(n_string!["a"], results[12]);
+    assert_eq!(n_nil![], results[13]);
+}
+
+#[test]
+fn test_eval_invalid_regex_literal_to_parser_error() {
+    let ref mut state = State::new("user".to_string());
+    let input = r#"(re-find #"[a-" "abc")"#;
+    let err = Parser::new(input.chars()).next().unwrap().err().unwrap();
+    match EvalError::from(err) {
+        ParserError(..) => {},
+        other => panic!("unexpected {:?}", other),
+    }
+    let expr = n_call!["re-find", vec![n_string!["a"], n_string!["abc"]]];
+    assert_eq!(IncorrectTypeOfArgumentError(n_string!["a"]), state.eval(&expr).err().unwrap());
+}
+
+#[test]
+fn test_eval_meta_builtin_fns() {
+    let ref mut state = State::new("user".to_string());
+    let input = r#"(def v (with-meta [1 2] {:tag :pair}))
+                   (meta v)
+                   (= 2 (get (meta (vary-meta v assoc :size 2)) :size 0))
+                   (meta (vary-meta v (fn [m k] (dissoc m k)) :tag))
+                   (meta '^:private ^{:doc "d"} x)
+                   (meta [1 2])
+                   (with-meta v nil)"#;
+    let results = Parser::new(input.chars())
+        .map(|e| state.eval(&e.ok().unwrap()).ok().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(n_map![vec![(n_keyword!["tag"], n_keyword!["pair"])]], results[1]);
+    assert_eq!(n_bool![true], results[2]);
+    assert_eq!(n_map![vec![]], results[3]);
+    assert_eq!(n_map![vec![(n_keyword!["private"], n_bool![true]), (n_keyword!["doc"], n_string!["d"])]],
+               results[4]);
+    assert_eq!(n_nil![], results[5]);
+    assert_eq!(n_vec![vec![n_number![1.], n_number![2.]]], results[6]);
+    assert_eq!(None, results[6].meta());
+}
+
+#[test]
+fn test_eval_meta_does_not_affect_equality() {
+    let ref mut state = State::new("user".to_string());
+    let input = "(with-meta 'x {:a 1})";
+    let result = state.eval(&Parser::new(input.chars()).next().unwrap().ok().unwrap()).ok().unwrap();
+    assert_eq!(n_symbol!["x"], result);
+    assert!(result.meta().is_some());
+}
+
+#[test]
+fn test_eval_def_records_var_meta() {
+    let ref mut state = State::new("user".to_string());
+    state.set_file("rules.ls".to_string());
+    let input = "(def m (macro [n v] `(def ~n ~v)))\n\
+                 (def ^:private a 1)\n  \
+                 (m b 2)\n\
+                 (meta #'a)\n\
+                 (meta #'b)\n\
+                 #'a\n\
+                 (var c)";
+    let results = Parser::new(input.chars())
+        .map(|e| state.eval(&e.ok().unwrap()))
+        .collect::<Vec<_>>();
+    let a_meta = results[3].as_ref().ok().unwrap();
+    if let Node::Map(ref m) = *a_meta {
+        assert_eq!(Some(&n_bool![true]), m.get(&n_keyword!["private"]));
+        assert_eq!(Some(&n_number![2.]), m.get(&n_keyword!["line"]));
+        assert_eq!(Some(&n_string!["rules.ls"]), m.get(&n_keyword!["file"]));
+        assert_eq!(Some(&n_symbol!["user"]), m.get(&n_keyword!["ns"]));
+        assert_eq!(Some(&n_symbol!["a"]), m.get(&n_keyword!["name"]));
+    } else {
+        panic!("unexpected {}", a_meta)
+    }
+    let b_meta = results[4].as_ref().ok().unwrap();
+    if let Node::Map(ref m) = *b_meta {
+        assert_eq!(Some(&n_number![3.]), m.get(&n_keyword!["line"]));
+        assert_eq!(Some(&n_number![4.]), m.get(&n_keyword!["column"]));
+    } else {
+        panic!("unexpected {}", b_meta)
+    }
+    assert_eq!("#'user/a", format!("{}", results[5].as_ref().ok().unwrap()));
+    assert_eq!(Err(ResolveError("c".to_string())), results[6]);
+}
+
+#[test]
+fn test_eval_map_literal_and_builtin_fns() {
+    let ref mut state = State::new("user".to_string());
+    let input = "(def m {:a (+ 1 2) :b 'c})
+                 (get m :a)
+                 (get m :z :none)
+                 (assoc m :a 0 :d 4)
+                 (dissoc m :a)
+                 (get [10 20] 1)
+                 '{:x y}";
+    let results = Parser::new(input.chars())
+        .map(|e| state.eval(&e.ok().unwrap()).ok().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(n_map![vec![(n_keyword!["a"], n_number![3.]), (n_keyword!["b"], n_symbol!["c"])]],
+               results[0]);
+    assert_eq!(n_number![3.], results[1]);
+    assert_eq!(n_keyword!["none"], results[2]);
+    assert_eq!("{:a 0, :b c, :d 4}", format!("{}", results[3]));
+    assert_eq!("{:b c}", format!("{}", results[4]));
+    assert_eq!(n_number![20.], results[5]);
+    assert_eq!(n_map![vec![(n_keyword!["x"], n_symbol!["y"])]], results[6]);
+}
+
+#[test]
+fn test_eval_call_of_any_callee_expression() {
+    let ref mut state = State::new("user".to_string());
+    let input = "((fn [x] x) 1)
+                 (defn make-adder [n] (fn [x] (+ x n)))
+                 ((make-adder 2) 3)
+                 (def person {:name \"Ann\"})
+                 (:name person)
+                 (:age person 30)
+                 (person :name)
+                 ([10 20 30] 2)
+                 (let [k :name] (k person))
+                 (defn lookup [f x] (f x))
+                 (lookup :name person)
+                 (lookup [:a :b] 1)";
+    let results = Parser::new(input.chars())
+        .map(|e| state.eval(&e.ok().unwrap()).ok().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(n_number![1.], results[0]);
+    assert_eq!(n_number![5.], results[2]);
+    assert_eq!(n_string!["Ann"], results[4]);
+    assert_eq!(n_number![30.], results[5]);
+    assert_eq!(n_string!["Ann"], results[6]);
+    assert_eq!(n_number![30.], results[7]);
+    assert_eq!(n_string!["Ann"], results[8]);
+    assert_eq!(n_string!["Ann"], results[10]);
+    assert_eq!(n_keyword!["b"], results[11]);
+    let expr = Parser::new("(1 2)".chars()).next().unwrap().ok().unwrap();
+    match state.eval(&expr).err().unwrap() {
+        IncorrectTypeOfArgumentError(..) => {},
+        e => panic!("unexpected {:?}", e),
+    }
+    let expr = Parser::new("(:a)".chars()).next().unwrap().ok().unwrap();
+    match state.eval(&expr).err().unwrap() {
+        IncorrectNumberOfArgumentsError(..) => {},
+        e => panic!("unexpected {:?}", e),
+    }
+}
+
+#[test]
+fn test_eval_set_literals_called_as_lookup_fns() {
+    let ref mut state = State::new("user".to_string());
+    let input = "(def a 1)
+                 #{a (+ a 1)}
+                 (= #{1 2} #{2 a})
+                 (#{:x :y} :y)
+                 (#{:x :y} :z)
+                 (#{:x :y} :z 0)
+                 (:x #{:x})
+                 (get #{a} 1)
+                 (let [s #{[a]}] (s [1]))
+                 '#{a}
+                 `#{~a ~@[2 3]}
+                 (seq #{3})";
+    let results = Parser::new(input.chars())
+        .map(|e| state.eval(&e.ok().unwrap()).ok().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(n_set![vec![n_number![1.], n_number![2.]]], results[1]);
+    assert_eq!(n_bool![true], results[2]);
+    assert_eq!(n_keyword!["y"], results[3]);
+    assert_eq!(n_nil![], results[4]);
+    assert_eq!(n_number![0.], results[5]);
+    assert_eq!(n_keyword!["x"], results[6]);
+    assert_eq!(n_number![1.], results[7]);
+    assert_eq!(n_vec![vec![n_number![1.]]], results[8]);
+    assert_eq!(n_set![vec![n_symbol!["a"]]], results[9]);
+    assert_eq!(n_set![vec![n_number![1.], n_number![2.], n_number![3.]]], results[10]);
+    assert_eq!(n_list![vec![n_number![3.]]], results[11]);
+}
+
+#[test]
+fn test_eval_builtins_as_fn_values() {
+    let ref mut state = State::new("user".to_string());
+    let input = "((fn [f] (f true)) not)
+                 (def my-not not)
+                 (my-not nil)
+                 (some not [1 false])
+                 (apply max [1 3 2])
+                 (let [f str] (f :a 1))
+                 (str inc)
+                 (def inc 0)
+                 inc";
+    let results = Parser::new(input.chars())
+        .map(|e| state.eval(&e.ok().unwrap()).ok().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(n_bool![false], results[0]);
+    assert_eq!(n_bool![true], results[2]);
+    assert_eq!(n_bool![true], results[3]);
+    assert_eq!(n_number![3.], results[4]);
+    assert_eq!(n_string![":a1"], results[5]);
+    assert_eq!(n_string!["#<builtin inc>"], results[6]);
+    assert_eq!(n_number![0.], results[8]);
+    let expr = Parser::new("((fn [f] (f 'a)) dec)".chars()).next().unwrap().ok().unwrap();
+    assert_eq!(IncorrectTypeOfArgumentError(n_symbol!["a"]), state.eval(&expr).err().unwrap());
+    let expr = Parser::new("((fn [f] (f)) not)".chars()).next().unwrap().ok().unwrap();
+    match state.eval(&expr).err().unwrap() {
+        IncorrectNumberOfArgumentsError(ref n) => assert!(n.is_call_of("f")),
+        e => panic!("unexpected {:?}", e),
+    }
+    let expr = Parser::new("(def w with-out-str)".chars()).next().unwrap().ok().unwrap();
+    assert_eq!(ResolveError("with-out-str".to_string()), state.eval(&expr).err().unwrap());
+}
+
+#[test]
+fn test_eval_vector_literal_elements() {
+    let ref mut state = State::new("user".to_string());
+    let input = "(def a 1)
+                 [a (+ 1 2) [a :k] {:x a}]
+                 (defn pair [x] [x (* x 2)])
+                 (pair 4)
+                 '[a b]";
+    let results = Parser::new(input.chars())
+        .map(|e| state.eval(&e.ok().unwrap()).ok().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(n_vec![vec![n_number![1.], n_number![3.],
+                           n_vec![vec![n_number![1.], n_keyword!["k"]]],
+                           n_map![vec![(n_keyword!["x"], n_number![1.])]]]],
+               results[1]);
+    assert_eq!(n_vec![vec![n_number![4.], n_number![8.]]], results[3]);
+    assert_eq!(n_vec![vec![n_symbol!["a"], n_symbol!["b"]]], results[4]);
+    let v = n_vec![vec![n_number![1.], n_string!["s"]]];
+    assert_eq!(v, state.compile(&v).ok().unwrap());
+}
+
+#[test]
+fn test_eval_apply_builtin_fn() {
+    let ref mut state = State::new("user".to_string());
+    let input = "(apply + 1 2 [3 4])
+                 (defn f [& xs] xs)
+                 (apply f '(a b))
+                 (apply (fn [x y] [y x]) 1 '(z))
+                 (apply :a [{:a 3}])
+                 (apply str \"ab\" (seq \"cd\"))
+                 (defn total [v] (apply + v))
+                 (total [1 2 3])";
+    let results = Parser::new(input.chars())
+        .map(|e| state.eval(&e.ok().unwrap()).ok().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(n_number![10.], results[0]);
+    assert_eq!(n_list![vec![n_symbol!["a"], n_symbol!["b"]]], results[2]);
+    assert_eq!(n_vec![vec![n_symbol!["z"], n_number![1.]]], results[3]);
+    assert_eq!(n_number![3.], results[4]);
+    assert_eq!(n_string!["abcd"], results[5]);
+    assert_eq!(n_number![6.], results[7]);
+    let expr = Parser::new("(apply f 1)".chars()).next().unwrap().ok().unwrap();
+    match state.eval(&expr).err().unwrap() {
+        IncorrectTypeOfArgumentError(n) => assert_eq!(n_number![1.], n),
+        e => panic!("unexpected {:?}", e),
+    }
+}
+
+#[test]
+fn test_eval_docstrings_and_doc_builtin_fn() {
+    let ref mut state = State::new("user".to_string());
+    let input = r#"(def limit "Maximum number of retries." 3)
+                   (defn add "Adds x and y." [x y] (+ x y))
+                   (defmacro unless [t e] `(if ~t nil ~e))
+                   (add limit 1)
+                   (with-out-str (doc add))
+                   (with-out-str (doc limit))
+                   (with-out-str (doc if))
+                   (with-out-str (doc lust.core/re-find))"#;
+    let results = Parser::new(input.chars())
+        .map(|e| state.eval(&e.ok().unwrap()).ok().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(n_number![4.], results[3]);
+    assert_eq!(n_string!["-------------------------\nuser/add\n([x y])\n  Adds x and y.\n"], results[4]);
+    assert_eq!(n_string!["-------------------------\nuser/limit\n  Maximum number of retries.\n"],
+               results[5]);
+    assert_eq!(n_string!["-------------------------\nlust.core/if\n([test then] [test then else])\n\
+                          Special Form\n  Evaluates then if test is neither nil nor false, otherwise else, \
+                          or returns nil\n  when there is no else.\n"],
+               results[6]);
+    assert_eq!(n_string!["-------------------------\nlust.core/re-find\n([re s])\n  \
+                          Returns the first match of re in s, or a vector of the match and its groups\n  \
+                          when re has groups. Returns nil when there is no match.\n"],
+               results[7]);
+    let expr = Parser::new("(doc missing)".chars()).next().unwrap().ok().unwrap();
+    assert_eq!(ResolveError("missing".to_string()), state.eval(&expr).err().unwrap());
+}
+
+#[test]
+fn test_eval_find_doc_apropos_dir_and_source_builtin_fns() {
+    let ref mut state = State::new("user".to_string());
+    let input = r#"(defn parse-line "Splits a log line." [l] (re-seq #"\S+" l))
+                   (def ^:private parse-cache 1)
+                   (apropos "parse")
+                   (apropos #"^re-(find|seq)$")
+                   (with-out-str (find-doc "log line"))
+                   (with-out-str (dir user))
+                   (with-out-str (source parse-line))
+                   (with-out-str (source str))"#;
+    let results = Parser::new(input.chars())
+        .map(|e| state.eval(&e.ok().unwrap()).ok().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(n_list![vec![n_symbol![Some("user".to_string()), "parse-cache"],
+                            n_symbol![Some("user".to_string()), "parse-line"],
+                            n_symbol![Some("lust.core".to_string()), "parse-inst"]]],
+               results[2]);
+    assert_eq!(n_list![vec![n_symbol![Some("lust.core".to_string()), "re-find"],
+                            n_symbol![Some("lust.core".to_string()), "re-seq"]]],
+               results[3]);
+    assert_eq!(n_string!["-------------------------\nuser/parse-line\n([l])\n  Splits a log line.\n"],
+               results[4]);
+    assert_eq!(n_string!["parse-line\n"], results[5]);
+    assert_eq!(n_string!["(defn parse-line \"Splits a log line.\" [l] (re-seq #\"\\S+\" l))\n"],
+               results[6]);
+    assert_eq!(n_string!["lust.core/str is implemented in Rust\n"], results[7]);
+}
+
+#[test]
+fn test_eval_source_builtin_fn_reads_loaded_file() {
+    let ref mut state = State::new("user".to_string());
+    let input = r#"(load "./tests/ns/some.ls")
+                   (with-out-str (source some/some-symbol))"#;
+    let results = Parser::new(input.chars())
+        .map(|e| state.eval(&e.ok().unwrap()).ok().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(n_string!["(def some-symbol 3)\n"], results[1]);
+}
+
+#[test]
+fn test_every_builtin_is_documented() {
+    let dispatch = include_str!("mod.rs");
+    for line in dispatch.lines().map(|l| l.trim()).filter(|l| l.ends_with("if !is_contains => {")) {
+        let name = line.split('"').nth(1).unwrap();
+        assert!(BUILTINS.iter().any(|b| b.0 == name), "{} is not documented", name);
+    }
+}
+
+#[test]
+fn test_eval_syntax_quote_qualifies_symbols() {
+    let ref mut state = State::new("user".to_string());
+    let input = "(in-ns 'other)
+                 (def x 1)
+                 (in-ns 'user)
+                 (refer y other/x)
+                 (def a 2)
+                 `(if a (+ y b) (let [c nil] c))
+                 (def l '(1 2))
+                 `[0 ~@l ~a]";
+    let results = Parser::new(input.chars())
+        .map(|e| state.eval(&e.ok().unwrap()).ok().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!("(if user/a (lust.core/+ other/x user/b) (let [user/c nil] user/c))",
+               format!("{}", results[5]));
+    assert_eq!(n_vec![vec![n_number![0.], n_number![1.], n_number![2.], n_number![2.]]], results[7]);
+}
+
+#[test]
+fn test_eval_syntax_quote_auto_gensym() {
+    let ref mut state = State::new("user".to_string());
+    let input = "(defmacro twice [v] `(let [x# ~v] (+ x# x#)))
+                 (let [x 2] (twice (+ x 1)))
+                 `(x# x# y#)
+                 `(x# `(x# ~x#))";
+    let results = Parser::new(input.chars())
+        .map(|e| state.eval(&e.ok().unwrap()).ok().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(n_number![6.], results[1]);
+    if let Node::List(ref l) = results[2] {
+        let name = format!("{}", l[0]);
+        assert!(name.starts_with("x__") && name.ends_with("__auto__"));
+        assert_eq!(l[0], l[1]);
+        assert!(l[0] != l[2]);
+    } else {
+        panic!("unexpected {}", results[2])
+    }
+    if let Node::List(ref l) = results[3] {
+        let inner = format!("{}", l[1]);
+        assert!(inner.starts_with("(syntax-quote (x__"));
+        assert!(inner.ends_with(&format!("(unquote {})))", l[0])));
+        assert!(!inner.starts_with(&format!("(syntax-quote ({} ", l[0])));
+    } else {
+        panic!("unexpected {}", results[3])
+    }
+}
+
+#[test]
+fn test_eval_nested_syntax_quote() {
+    let ref mut state = State::new("user".to_string());
+    let input = "(def d 5)
+                 `(a `(b ~(c ~d)))
+                 (defmacro def-const-fn [n v] `(defmacro ~n [] `(quote ~~v)))
+                 (def-const-fn five (+ 2 3))
+                 (five)";
+    let results = Parser::new(input.chars())
+        .map(|e| state.eval(&e.ok().unwrap()).ok().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!("(user/a (syntax-quote (user/b (unquote (user/c 5)))))", format!("{}", results[1]));
+    assert_eq!(n_number![5.], results[4]);
+}
+
+#[test]
+fn test_eval_macroexpand() {
+    let ref mut state = State::new("user".to_string());
+    let input = "(defmacro unless [t e] `(if ~t nil ~e))
+                 (defmacro when-not [t e] `(unless ~t ~e))
+                 (macroexpand-1 '(when-not (> a 1) (f a)))
+                 (macroexpand '(when-not (> a 1) (f a)))
+                 (macroexpand '(f a))
+                 (macroexpand-all '(defn g [a] [(when-not a 1) '(unless a 2)]))";
+    let results = Parser::new(input.chars())
+        .map(|e| state.eval(&e.ok().unwrap()).ok().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!("(user/unless (> a 1) (f a))", format!("{}", results[2]));
+    assert_eq!("(if (> a 1) nil (f a))", format!("{}", results[3]));
+    assert_eq!("(f a)", format!("{}", results[4]));
+    assert_eq!("(def g (fn [a] [(if a nil 1) (quote (unless a 2))]))", format!("{}", results[5]));
+}
+
+#[test]
+fn test_eval_expands_fn_body_once() {
+    let ref mut state = State::new("user".to_string());
+    let buf = Buffer::new();
+    state.set_out(buf.clone());
+    let input = "(defmacro noisy [x] (print \"expanded\") x)
+                 (defn f [a] (noisy (+ a 1)))
+                 (f 1)
+                 (f 2)
+                 (defn g [] (late 1))
+                 (defmacro late [x] `(+ ~x 1))
+                 (g)";
+    let results = Parser::new(input.chars())
+        .map(|e| state.eval(&e.ok().unwrap()).ok().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(n_number![3.], results[3]);
+    assert_eq!(n_number![2.], results[6]);
+    state.flush().ok();
+    assert_eq!("expanded", buf.contents());
+}
+
+#[test]
+fn test_eval_expands_late_macro_once() {
+    let ref mut state = State::new("user".to_string());
+    let buf = Buffer::new();
+    state.set_out(buf.clone());
+    let input = "(defn g [a] (let [b (* a 10)] (late a b)))
+                 (defmacro late [x y] (print \"expanded\") `(+ ~x ~y))
+                 [(g 1) (g 2) (g 3)]";
+    let results = Parser::new(input.chars())
+        .map(|e| state.eval(&e.ok().unwrap()).ok().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(n_vec![vec![n_number![11.], n_number![22.], n_number![33.]]], results[2]);
+    state.flush().ok();
+    assert_eq!("expanded", buf.contents());
+}
+
+#[test]
+fn test_eval_expands_late_macro_again_after_redefinition() {
+    let ref mut state = State::new("user".to_string());
+    let input = "(defn g [] (m2 1))
+                 (def m2 (macro [x] `(+ ~x 1)))
+                 (g)
+                 (def m2 (macro [x] `(+ ~x 2)))
+                 (g)
+                 (def m2 (fn [x] (* x 10)))
+                 (g)";
+    let results = Parser::new(input.chars())
+        .map(|e| state.eval(&e.ok().unwrap()).ok().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(n_number![2.], results[2]);
+    assert_eq!(n_number![3.], results[4]);
+    assert_eq!(n_number![10.], results[6]);
+}
+
+#[test]
+fn test_compile_produces_core_forms() {
+    let ref mut state = State::new("user".to_string());
+    let input = "(defmacro unless [t e] `(if ~t nil ~e))
+                 (fn [a] (unless a {:k (unless a 1)}))";
+    let mut exprs = Parser::new(input.chars()).map(|e| e.ok().unwrap());
+    state.eval(&exprs.next().unwrap()).ok().unwrap();
+    let compiled = state.compile(&exprs.next().unwrap()).ok().unwrap();
+    let inner = n_call!["if", vec![n_symbol!["a"], n_nil![], n_number![1.]]];
+    let outer = n_call!["if", vec![n_symbol!["a"], n_nil![],
+                                   n_map![vec![(n_keyword!["k"], inner)]]]];
+    assert_eq!(n_fn![[n_symbol!["a"]], [outer]], compiled);
+}
+
+#[test]
+fn test_eval_fn_calls_on_vm() {
+    let ref mut state = State::new("user".to_string());
+    let input = "(defn count-down [n] (if (= n 0) :done (count-down (- n 1))))
+                 (count-down 50000)
+                 (defn adder [x] (fn [y] (+ x y)))
+                 (let [add2 (adder 2) x 10] (add2 x))
+                 (defn f [a & more] `(~a ~@more))
+                 (f 1 2 3)
+                 (defn g [s] (let [t (str s \"!\")] (str t t)))
+                 (g \"a\")
+                 (f)
+                 (+ 1 (g \"a\"))";
+    let results = Parser::new(input.chars())
+        .map(|e| state.eval(&e.ok().unwrap()))
+        .collect::<Vec<_>>();
+    assert_eq!(Ok(n_keyword!["done"]), results[1]);
+    assert_eq!(Ok(n_number![12.]), results[3]);
+    assert_eq!(Ok(n_list![vec![n_number![1.], n_number![2.], n_number![3.]]]), results[5]);
+    assert_eq!(Ok(n_string!["a!a!"]), results[7]);
+    assert_eq!(Err(IncorrectNumberOfArgumentsError(n_call!["f", vec![]])), results[8]);
+    assert_eq!(Err(IncorrectTypeOfArgumentError(n_call!["g", vec![n_string!["a"]]])), results[9]);
+}
+
+#[test]
+fn test_compile_builtin_calls_with_locals_to_bytecode() {
+    let ref mut state = State::new("user".to_string());
+    let input = "(let [xs [1 2] s 'a] (with-out-str (print (str (first xs) (get xs 1) s))))
+                 (with-seed 7 (let [n 3] (rand-int n)))
+                 (let [s 'a] (inc s))
+                 (doc inc)";
+    let exprs = Parser::new(input.chars()).map(|e| e.ok().unwrap()).collect::<Vec<_>>();
+    let ops = exprs.iter().map(|e| {
+        let compiled = state.compile(e).ok().unwrap();
+        state.compile_chunk(&compiled).ok().unwrap().code.iter().filter(|op| {
+            if let Op::Eval(..) = **op { true } else { false }
+        }).count()
+    }).collect::<Vec<_>>();
+    assert_eq!(vec![0, 0, 0, 1], ops);
+    assert_eq!(Ok(n_string!["12a"]), state.eval(&exprs[0]));
+    assert!(state.eval(&exprs[1]).is_ok());
+    assert_eq!(Err(IncorrectTypeOfArgumentError(n_symbol!["a"])), state.eval(&exprs[2]));
+}
+
+#[test]
+fn test_eval_builtins_with_evaluated_args() {
+    let ref mut state = State::new("user".to_string());
+    let input = "(let [x \"a\"] (inc x))
+                 (apply inc [\"a\"])
+                 (apply + 1 [2 3])
+                 (with-out-str)
+                 (with-seed 7)
+                 ((fn [] (with-out-str)))
+                 ((fn [] (with-seed 7)))";
+    let results = Parser::new(input.chars())
+        .map(|e| state.eval(&e.ok().unwrap()))
+        .collect::<Vec<_>>();
+    assert_eq!(Err(IncorrectTypeOfArgumentError(n_string!["a"])), results[0]);
+    assert_eq!(Err(IncorrectTypeOfArgumentError(n_string!["a"])), results[1]);
+    assert_eq!(Ok(n_number![6.]), results[2]);
+    assert_eq!(Ok(n_string![""]), results[3]);
+    assert_eq!(Ok(n_nil![]), results[4]);
+    assert_eq!(Ok(n_string![""]), results[5]);
+    assert_eq!(Ok(n_nil![]), results[6]);
+}
+
+#[test]
+fn test_eval_do_when_cond_and_case_forms() {
+    let ref mut state = State::new("user".to_string());
+    let input = r#"(do) (do 1 2) (if false 1) (when nil 1) (when-not nil 1 2)
+                   (defn sign [n] (cond (< n 0) :neg (= n 0) :zero :else :pos))
+                   [(sign -2) (sign 0) (sign 3)]
+                   (defn size [n] (case n 0 :none (1 2) :few "x" :str nil :nil :many))
+                   [(size 0) (size 2) (size "x") (size nil) (size 7)]
+                   (defn named [n] (str (case n 1 "one" (2 3) "few") (when (> n 1) "!")))
+                   (named 3)
+                   (named 4)
+                   (case 1 (1 2) :a 2 :b)
+                   (cond :a)"#;
+    let results = Parser::new(input.chars())
+        .map(|e| state.eval(&e.ok().unwrap()))
+        .collect::<Vec<_>>();
+    assert_eq!(&[Ok(n_nil![]), Ok(n_number![2.]), Ok(n_nil![]), Ok(n_nil![]), Ok(n_number![2.])],
+               &results[..5]);
+    assert_eq!(Ok(n_vec![vec![n_keyword!["neg"], n_keyword!["zero"], n_keyword!["pos"]]]), results[6]);
+    assert_eq!(Ok(n_vec![vec![n_keyword!["none"], n_keyword!["few"], n_keyword!["str"],
+                              n_keyword!["nil"], n_keyword!["many"]]]),
+               results[8]);
+    assert_eq!(Ok(n_string!["few!"]), results[10]);
+    assert_eq!(Err(IncorrectTypeOfArgumentError(n_number![4.])), results[11]);
+    assert_eq!(Err(IncorrectTypeOfArgumentError(n_number![2.])), results[12]);
+    assert_eq!(Err(IncorrectNumberOfArgumentsError(n_list![vec![n_symbol!["cond"], n_keyword!["a"]]])),
+               results[13]);
+}
+
+#[test]
+fn test_eval_and_or_not_short_circuit() {
+    let ref mut state = State::new("user".to_string());
+    let input = r#"(and) (or) (and 1 nil (undefined)) (or false 2 (undefined)) (and 1 2) (or nil false)
+                   (not nil) (not 0) (xor true nil) (xor 1 2) (xor 1 2 3)
+                   (defn guard [x] (and (some? x) (or (get x :v) :none)))
+                   [(guard nil) (guard {:v 1}) (guard {})]
+                   (apply or [nil 3]) (apply and [1 false])
+                   ((some-fn nil? (fn [x] (get x :v))) {:v 5} {})
+                   ((some-fn nil?) 1 2)
+                   ((every-pred some? (fn [x] (< 0 x))) 1 2)
+                   ((every-pred some? (fn [x] (< 0 x))) 1 -2)
+                   (some (fn [x] (and (< 1 x) x)) [1 2 3]) (every? nil? [nil nil]) (every? not [])"#;
+    let results = Parser::new(input.chars())
+        .map(|e| state.eval(&e.ok().unwrap()).ok().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(vec![n_bool![true], n_nil![], n_nil![], n_number![2.], n_number![2.], n_bool![false],
+                    n_bool![true], n_bool![false], n_bool![true], n_bool![false], n_bool![true]],
+               results[..11].to_vec());
+    assert_eq!(n_vec![vec![n_bool![false], n_number![1.], n_keyword!["none"]]], results[12]);
+    assert_eq!(vec![n_number![3.], n_bool![false], n_number![5.], n_nil![], n_bool![true],
+                    n_bool![false], n_number![2.], n_bool![true], n_bool![true]],
+               results[13..].to_vec());
+}
+
+#[test]
+fn test_eval_some_fn_and_every_pred_of_builtins() {
+    let ref mut state = State::new("user".to_string());
+    let input = r#"((some-fn not nil?) 1 false)
+                   ((some-fn :a :b) {:b 2} {:a 1})
+                   ((every-pred some? not) false)
+                   (some (every-pred some? pos?) [nil -1 3])
+                   (every? (some-fn nil? #{1 2}) [nil 2 1])
+                   (apply xor [true false false])
+                   (let [p (some-fn not)] (p 1 2))
+                   (def odd-and-small? (every-pred odd? (fn [x] (< x 10))))
+                   [(odd-and-small? 3 5) (odd-and-small? 3 11)]
+                   (str (some-fn not))"#;
+    let results = Parser::new(input.chars())
+        .map(|e| state.eval(&e.ok().unwrap()).ok().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(vec![n_bool![true], n_number![1.], n_bool![true], n_bool![true], n_bool![true],
+                    n_bool![true], n_nil![]],
+               results[..7].to_vec());
+    assert_eq!(n_vec![vec![n_bool![true], n_bool![false]]], results[8]);
+    assert_eq!(n_string!["#<some-fn #<builtin not>>"], results[9]);
+    let expr = Parser::new("((every-pred pos?) 1 :a)".chars()).next().unwrap().ok().unwrap();
+    match state.eval(&expr).err().unwrap() {
+        IncorrectTypeOfArgumentError(..) => {},
+        e => panic!("unexpected {:?}", e),
+    }
+}
+
+#[test]
+fn test_compile_case_and_cond() {
+    let ref mut state = State::new("user".to_string());
+    let input = "(fn [a] (cond a (when-not a 1) :else (case a (1 2) 3 4)))";
+    let expr = Parser::new(input.chars()).next().unwrap().ok().unwrap();
+    let clauses = vec![(n_list![vec![n_number![1.], n_number![2.]]], n_number![3.])];
+    let case = Node::Case(nodes::Case::new(n_symbol!["a"], clauses, Some(n_number![4.])).ok().unwrap());
+    let when_not = n_call!["if", vec![n_symbol!["a"], n_nil![], n_call!["do", vec![n_number![1.]]]]];
+    let expected = n_fn![[n_symbol!["a"]], [n_call!["if", vec![n_symbol!["a"], when_not, case]]]];
+    assert_eq!(expected, state.compile(&expr).ok().unwrap());
+}
+
+#[test]
+fn test_eval_globals_resolve_to_vars() {
+    let ref mut state = State::new("user".to_string());
+    let input = "(def x 1)
+                 (defn get-x [] x)
+                 (def x 2)
+                 (get-x)
+                 (in-ns 'other)
+                 (def x 3)
+                 (user/get-x)
+                 (defn later [] (not-yet))
+                 (defn not-yet [] :found)
+                 (later)";
+    let results = Parser::new(input.chars())
+        .map(|e| state.eval(&e.ok().unwrap()).ok().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(n_number![2.], results[3]);
+    assert_eq!(n_number![2.], results[6]);
+    assert_eq!(n_keyword!["found"], results[9]);
+}
+
+#[test]
+fn test_eval_globals_unbound_when_compiled_resolve_once_bound() {
+    let ref mut state = State::new("user".to_string());
+    let input = "(defn later [] not-yet)
+                 (later)
+                 (var not-yet)
+                 (def not-yet 1)
+                 (later)
+                 (def not-yet 2)
+                 (later)";
+    let results = Parser::new(input.chars())
+        .map(|e| state.eval(&e.ok().unwrap()))
+        .collect::<Vec<_>>();
+    assert_eq!(Err(ResolveError("not-yet".to_string())), results[1]);
+    assert_eq!(Err(ResolveError("not-yet".to_string())), results[2]);
+    assert_eq!(Ok(n_number![1.]), results[4]);
+    assert_eq!(Ok(n_number![2.]), results[6]);
+}
+
+#[test]
+fn test_eval_random_builtin_fns_are_reproducible() {
+    let ref mut state = State::new("user".to_string());
+    let input = "(set-seed! 42)
+                 [(rand) (rand 10) (rand-int 6) (rand-nth [:a :b :c]) (shuffle '(1 2 3 4 5))]
+                 (set-seed! 42)
+                 [(rand) (rand 10) (rand-int 6) (rand-nth [:a :b :c]) (shuffle '(1 2 3 4 5))]
+                 (with-seed 7 (rand) (rand))
+                 (with-seed 7 (rand) (rand))
+                 (set-seed! 1)
+                 (rand)
+                 (set-seed! 1)
+                 (with-seed 3 (rand))
+                 (rand)
+                 (rand-nth [])";
+    let results = Parser::new(input.chars())
+        .map(|e| state.eval(&e.ok().unwrap()))
+        .collect::<Vec<_>>();
+    assert_eq!(results[1], results[3]);
+    assert_eq!(results[4], results[5]);
+    assert_eq!(results[7], results[10]);
+    assert!(results[7] != results[9]);
+    assert_eq!(Err(IncorrectTypeOfArgumentError(n_vec![vec![]])), results[11]);
+}
+
+#[test]
+fn test_eval_inst_builtin_fns() {
+    let ref mut state = State::new("user".to_string());
+    let input = r#"(def t #inst "2026-10-17T12:00:00Z")
+                   (inst-add t (duration 1.5 :hours) (duration -1 :days))
+                   (inst-diff t (parse-inst "2026-10-17T13:00:00+02:00"))
+                   (format-inst t "%a %d %b %Y, %I:%M:%S.%f %p %Z (day %j, %%)")
+                   [(< (parse-inst "2026") t) (>= t t)
+                    (> (parse-inst "2027") t (parse-inst "1999-12-31"))]
+                   (defn before? [a b] (< a b))
+                   (before? t (inst-add t 1))
+                   [(compare t (inst-add t -1)) (inst? t) (inst? 1)
+                    (inst-ms #inst "1970-01-01T00:00:01Z")]
+                   (= t (parse-inst (format-inst t "%FT%T.%fZ")))
+                   (< (inst-add (now) -1) (now))
+                   (< t 1)
+                   (parse-inst "2026-10-17T25:00")
+                   (format-inst t "%Q")
+                   (inst-add t 0.5)
+                   (duration 1 :fortnights)"#;
+    let results = Parser::new(input.chars())
+        .map(|e| state.eval(&e.ok().unwrap()))
+        .collect::<Vec<_>>();
+    assert_eq!(Ok(n_inst![1_792_238_400_000 + 5_400_000 - 86_400_000]), results[1]);
+    assert_eq!(Ok(n_number![3_600_000.]), results[2]);
+    assert_eq!(Ok(n_string!["Sat 17 Oct 2026, 12:00:00.000 PM UTC (day 290, %)"]), results[3]);
+    assert_eq!(Ok(n_vec![vec![n_bool![true], n_bool![true], n_bool![true]]]), results[4]);
+    assert_eq!(Ok(n_bool![true]), results[6]);
+    assert_eq!(Ok(n_vec![vec![n_number![1.], n_bool![true], n_bool![false], n_number![1000.]]]),
+               results[7]);
+    assert_eq!(Ok(n_bool![true]), results[8]);
+    assert_eq!(Ok(n_bool![true]), results[9]);
+    assert_eq!(Err(IncorrectTypeOfArgumentError(n_number![1.])), results[10]);
+    assert_eq!(Err(IncorrectTypeOfArgumentError(n_string!["2026-10-17T25:00"])), results[11]);
+    assert_eq!(Err(IncorrectTypeOfArgumentError(n_string!["%Q"])), results[12]);
+    assert_eq!(Err(IncorrectTypeOfArgumentError(n_number![0.5])), results[13]);
+    assert_eq!(Err(IncorrectTypeOfArgumentError(n_keyword!["fortnights"])), results[14]);
+}
+
+#[test]
+fn test_eval_env_and_sh_builtin_fns() {
+    let ref mut state = State::new("user".to_string());
+    let input = r#"(setenv "LUST_TEST_GREETING" "hi")
+                   [(getenv "LUST_TEST_GREETING") (get (getenv) "LUST_TEST_GREETING")]
+                   (sh "sh" "-c" (str "read l; echo $LUST_TEST_GREETING $l $X in $(pwd);"
+                                      " echo oops >&2; exit 3")
+                       :in (str "there" \newline) :env {"X" "!"} :dir "/")
+                   (:out (sh "sh" "-c" "echo ${LUST_TEST_GREETING-unset}"
+                             :env {:LUST_TEST_GREETING nil}))
+                   (setenv "LUST_TEST_GREETING" nil)
+                   (getenv "LUST_TEST_GREETING")
+                   (= (cwd) (:out (sh "sh" "-c" "printf %s $(pwd -P)")))
+                   (sh "lust-no-such-program")
+                   (sh "true" :dir)
+                   (sh "true" :timeout 1)
+                   (setenv "A=B" "c")"#;
+    let results = Parser::new(input.chars())
+        .map(|e| state.eval(&e.ok().unwrap()))
+        .collect::<Vec<_>>();
+    assert_eq!(Ok(n_vec![vec![n_string!["hi"], n_string!["hi"]]]), results[1]);
+    assert_eq!(Ok(n_map![vec![(n_keyword!["exit"], n_number![3.]),
+                              (n_keyword!["out"], n_string!["hi there ! in /\n"]),
+                              (n_keyword!["err"], n_string!["oops\n"])]]),
+               results[2]);
+    assert_eq!(Ok(n_string!["unset\n"]), results[3]);
+    assert_eq!(Ok(n_nil![]), results[5]);
+    assert_eq!(Ok(n_bool![true]), results[6]);
+    match results[7] {
+        Err(IoError(ref e)) => {
+            assert!(e.starts_with(r#"Unable to run "lust-no-such-program": "#), "{}", e)
+        },
+        ref other => panic!("unexpected {:?}", other),
+    }
+    let expected = n_call!["sh", vec![n_string!["true"], n_keyword!["dir"]]];
+    assert_eq!(Err(IncorrectNumberOfArgumentsError(expected)), results[8]);
+    assert_eq!(Err(IncorrectTypeOfArgumentError(n_number![1.])), results[9]);
+    assert_eq!(Err(IncorrectTypeOfArgumentError(n_string!["A=B"])), results[10]);
+}
+
+struct Highest;
+
+impl Rng for Highest {
+    fn next_u64(&mut self) -> u64 {
+        u64::max_value()
+    }
+}
+
+#[test]
+fn test_eval_random_builtin_fns_with_injected_rng() {
+    let ref mut state = State::new("user".to_string());
+    state.set_rng(Highest);
+    let input = "[(rand-int 10) (rand-nth [:a :b :c]) (shuffle [1 2 3]) (< 0.99 (rand) 1)]";
+    let expr = Parser::new(input.chars()).next().unwrap().ok().unwrap();
+    assert_eq!(n_vec![vec![n_number![9.], n_keyword!["c"],
+                           n_vec![vec![n_number![1.], n_number![2.], n_number![3.]]], n_bool![true]]],
+               state.eval(&expr).ok().unwrap());
+}
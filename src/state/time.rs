@@ -0,0 +1,148 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+use super::{State, EvalResult};
+use super::error::EvalError::*;
+use super::error::EvalError;
+use super::math::integer;
+use ast::{Node, nodes};
+
+/// Milliseconds in one `unit` of a duration.
+fn unit_millis(unit: &str) -> Option<f64> {
+    match unit {
+        "ms" => Some(1.),
+        "seconds" => Some(1000.),
+        "minutes" => Some(60_000.),
+        "hours" => Some(3_600_000.),
+        "days" => Some(86_400_000.),
+        "weeks" => Some(604_800_000.),
+        _ => None,
+    }
+}
+
+/// The value of `arg` as an instant.
+fn inst_arg(arg: &Node) -> Result<&nodes::Inst, EvalError> {
+    match *arg {
+        Node::Inst(ref i) => Ok(i),
+        _ => Err(IncorrectTypeOfArgumentError(arg.clone())),
+    }
+}
+
+/// The value of `arg` as a duration, an integral number of milliseconds.
+fn millis_arg(arg: &Node) -> Result<i64, EvalError> {
+    match *arg {
+        Node::Number(ref n) => integer(n.value())
+            .ok_or_else(|| IncorrectTypeOfArgumentError(arg.clone())),
+        _ => Err(IncorrectTypeOfArgumentError(arg.clone())),
+    }
+}
+
+impl State {
+    pub(super) fn builtin_now(&mut self, args: &[Node], node: &Node) -> EvalResult {
+        if args.is_empty() {
+            let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+            let millis = now.as_secs() as i64 * 1000 + (now.subsec_nanos() / 1_000_000) as i64;
+            Ok(n_inst![millis])
+        } else {
+            Err(IncorrectNumberOfArgumentsError(node.clone()))
+        }
+    }
+
+    pub(super) fn builtin_is_inst(&mut self, args: &[Node], node: &Node) -> EvalResult {
+        if args.len() == 1 {
+            if let Node::Inst(..) = args[0] {
+                Ok(n_bool![true])
+            } else {
+                Ok(n_bool![false])
+            }
+        } else {
+            Err(IncorrectNumberOfArgumentsError(node.clone()))
+        }
+    }
+
+    pub(super) fn builtin_inst_ms(&mut self, args: &[Node], node: &Node) -> EvalResult {
+        if args.len() == 1 {
+            let inst = try!(inst_arg(&args[0]));
+            Ok(n_number![inst.millis() as f64])
+        } else {
+            Err(IncorrectNumberOfArgumentsError(node.clone()))
+        }
+    }
+
+    pub(super) fn builtin_parse_inst(&mut self, args: &[Node], node: &Node) -> EvalResult {
+        if args.len() == 1 {
+            match args[0] {
+                Node::String(ref s) => {
+                    nodes::Inst::parse(s.value())
+                        .map(Node::Inst)
+                        .ok_or_else(|| IncorrectTypeOfArgumentError(args[0].clone()))
+                },
+                _ => {
+                    Err(IncorrectTypeOfArgumentError(args[0].clone()))
+                },
+            }
+        } else {
+            Err(IncorrectNumberOfArgumentsError(node.clone()))
+        }
+    }
+
+    pub(super) fn builtin_format_inst(&mut self, args: &[Node], node: &Node) -> EvalResult {
+        if args.len() == 2 {
+            let inst = try!(inst_arg(&args[0]));
+            match args[1] {
+                Node::String(ref s) => {
+                    inst.format(s.value())
+                        .map(|s| n_string![s])
+                        .ok_or_else(|| IncorrectTypeOfArgumentError(args[1].clone()))
+                },
+                _ => {
+                    Err(IncorrectTypeOfArgumentError(args[1].clone()))
+                },
+            }
+        } else {
+            Err(IncorrectNumberOfArgumentsError(node.clone()))
+        }
+    }
+
+    pub(super) fn builtin_duration(&mut self, args: &[Node], node: &Node) -> EvalResult {
+        if args.len() == 2 {
+            let n = match args[0] {
+                Node::Number(ref n) => n.value(),
+                _ => return Err(IncorrectTypeOfArgumentError(args[0].clone())),
+            };
+            match args[1] {
+                Node::Keyword(ref k) if k.ns().is_none() => {
+                    unit_millis(k.name())
+                        .map(|ms| n_number![(n * ms).round()])
+                        .ok_or_else(|| IncorrectTypeOfArgumentError(args[1].clone()))
+                },
+                _ => {
+                    Err(IncorrectTypeOfArgumentError(args[1].clone()))
+                },
+            }
+        } else {
+            Err(IncorrectNumberOfArgumentsError(node.clone()))
+        }
+    }
+
+    pub(super) fn builtin_inst_add(&mut self, args: &[Node], node: &Node) -> EvalResult {
+        if args.len() >= 1 {
+            let mut millis = try!(inst_arg(&args[0])).millis();
+            for a in &args[1..] {
+                let ms = try!(millis_arg(a));
+                millis = try!(millis.checked_add(ms)
+                              .ok_or_else(|| IncorrectTypeOfArgumentError(a.clone())));
+            }
+            Ok(n_inst![millis])
+        } else {
+            Err(IncorrectNumberOfArgumentsError(node.clone()))
+        }
+    }
+
+    pub(super) fn builtin_inst_diff(&mut self, args: &[Node], node: &Node) -> EvalResult {
+        if args.len() == 2 {
+            let (a, b) = (try!(inst_arg(&args[0])), try!(inst_arg(&args[1])));
+            Ok(n_number![(a.millis() as f64) - (b.millis() as f64)])
+        } else {
+            Err(IncorrectNumberOfArgumentsError(node.clone()))
+        }
+    }
+}
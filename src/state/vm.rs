@@ -0,0 +1,400 @@
+use std::cmp::Ordering;
+use std::rc::Rc;
+use super::{State, EvalResult, is_arity_matched};
+use super::error::EvalError::*;
+use super::error::EvalError;
+use super::map::call_lookup;
+use super::native::{NATIVES, native};
+use ast::{Node, nodes};
+use chunk::{Addr, Arith, BuildKind, Chunk, Guard, Op};
+
+struct Frame {
+    chunk: Rc<Chunk>,
+    ip: usize,
+    base: usize,
+    captures: Rc<Vec<Node>>,
+}
+
+/// Turns the args on top of the stack starting at `base` into the locals of `chunk`,
+/// packing the variadic ones into a list.
+fn enter(chunk: &Chunk, stack: &mut Vec<Node>, base: usize) {
+    if chunk.variadic {
+        let rest = stack.split_off(base + chunk.required);
+        stack.push(n_list![rest]);
+    }
+    stack.resize(base + chunk.slots, n_nil![]);
+}
+
+fn load(addr: Addr, frame: &Frame, stack: &[Node]) -> Node {
+    match addr {
+        Addr::Local(i) => stack[frame.base + i].clone(),
+        Addr::Capture(i) => frame.captures[i].clone(),
+    }
+}
+
+fn arg_of(node: &Node, i: usize) -> Node {
+    match *node {
+        Node::Call(ref c) => c.args()[i].clone(),
+        _ => node.clone(),
+    }
+}
+
+/// Applies `op` to evaluated `args`. The ordering ops compare numbers, or instants. Errors
+/// report the call `node`, or `report` of the index of the wrong arg.
+pub(super) fn arith<F: Fn(usize) -> Node>(op: Arith, args: &[Node], node: &Node,
+                                          report: F) -> EvalResult {
+    let number = |i: usize| {
+        match args[i] {
+            Node::Number(ref n) => Ok(n.value()),
+            _ => Err(IncorrectTypeOfArgumentError(report(i))),
+        }
+    };
+    match op {
+        Arith::Add | Arith::Mul => {
+            let mut result = if op == Arith::Add { 0_f64 } else { 1_f64 };
+            for i in 0..args.len() {
+                let value = try!(number(i));
+                if op == Arith::Add { result += value } else { result *= value }
+            }
+            Ok(n_number![result])
+        },
+        Arith::Sub | Arith::Div => {
+            let value = try!(number(0));
+            if op == Arith::Div && args.len() == 1 && value == 0. {
+                return Err(DivideByZeroError(node.clone()))
+            }
+            let mut result = match (op, args.len()) {
+                (Arith::Sub, 1) => -value,
+                (Arith::Div, 1) => 1. / value,
+                _ => value,
+            };
+            for i in 1..args.len() {
+                let value = try!(number(i));
+                if op == Arith::Div && value == 0. {
+                    return Err(DivideByZeroError(node.clone()))
+                }
+                if op == Arith::Sub { result -= value } else { result /= value }
+            }
+            Ok(n_number![result])
+        },
+        Arith::Eq => {
+            Ok(n_bool![args[1..].iter().all(|a| *a == args[0])])
+        },
+        Arith::Lt | Arith::Gt | Arith::Le | Arith::Ge => {
+            match args[0] {
+                Node::Number(..) | Node::Inst(..) => {},
+                _ => return Err(IncorrectTypeOfArgumentError(report(0))),
+            }
+            for i in 1..args.len() {
+                let ordering = match (&args[i - 1], &args[i]) {
+                    (&Node::Number(ref a), &Node::Number(ref b)) => a.value().partial_cmp(&b.value()),
+                    (&Node::Inst(ref a), &Node::Inst(ref b)) => Some(a.cmp(b)),
+                    _ => return Err(IncorrectTypeOfArgumentError(report(i))),
+                };
+                let holds = match (op, ordering) {
+                    (_, None) => false,
+                    (Arith::Lt, Some(o)) => o == Ordering::Less,
+                    (Arith::Gt, Some(o)) => o == Ordering::Greater,
+                    (Arith::Le, Some(o)) => o != Ordering::Greater,
+                    (_, Some(o)) => o != Ordering::Less,
+                };
+                if !holds {
+                    return Ok(n_bool![false])
+                }
+            }
+            Ok(n_bool![true])
+        },
+    }
+}
+
+impl State {
+    /// Calls a fn or macro body with already evaluated `args`.
+    pub(super) fn call_fn(&mut self, f: &nodes::Fn, args: Vec<Node>) -> EvalResult {
+        let code = match f.code() {
+            Some(code) => code.clone(),
+            None => Rc::new(try!(self.compile_fn(f))),
+        };
+        self.run(code, args, f.captures().clone())
+    }
+
+    /// Calls a fn, a builtin or a combined pred, or a keyword, map, set or vector as a lookup
+    /// fn, with already evaluated `args`. Errors report `node`, the call being made.
+    pub(super) fn call_value(&mut self, f: &Node, args: Vec<Node>, node: &Node) -> EvalResult {
+        match *f {
+            Node::Fn(ref f) => {
+                if !is_arity_matched(f.params(), args.len()) {
+                    return Err(IncorrectNumberOfArgumentsError(node.clone()))
+                }
+                self.call_fn(f, args)
+            },
+            Node::Builtin(ref s) => {
+                match native(s.name()) {
+                    Some(b) => (NATIVES[b].1)(self, &args, node),
+                    None => Err(IncorrectTypeOfArgumentError(arg_of(node, 0))),
+                }
+            },
+            Node::Preds(ref p) => {
+                self.call_preds(p, args, node)
+            },
+            Node::Keyword(..) | Node::Map(..) | Node::Set(..) | Node::Vector(..) => {
+                call_lookup(f, &args, node)
+            },
+            _ => {
+                Err(IncorrectTypeOfArgumentError(arg_of(node, 0)))
+            },
+        }
+    }
+
+    /// Executes `chunk` with `args` as its first locals.
+    pub(super) fn run(&mut self, chunk: Rc<Chunk>, args: Vec<Node>, captures: Rc<Vec<Node>>) -> EvalResult {
+        let mut stack = args;
+        enter(&chunk, &mut stack, 0);
+        let mut frames: Vec<Frame> = vec![];
+        let mut frame = Frame {
+            chunk: chunk,
+            ip: 0,
+            base: 0,
+            captures: captures,
+        };
+
+        loop {
+            let op = frame.chunk.code[frame.ip];
+            frame.ip += 1;
+            match op {
+                Op::Const(i) => {
+                    stack.push(frame.chunk.consts[i].clone())
+                },
+                Op::LoadLocal(i) => {
+                    let value = stack[frame.base + i].clone();
+                    stack.push(value)
+                },
+                Op::StoreLocal(i) => {
+                    let value = stack.pop().unwrap();
+                    stack[frame.base + i] = value
+                },
+                Op::LoadCapture(i) => {
+                    stack.push(frame.captures[i].clone())
+                },
+                Op::LoadVar(i) => {
+                    let (ref s, ref var) = frame.chunk.vars[i];
+                    match *var.borrow() {
+                        Some(ref value) => stack.push(value.clone()),
+                        None => return Err(ResolveError(s.name().clone())),
+                    }
+                },
+                Op::LoadGlobal(i) => {
+                    let (ref s, ref found) = frame.chunk.globals[i];
+                    if found.borrow().is_none() {
+                        *found.borrow_mut() = self.find_var(s);
+                    }
+                    let value = found.borrow().as_ref().and_then(|var| var.borrow().clone());
+                    match value {
+                        Some(value) => stack.push(value),
+                        None => return Err(ResolveError(s.name().clone())),
+                    }
+                },
+                Op::Def(i) => {
+                    let value = stack.pop().unwrap();
+                    let value = try!(self.define(&frame.chunk.defs[i], value));
+                    stack.push(value)
+                },
+                Op::Closure(i) => {
+                    let closure = match frame.chunk.fns[i] {
+                        Node::Fn(ref f) | Node::Macro(ref f) => {
+                            let captures = f.code().unwrap().captures.iter()
+                                .map(|&addr| load(addr, &frame, &stack))
+                                .collect();
+                            let f = f.clone().with_captures(captures);
+                            if let Node::Fn(..) = frame.chunk.fns[i] { Node::Fn(f) } else { Node::Macro(f) }
+                        },
+                        ref node => {
+                            return Err(DispatchError(node.clone()))
+                        },
+                    };
+                    stack.push(closure)
+                },
+                Op::Build(i) => {
+                    let build = &frame.chunk.builds[i];
+                    let at = stack.len() - build.spliced.len();
+                    let mut items = vec![];
+                    for (value, spliced) in stack.drain(at..).zip(&build.spliced) {
+                        match (value, spliced) {
+                            (Node::List(ref l), &Some(_)) | (Node::Vector(ref l), &Some(_)) => {
+                                items.extend(l.into_iter().cloned())
+                            },
+                            (_, &Some(ref form)) => {
+                                return Err(IncorrectTypeOfArgumentError(form.clone()))
+                            },
+                            (value, &None) => {
+                                items.push(value)
+                            },
+                        }
+                    }
+                    stack.push(match build.kind {
+                        BuildKind::List => {
+                            n_list![items]
+                        },
+                        BuildKind::Vector(ref meta) => {
+                            n_vec![items].with_meta(meta.clone()).unwrap()
+                        },
+                        BuildKind::Map(ref meta) => {
+                            let mut entries = vec![];
+                            let mut items = items.into_iter();
+                            while let (Some(k), Some(v)) = (items.next(), items.next()) {
+                                entries.push((k, v))
+                            }
+                            Node::Map(nodes::Map::new(entries).with_meta(meta.clone()))
+                        },
+                        BuildKind::Set(ref meta) => {
+                            Node::Set(nodes::Set::new(items).with_meta(meta.clone()))
+                        },
+                    })
+                },
+                Op::Arith(op, argc, i) => {
+                    let at = stack.len() - argc;
+                    let node = &frame.chunk.consts[i];
+                    let result = try!(arith(op, &stack[at..], node, |i| arg_of(node, i)));
+                    stack.truncate(at);
+                    stack.push(result)
+                },
+                Op::Jump(target) => {
+                    frame.ip = target
+                },
+                Op::JumpIfFalse(target) => {
+                    if !stack.pop().unwrap().as_bool() {
+                        frame.ip = target
+                    }
+                },
+                Op::JumpIfFalseOrPop(target) => {
+                    if stack.last().unwrap().as_bool() {
+                        stack.pop();
+                    } else {
+                        frame.ip = target
+                    }
+                },
+                Op::JumpIfTrueOrPop(target) => {
+                    if stack.last().unwrap().as_bool() {
+                        frame.ip = target
+                    } else {
+                        stack.pop();
+                    }
+                },
+                Op::Not => {
+                    let value = !stack.pop().unwrap().as_bool();
+                    stack.push(n_bool![value])
+                },
+                Op::Pop => {
+                    stack.pop();
+                },
+                Op::MacroGuard(i, target) => {
+                    if stack.last().map_or(false, Node::is_macro) {
+                        let m = stack.pop().unwrap();
+                        let value = try!(self.run_guarded(&frame.chunk.guards[i], &m, &frame, &stack));
+                        stack.push(value);
+                        frame.ip = target
+                    }
+                },
+                Op::Call(argc, i) => {
+                    let base = stack.len() - argc;
+                    let (code, captures) = match stack[base - 1] {
+                        Node::Fn(ref f) => {
+                            let code = match f.code() {
+                                Some(code) => code.clone(),
+                                None => Rc::new(try!(self.compile_fn(f))),
+                            };
+                            (code, f.captures().clone())
+                        },
+                        Node::Builtin(..) | Node::Preds(..) | Node::Keyword(..) | Node::Map(..) |
+                        Node::Set(..) | Node::Vector(..) => {
+                            let args = stack.split_off(base);
+                            let f = stack.pop().unwrap();
+                            stack.push(try!(self.call_value(&f, args, &frame.chunk.consts[i])));
+                            continue
+                        },
+                        _ => {
+                            return Err(IncorrectTypeOfArgumentError(frame.chunk.consts[i].clone()))
+                        },
+                    };
+                    if argc < code.required || (!code.variadic && argc != code.required) {
+                        return Err(IncorrectNumberOfArgumentsError(frame.chunk.consts[i].clone()))
+                    }
+                    enter(&code, &mut stack, base);
+                    let callee = Frame {
+                        chunk: code,
+                        ip: 0,
+                        base: base,
+                        captures: captures,
+                    };
+                    frames.push(::std::mem::replace(&mut frame, callee));
+                },
+                Op::Builtin(argc, b, i) => {
+                    let at = stack.len() - argc;
+                    let result = try!((NATIVES[b].1)(self, &stack[at..], &frame.chunk.consts[i]));
+                    stack.truncate(at);
+                    stack.push(result)
+                },
+                Op::Eval(i) => {
+                    stack.push(try!(self.eval_compiled(&frame.chunk.consts[i])))
+                },
+                Op::Case(i) => {
+                    let value = stack.pop().unwrap();
+                    let dispatch = &frame.chunk.dispatches[i];
+                    match dispatch.case.branch(&value) {
+                        Some(b) => frame.ip = dispatch.targets[b],
+                        None if dispatch.case.default().is_some() => {
+                            frame.ip = *dispatch.targets.last().unwrap()
+                        },
+                        None => return Err(IncorrectTypeOfArgumentError(value)),
+                    }
+                },
+                Op::Return => {
+                    let result = stack.pop().unwrap();
+                    match frames.pop() {
+                        Some(caller) => {
+                            stack.truncate(frame.base - 1);
+                            stack.push(result);
+                            frame = caller
+                        },
+                        None => {
+                            return Ok(result)
+                        },
+                    }
+                },
+            }
+        }
+    }
+
+    /// Runs the expansion of a guarded call of the macro `m`, expanding and compiling it into
+    /// a fn of the locals the call refers to on the first run only, and again whenever the
+    /// global has since been redefined to another macro.
+    fn run_guarded(&mut self, guard: &Guard, m: &Node, frame: &Frame, stack: &[Node]) -> EvalResult {
+        let cached = match *guard.expansion.borrow() {
+            Some((ref expanded, ref code)) if expanded == m => Some(code.clone()),
+            _ => None,
+        };
+        let code = match cached {
+            Some(code) => code,
+            None => {
+                let code = Rc::new(try!(self.compile_expansion(guard, m)));
+                *guard.expansion.borrow_mut() = Some((m.clone(), code.clone()));
+                code
+            },
+        };
+        let args = guard.locals.iter().map(|&(_, addr)| load(addr, frame, stack)).collect();
+        self.run(code, args, Rc::new(vec![]))
+    }
+
+    fn compile_expansion(&mut self, guard: &Guard, m: &Node) -> Result<Chunk, EvalError> {
+        match (&guard.node, m) {
+            (&Node::Call(ref c), &Node::Macro(ref f)) => {
+                let expansion = try!(self.apply_macro(f, &guard.node, c.args(), None));
+                let compiled = try!(self.compile(&expansion));
+                let params = guard.locals.iter().map(|&(ref s, _)| Node::Symbol(s.clone())).collect();
+                self.compile_fn(&nodes::Fn::new(params, vec![compiled]))
+            },
+            _ => {
+                Err(DispatchError(guard.node.clone()))
+            },
+        }
+    }
+}
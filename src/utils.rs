@@ -14,3 +14,17 @@ pub fn format_vec(v: &[Node]) -> String {
     }
     a
 }
+
+pub fn format_for_print(node: &Node) -> String {
+    match *node {
+        Node::String(ref s) => {
+            s.value().clone()
+        },
+        Node::Char(ref c) => {
+            c.value().to_string()
+        },
+        _ => {
+            format!("{}", node)
+        },
+    }
+}